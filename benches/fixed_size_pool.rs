@@ -0,0 +1,108 @@
+//! Compares allocating and freeing uniform allocations through a warmed up `FixedSizePool` and
+//! through `Allocator::allocate_memory` from a custom pool.
+//!
+//! Run with `cargo bench --bench fixed_size_pool`.
+
+#[allow(dead_code)]
+#[path = "../tests/test_support/mod.rs"]
+mod test_support;
+
+use std::time::{Duration, Instant};
+use test_support::TestHarness;
+
+const ELEMENT_SIZE: u64 = 256;
+const ALIGNMENT: u64 = 256;
+const ELEMENTS_PER_BLOCK: usize = 1024;
+const LIVE_COUNT: usize = 1000;
+const ROUNDS: usize = 100;
+
+fn time(f: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+fn main() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            u32::MAX,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+
+    let mut fixed = unsafe {
+        vk_mem::FixedSizePool::new(
+            &allocator,
+            memory_type_index,
+            ELEMENT_SIZE,
+            ALIGNMENT,
+            ELEMENTS_PER_BLOCK,
+        )
+        .unwrap()
+    };
+    let cycle_fixed = |fixed: &mut vk_mem::FixedSizePool<'_>| unsafe {
+        let allocations = (0..LIVE_COUNT)
+            .map(|_| fixed.alloc().unwrap())
+            .collect::<Vec<_>>();
+        for allocation in allocations {
+            fixed.free(allocation);
+        }
+    };
+    // Fills the free list, so the timed rounds don't call into VMA.
+    cycle_fixed(&mut fixed);
+    let fixed_time = time(|| {
+        for _ in 0..ROUNDS {
+            cycle_fixed(&mut fixed);
+        }
+    });
+    drop(fixed);
+
+    let pool = unsafe {
+        allocator
+            .create_pool(&vk_mem::AllocatorPoolCreateInfo {
+                memory_type_index,
+                block_size: ELEMENT_SIZE as usize * ELEMENTS_PER_BLOCK,
+                ..Default::default()
+            })
+            .unwrap()
+    };
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: ELEMENT_SIZE,
+        alignment: ALIGNMENT,
+        memory_type_bits: 1 << memory_type_index,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        pool: Some(pool),
+        ..Default::default()
+    };
+    let cycle_vma = || unsafe {
+        let allocations = (0..LIVE_COUNT)
+            .map(|_| {
+                allocator
+                    .allocate_memory(&memory_requirements, &allocation_info)
+                    .unwrap()
+                    .0
+            })
+            .collect::<Vec<_>>();
+        for allocation in allocations {
+            allocator.free_memory(allocation);
+        }
+    };
+    cycle_vma();
+    let vma_time = time(|| {
+        for _ in 0..ROUNDS {
+            cycle_vma();
+        }
+    });
+
+    println!(
+        "{} rounds of {} allocations: FixedSizePool {:?}, allocate_memory {:?}",
+        ROUNDS, LIVE_COUNT, fixed_time, vma_time
+    );
+    unsafe {
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
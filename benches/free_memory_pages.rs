@@ -0,0 +1,64 @@
+//! Compares freeing 100k scattered allocations in their original and in memory order.
+//!
+//! Run with `cargo bench --bench free_memory_pages`.
+
+#[allow(dead_code)]
+#[path = "../tests/test_support/mod.rs"]
+mod test_support;
+
+use std::time::{Duration, Instant};
+use test_support::TestHarness;
+
+const ALLOCATION_COUNT: usize = 100_000;
+
+fn allocate_scattered(allocator: &vk_mem::Allocator) -> Vec<vk_mem::Allocation> {
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 256,
+        alignment: 256,
+        memory_type_bits: u32::MAX,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        ..Default::default()
+    };
+    let mut allocations = unsafe {
+        allocator
+            .allocate_memory_pages(&memory_requirements, &allocation_info, ALLOCATION_COUNT)
+            .unwrap()
+    }
+    .into_iter()
+    .map(|(allocation, _)| allocation)
+    .collect::<Vec<_>>();
+    // Deterministic shuffle, so both runs free in the same scattered order.
+    let mut state = 0x2545_f491_u32;
+    for i in (1..allocations.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        allocations.swap(i, state as usize % (i + 1));
+    }
+    allocations
+}
+
+fn time(f: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+fn main() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+
+    let allocations = allocate_scattered(&allocator);
+    let unsorted = time(|| unsafe { allocator.free_memory_pages(&allocations) });
+
+    let mut allocations = allocate_scattered(&allocator);
+    let sorted = time(|| unsafe { allocator.free_memory_pages_sorted(&mut allocations) });
+
+    println!(
+        "free {} allocations: unsorted {:?}, sorted {:?}",
+        ALLOCATION_COUNT, unsorted, sorted
+    );
+    unsafe { allocator.destroy_allocator() };
+}
@@ -0,0 +1,80 @@
+//! Compares 8 threads allocating from the default pool with each using its own pool of a
+//! `ThreadLocalArena`.
+//!
+//! Run with `cargo bench --bench thread_local_arena`.
+
+#[allow(dead_code)]
+#[path = "../tests/test_support/mod.rs"]
+mod test_support;
+
+use std::time::{Duration, Instant};
+use test_support::TestHarness;
+
+const THREAD_COUNT: usize = 8;
+const ALLOCATIONS_PER_THREAD: usize = 10_000;
+
+fn main() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: u32::MAX,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        ..Default::default()
+    };
+    let memory_type_index = allocator
+        .find_memory_type_index(memory_requirements.memory_type_bits, &allocation_info)
+        .unwrap();
+    let arena = vk_mem::ThreadLocalArena::new(
+        &allocator,
+        &vk_mem::AllocatorPoolCreateInfo {
+            memory_type_index,
+            ..Default::default()
+        },
+    );
+
+    let run = |use_arena: bool| -> Duration {
+        let start = Instant::now();
+        std::thread::scope(|scope| {
+            for _ in 0..THREAD_COUNT {
+                scope.spawn(|| {
+                    let allocation_info = vk_mem::AllocationCreateInfo {
+                        pool: if use_arena {
+                            Some(unsafe { arena.for_current_thread().unwrap() })
+                        } else {
+                            None
+                        },
+                        usage: vk_mem::MemoryUsage::GpuOnly,
+                        ..Default::default()
+                    };
+                    let mut allocations = Vec::with_capacity(ALLOCATIONS_PER_THREAD);
+                    for _ in 0..ALLOCATIONS_PER_THREAD {
+                        let (allocation, _) = unsafe {
+                            allocator
+                                .allocate_memory(&memory_requirements, &allocation_info)
+                                .unwrap()
+                        };
+                        allocations.push(allocation);
+                    }
+                    for allocation in allocations {
+                        unsafe { allocator.free_memory(allocation) };
+                    }
+                });
+            }
+        });
+        start.elapsed()
+    };
+
+    let shared = run(false);
+    let per_thread = run(true);
+    println!(
+        "{} threads x {} allocations: default pool {:?}, per-thread pools {:?}",
+        THREAD_COUNT, ALLOCATIONS_PER_THREAD, shared, per_thread
+    );
+
+    drop(arena);
+    unsafe { allocator.destroy_allocator() };
+}
@@ -23,11 +23,8 @@ fn main() {
     build.define("VMA_DYNAMIC_VULKAN_FUNCTIONS", "0");
 
     // TODO: Add some configuration options under crate features
-    //#define VMA_HEAVY_ASSERT(expr) assert(expr)
-    //#define VMA_USE_STL_CONTAINERS 1
     //#define VMA_DEDICATED_ALLOCATION 0
     //#define VMA_DEBUG_INITIALIZE_ALLOCATIONS 1
-    //#define VMA_DEBUG_MIN_BUFFER_IMAGE_GRANULARITY 256
 
     #[cfg(feature = "detect_corruption")]
     {
@@ -35,9 +32,39 @@ fn main() {
         build.define("VMA_DEBUG_MARGIN", "16");
     }
 
+    // Forces a minimum buffer-image granularity regardless of what the device reports,
+    // which helps reproducing granularity related bugs on hardware where it is 1.
+    #[cfg(feature = "min_buffer_image_granularity")]
+    {
+        println!("cargo:rerun-if-env-changed=VK_MEM_MIN_BUFFER_IMAGE_GRANULARITY");
+        let granularity =
+            env::var("VK_MEM_MIN_BUFFER_IMAGE_GRANULARITY").unwrap_or_else(|_| "256".to_string());
+        let value: u64 = granularity
+            .parse()
+            .expect("VK_MEM_MIN_BUFFER_IMAGE_GRANULARITY must be an integer");
+        assert!(
+            value.is_power_of_two(),
+            "VK_MEM_MIN_BUFFER_IMAGE_GRANULARITY must be a power of two"
+        );
+        build.define(
+            "VMA_DEBUG_MIN_BUFFER_IMAGE_GRANULARITY",
+            granularity.as_str(),
+        );
+    }
+
     #[cfg(feature = "recording")]
     build.define("VMA_RECORDING_ENABLED", "1");
 
+    // Enables VMA's expensive internal consistency checks, e.g. validating whole blocks after
+    // every allocation. Like VMA_ASSERT, they are compiled out by NDEBUG in release builds.
+    #[cfg(feature = "heavy_assert")]
+    build.define("VMA_HEAVY_ASSERT(expr)", "assert(expr)");
+
+    // Use std::vector, std::list and std::unordered_map instead of VMA's own containers,
+    // so that debuggers and other C++ tooling can inspect VMA's internal state.
+    #[cfg(feature = "stl_containers")]
+    build.define("VMA_USE_STL_CONTAINERS", "1");
+
     // Add the files we build
     build.file("src/vma.cpp");
 
@@ -189,7 +216,10 @@ impl bindgen::callbacks::ParseCallbacks for FixAshTypes {
 
     // When ignoring `Vk` types, bindgen loses derives for some type. Quick workaround.
     fn add_derives(&self, name: &str) -> Vec<String> {
-        if name.starts_with("VmaAllocationInfo") || name.starts_with("VmaDefragmentationStats") {
+        if name.starts_with("VmaAllocationInfo")
+            || name.starts_with("VmaDefragmentationStats")
+            || name.starts_with("VmaBudget")
+        {
             vec!["Debug".into(), "Copy".into(), "Clone".into()]
         } else {
             vec![]
@@ -0,0 +1,120 @@
+use crate::{Allocation, AllocatorPool};
+use ash::vk;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// What the wrapper remembers about a live allocation, beyond what VMA keeps.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AllocationState {
+    /// Tag and size, for `Allocator::bytes_by_tag`.
+    pub(crate) tag: Option<(u32, vk::DeviceSize)>,
+
+    /// Made for a buffer or image that was not bound to it yet, for `Allocator::is_bound`.
+    pub(crate) unbound: bool,
+
+    /// Handle types of the exportable pool the allocation was made from, for
+    /// `Allocator::export_memory_info`.
+    pub(crate) export_handle_types: vk::ExternalMemoryHandleTypeFlags,
+
+    /// Size counted in `Allocator::peak_usage`.
+    pub(crate) device_local_size: Option<vk::DeviceSize>,
+
+    /// Single-block pool owned by an allocation made with a custom memory allocation chain,
+    /// destroyed when the allocation is freed.
+    pub(crate) chained_pool: Option<AllocatorPool>,
+}
+
+impl AllocationState {
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == AllocationState::default()
+    }
+}
+
+/// State of the allocations the wrapper remembers something about, behind a single lock.
+///
+/// With the `allocation_index` feature, every allocation made through the wrapper has an entry,
+/// for `Allocator::allocations_in_block`. Otherwise only allocations with a non-empty
+/// `AllocationState` are added, so allocating and freeing other allocations doesn't take the
+/// lock while there are none.
+#[derive(Debug, Default)]
+pub(crate) struct AllocationStates {
+    states: Mutex<HashMap<Allocation, AllocationState>>,
+    // Number of entries in `states`.
+    len: AtomicUsize,
+}
+
+impl AllocationStates {
+    /// Adds the state of a new allocation, if there is anything to remember about it.
+    pub(crate) fn insert(&self, allocation: Allocation, state: AllocationState) {
+        if !cfg!(feature = "allocation_index") && state.is_empty() {
+            return;
+        }
+        if self
+            .states
+            .lock()
+            .unwrap()
+            .insert(allocation, state)
+            .is_none()
+        {
+            self.len.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    /// Changes the state of `allocation` with `f`, adding an entry first if it has none.
+    pub(crate) fn update(&self, allocation: Allocation, f: impl FnOnce(&mut AllocationState)) {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(allocation).or_insert_with(|| {
+            self.len.fetch_add(1, Ordering::Release);
+            AllocationState::default()
+        });
+        f(state);
+    }
+
+    /// Changes the state of `allocation` with `f`, only if it has an entry.
+    pub(crate) fn update_existing(
+        &self,
+        allocation: Allocation,
+        f: impl FnOnce(&mut AllocationState),
+    ) {
+        if self.len.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        if let Some(state) = self.states.lock().unwrap().get_mut(&allocation) {
+            f(state);
+        }
+    }
+
+    pub(crate) fn get(&self, allocation: Allocation) -> AllocationState {
+        if self.len.load(Ordering::Acquire) == 0 {
+            return AllocationState::default();
+        }
+        self.states
+            .lock()
+            .unwrap()
+            .get(&allocation)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Forgets `allocation`, returning what was remembered about it.
+    pub(crate) fn remove(&self, allocation: Allocation) -> AllocationState {
+        if self.len.load(Ordering::Acquire) == 0 {
+            return AllocationState::default();
+        }
+        match self.states.lock().unwrap().remove(&allocation) {
+            Some(state) => {
+                self.len.fetch_sub(1, Ordering::Release);
+                state
+            }
+            None => AllocationState::default(),
+        }
+    }
+
+    /// Calls `f` for every entry, with the lock held.
+    pub(crate) fn for_each(&self, mut f: impl FnMut(Allocation, &AllocationState)) {
+        for (&allocation, state) in self.states.lock().unwrap().iter() {
+            f(allocation, state);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{AllocationCreateFlags, AllocatorPoolCreateFlags, AllocatorPoolCreateInfo, Error};
+
+/// Allocation flags supported by the algorithm of a custom pool, see
+/// `AllocatorPool::allocation_constraints`.
+///
+/// The rules of VMA are:
+///
+/// - `AllocationCreateFlags::UPPER_ADDRESS` needs `AllocatorPoolCreateFlags::LINEAR_ALGORITHM`
+///   and a `max_block_count` of 1, i.e. a double stack.
+/// - The `AllocationCreateFlags::STRATEGY_*` flags (and `AllocationCreateInfo::strategy`) only
+///   apply to the default algorithm. The linear and buddy algorithms ignore them, so they are
+///   allowed.
+/// - The buddy algorithm doesn't support lost allocations, so neither
+///   `AllocationCreateFlags::CAN_BECOME_LOST` nor `AllocationCreateFlags::CAN_MAKE_OTHER_LOST`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocationConstraints {
+    /// Allocation flags the pool doesn't support.
+    pub incompatible_flags: AllocationCreateFlags,
+}
+
+impl AllocationConstraints {
+    /// Constraints of a pool created from `pool_info`.
+    pub(crate) fn for_pool(pool_info: &AllocatorPoolCreateInfo) -> Self {
+        let incompatible_flags = if pool_info
+            .flags
+            .contains(AllocatorPoolCreateFlags::LINEAR_ALGORITHM)
+        {
+            if pool_info.max_block_count == 1 {
+                AllocationCreateFlags::empty()
+            } else {
+                AllocationCreateFlags::UPPER_ADDRESS
+            }
+        } else if pool_info
+            .flags
+            .contains(AllocatorPoolCreateFlags::BUDDY_ALGORITHM)
+        {
+            AllocationCreateFlags::UPPER_ADDRESS
+                | AllocationCreateFlags::CAN_BECOME_LOST
+                | AllocationCreateFlags::CAN_MAKE_OTHER_LOST
+        } else {
+            AllocationCreateFlags::UPPER_ADDRESS
+        };
+        AllocationConstraints { incompatible_flags }
+    }
+
+    /// Returns `true` if allocations with `flags` can be made from the pool.
+    pub fn allows(&self, flags: AllocationCreateFlags) -> bool {
+        !flags.intersects(self.incompatible_flags)
+    }
+
+    /// Fails with `Error::IncompatibleFlags` unless `flags` are allowed.
+    pub fn check(&self, flags: AllocationCreateFlags) -> Result<(), Error> {
+        if self.allows(flags) {
+            Ok(())
+        } else {
+            Err(Error::IncompatibleFlags)
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{Allocator, AllocatorPool, AllocatorPoolCreateInfo};
+use ash::prelude::VkResult;
+use ash::vk;
+
+/// Custom pool whose memory blocks are allocated with a `ash::vk::MemoryAllocateFlagsInfo`,
+/// e.g. to place allocations on a subset of the physical devices in a device group.
+///
+/// Allocate from it by setting `AllocationCreateInfo::pool` to `DeviceGroupPool::pool`.
+/// The pool is destroyed when dropped, so all its allocations must be freed before that.
+///
+/// Prerequisites for `ash::vk::MemoryAllocateFlags::DEVICE_MASK`:
+///
+/// - The logical device must be created from a device group, i.e. with
+///   `ash::vk::DeviceGroupDeviceCreateInfo` listing the physical devices returned by
+///   `vkEnumeratePhysicalDeviceGroups`.
+/// - Vulkan 1.1 or the `VK_KHR_device_group` extension must be enabled.
+/// - The device mask must be non-zero and only contain bits of devices in the group.
+///
+/// VMA attaches its own `ash::vk::MemoryAllocateFlagsInfo` when the allocator is created with
+/// `AllocatorCreateFlags::BUFFER_DEVICE_ADDRESS`, which must not be combined with this pool.
+#[derive(Debug)]
+pub struct DeviceGroupPool<'a> {
+    allocator: &'a Allocator,
+    pool: AllocatorPool,
+    // Referenced by every `vkAllocateMemory` of the pool, so it must stay at a fixed address.
+    _flags_info: Box<vk::MemoryAllocateFlagsInfo>,
+}
+
+impl<'a> DeviceGroupPool<'a> {
+    /// Creates a pool from `pool_info`, with `memory_allocate_flags` prepended to the
+    /// `AllocatorPoolCreateInfo::memory_allocate_next` chain.
+    ///
+    /// If `device_mask` is `Some`, `ash::vk::MemoryAllocateFlags::DEVICE_MASK` is added to the flags.
+    pub unsafe fn new(
+        allocator: &'a Allocator,
+        pool_info: &AllocatorPoolCreateInfo,
+        memory_allocate_flags: vk::MemoryAllocateFlags,
+        device_mask: Option<u32>,
+    ) -> VkResult<Self> {
+        let mut flags_info = Box::new(vk::MemoryAllocateFlagsInfo {
+            p_next: pool_info
+                .memory_allocate_next
+                .unwrap_or(std::ptr::null_mut()),
+            flags: memory_allocate_flags,
+            device_mask: device_mask.unwrap_or(0),
+            ..Default::default()
+        });
+        if device_mask.is_some() {
+            flags_info.flags |= vk::MemoryAllocateFlags::DEVICE_MASK;
+        }
+
+        let pool = allocator.create_pool(&AllocatorPoolCreateInfo {
+            memory_allocate_next: Some(&mut *flags_info as *mut vk::MemoryAllocateFlagsInfo as _),
+            ..pool_info.clone()
+        })?;
+
+        Ok(DeviceGroupPool {
+            allocator,
+            pool,
+            _flags_info: flags_info,
+        })
+    }
+
+    /// Underlying custom pool.
+    #[inline(always)]
+    pub fn pool(&self) -> AllocatorPool {
+        self.pool
+    }
+}
+
+impl Drop for DeviceGroupPool<'_> {
+    fn drop(&mut self) {
+        unsafe { self.allocator.destroy_pool(self.pool) };
+    }
+}
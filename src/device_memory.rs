@@ -0,0 +1,77 @@
+use crate::{ffi, AllocationInfo};
+use ash::vk;
+use std::collections::HashMap;
+use std::os::raw::c_void;
+use std::sync::{Mutex, MutexGuard};
+
+/// Parameters of an `Allocation` together with the memory block it lives in, see
+/// `Allocator::get_allocation_info2`.
+#[derive(Debug, Clone, Copy)]
+pub struct AllocationInfo2 {
+    /// Same as returned by `Allocator::get_allocation_info`.
+    pub allocation_info: AllocationInfo,
+
+    /// Size of the `ash::vk::DeviceMemory` block the allocation lives in, in bytes.
+    ///
+    /// For a dedicated allocation, this is the size of the allocation.
+    pub block_size: vk::DeviceSize,
+
+    /// `true` if the allocation covers its whole `ash::vk::DeviceMemory` block, which is a
+    /// heuristic for a dedicated allocation, see `Allocator::get_allocation_info2`.
+    pub dedicated_memory: bool,
+}
+
+/// Every `ash::vk::DeviceMemory` block currently allocated by VMA, with its size.
+///
+/// Kept up to date by the device memory callbacks installed by `Allocator::new`, which receive a
+/// pointer to this registry as user data. It must therefore stay at a fixed address, i.e. boxed,
+/// for as long as the VMA allocator exists.
+#[derive(Debug, Default)]
+pub(crate) struct DeviceMemoryBlocks {
+    blocks: Mutex<HashMap<vk::DeviceMemory, vk::DeviceSize>>,
+}
+
+impl DeviceMemoryBlocks {
+    /// Returns the callbacks to pass in `VmaAllocatorCreateInfo::pDeviceMemoryCallbacks`.
+    pub(crate) fn callbacks(&self) -> ffi::VmaDeviceMemoryCallbacks {
+        ffi::VmaDeviceMemoryCallbacks {
+            pfnAllocate: Some(on_allocate),
+            pfnFree: Some(on_free),
+            pUserData: self as *const DeviceMemoryBlocks as *mut c_void,
+        }
+    }
+
+    /// Size of the block `memory`, or `None` if it is not a block allocated by VMA.
+    pub(crate) fn size(&self, memory: vk::DeviceMemory) -> Option<vk::DeviceSize> {
+        self.lock().get(&memory).copied()
+    }
+
+    // Called from VMA; a poisoned lock must not panic across the FFI boundary.
+    fn lock(&self) -> MutexGuard<'_, HashMap<vk::DeviceMemory, vk::DeviceSize>> {
+        self.blocks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+unsafe extern "C" fn on_allocate(
+    _allocator: ffi::VmaAllocator,
+    _memory_type: u32,
+    memory: vk::DeviceMemory,
+    size: vk::DeviceSize,
+    user_data: *mut c_void,
+) {
+    let blocks = &*(user_data as *const DeviceMemoryBlocks);
+    blocks.lock().insert(memory, size);
+}
+
+unsafe extern "C" fn on_free(
+    _allocator: ffi::VmaAllocator,
+    _memory_type: u32,
+    memory: vk::DeviceMemory,
+    _size: vk::DeviceSize,
+    user_data: *mut c_void,
+) {
+    let blocks = &*(user_data as *const DeviceMemoryBlocks);
+    blocks.lock().remove(&memory);
+}
@@ -0,0 +1,106 @@
+use crate::{
+    Allocation, AllocationCreateFlags, AllocationCreateInfo, AllocationInfo, Allocator,
+    AllocatorPool, AllocatorPoolCreateFlags, AllocatorPoolCreateInfo, Error,
+};
+use ash::vk;
+
+/// Double stack allocator built on a custom pool with `AllocatorPoolCreateFlags::LINEAR_ALGORITHM`
+/// and a single memory block.
+///
+/// Allocations made with `DoubleStackPool::alloc_lower` grow from the beginning of the block,
+/// allocations made with `DoubleStackPool::alloc_upper` grow down from its end, using
+/// `AllocationCreateFlags::UPPER_ADDRESS`. Allocation fails with
+/// `ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY` when both stacks meet.
+///
+/// For the memory to be reused, each stack should be freed in reverse order of allocation.
+/// All allocations must be freed before the `DoubleStackPool` is dropped.
+#[derive(Debug)]
+pub struct DoubleStackPool<'a> {
+    allocator: &'a Allocator,
+    pool: AllocatorPool,
+}
+
+impl<'a> DoubleStackPool<'a> {
+    /// Creates the underlying pool from `pool_info`.
+    ///
+    /// Returns `Error::NotLinearPool` if `pool_info.flags` does not contain
+    /// `AllocatorPoolCreateFlags::LINEAR_ALGORITHM` or `pool_info.max_block_count` is not 1,
+    /// as the upper stack is only supported in that configuration.
+    pub unsafe fn new(
+        allocator: &'a Allocator,
+        pool_info: &AllocatorPoolCreateInfo,
+    ) -> Result<Self, Error> {
+        if !pool_info
+            .flags
+            .contains(AllocatorPoolCreateFlags::LINEAR_ALGORITHM)
+            || pool_info.max_block_count != 1
+        {
+            return Err(Error::NotLinearPool);
+        }
+
+        let pool = allocator.create_pool(pool_info)?;
+        Ok(DoubleStackPool { allocator, pool })
+    }
+
+    /// Allocates `size` bytes aligned to `alignment` from the bottom of the block.
+    pub unsafe fn alloc_lower(
+        &self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Result<(Allocation, AllocationInfo), Error> {
+        self.alloc(size, alignment, AllocationCreateFlags::NONE)
+    }
+
+    /// Allocates `size` bytes aligned to `alignment` from the top of the block.
+    ///
+    /// Fails with `Error::NotLinearPool` if the pool doesn't support the upper stack.
+    pub unsafe fn alloc_upper(
+        &self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Result<(Allocation, AllocationInfo), Error> {
+        if let Some(constraints) = self.pool.allocation_constraints() {
+            if !constraints.allows(AllocationCreateFlags::UPPER_ADDRESS) {
+                return Err(Error::NotLinearPool);
+            }
+        }
+        self.alloc(size, alignment, AllocationCreateFlags::UPPER_ADDRESS)
+    }
+
+    /// Frees an allocation made from either stack.
+    pub unsafe fn free(&self, allocation: Allocation) {
+        self.allocator.free_memory(allocation);
+    }
+
+    /// Underlying custom pool.
+    #[inline(always)]
+    pub fn pool(&self) -> AllocatorPool {
+        self.pool
+    }
+
+    unsafe fn alloc(
+        &self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+        flags: AllocationCreateFlags,
+    ) -> Result<(Allocation, AllocationInfo), Error> {
+        let memory_requirements = vk::MemoryRequirements {
+            size,
+            alignment,
+            memory_type_bits: u32::MAX,
+        };
+        let allocation_info = AllocationCreateInfo {
+            flags,
+            pool: Some(self.pool),
+            ..Default::default()
+        };
+        self.allocator
+            .allocate_memory(&memory_requirements, &allocation_info)
+    }
+}
+
+impl Drop for DoubleStackPool<'_> {
+    fn drop(&mut self) {
+        unsafe { self.allocator.destroy_pool(self.pool) };
+    }
+}
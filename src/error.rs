@@ -0,0 +1,120 @@
+use ash::vk;
+use std::fmt;
+
+/// Error returned by functions of this crate that check their arguments before calling into
+/// VMA or Vulkan.
+///
+/// Errors reported by VMA or Vulkan themselves are wrapped in `Error::Vulkan`. Functions that
+/// return `ash::prelude::VkResult` report these errors as the `ash::vk::Result` given by
+/// `From<Error>`, which is `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` unless stated otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error returned by VMA or Vulkan.
+    Vulkan(vk::Result),
+
+    /// `AllocationCreateInfo::flags` contains mutually exclusive flags, flags that conflict with
+    /// `AllocationCreateInfo::strategy`, or flags that can never succeed.
+    InvalidFlags,
+
+    /// `AllocationCreateInfo::pool` is set together with `usage`, `required_flags`,
+    /// `preferred_flags` or `memory_type_bits`, which the pool would ignore.
+    ConflictingPool,
+
+    /// A size or offset does not fit into `usize` or `ash::vk::DeviceSize`, or the end of a
+    /// range computed from them overflows.
+    ///
+    /// Reported as `ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY` through `From<Error>`.
+    SizeOverflow,
+
+    /// An `ash::vk::ImageCreateInfo` has a zero width, height, depth, mip level count or array
+    /// layer count, on which VMA would hit an assertion.
+    InvalidImageExtent,
+
+    /// `AllocationCreateInfo::flags` or `AllocationCreateInfo::strategy` are not supported by the
+    /// algorithm of `AllocationCreateInfo::pool`, see `AllocatorPool::allocation_constraints`.
+    IncompatibleFlags,
+
+    /// `AllocatorPoolCreateInfo::memory_type_index` is not a memory type of the physical device.
+    InvalidMemoryType,
+
+    /// `AllocatorCreateInfo::heap_size_limits` is neither empty nor has one element per memory
+    /// heap of the physical device.
+    HeapLimitsLengthMismatch,
+
+    /// The upper stack was requested from a pool without `AllocatorPoolCreateFlags::LINEAR_ALGORITHM`
+    /// or with a `max_block_count` other than 1, see `DoubleStackPool`.
+    NotLinearPool,
+
+    /// A size, count or alignment is zero or out of range, e.g. empty data to upload, or a copy
+    /// larger than the allocations it refers to.
+    InvalidSize,
+
+    /// An `AllocatorPoolCreateInfo` has a `min_block_count` above a non-zero `max_block_count`,
+    /// a `min_allocation_alignment` that is neither zero nor a power of two, or a `priority`
+    /// outside of `[0, 1]`.
+    InvalidPoolCreateInfo,
+
+    /// No memory type is supported by all the resources that would share an allocation, see
+    /// `Allocator::create_packed_buffers`.
+    ///
+    /// Reported as `ash::vk::Result::ERROR_FEATURE_NOT_PRESENT` through `From<Error>`, as VMA
+    /// does when no memory type fits.
+    NoCompatibleMemoryType,
+}
+
+impl From<vk::Result> for Error {
+    fn from(result: vk::Result) -> Self {
+        Error::Vulkan(result)
+    }
+}
+
+impl From<Error> for vk::Result {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Vulkan(result) => result,
+            Error::InvalidFlags
+            | Error::ConflictingPool
+            | Error::InvalidImageExtent
+            | Error::IncompatibleFlags
+            | Error::InvalidMemoryType
+            | Error::HeapLimitsLengthMismatch
+            | Error::NotLinearPool
+            | Error::InvalidSize
+            | Error::InvalidPoolCreateInfo => vk::Result::ERROR_VALIDATION_FAILED_EXT,
+            Error::SizeOverflow => vk::Result::ERROR_OUT_OF_HOST_MEMORY,
+            Error::NoCompatibleMemoryType => vk::Result::ERROR_FEATURE_NOT_PRESENT,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Vulkan(result) => write!(f, "{}", result),
+            Error::InvalidFlags => f.write_str("invalid combination of allocation flags"),
+            Error::ConflictingPool => {
+                f.write_str("allocation from a pool with memory type selection members set")
+            }
+            Error::SizeOverflow => f.write_str("size or offset overflows the address space"),
+            Error::InvalidImageExtent => {
+                f.write_str("image with a zero extent, mip level count or array layer count")
+            }
+            Error::IncompatibleFlags => {
+                f.write_str("allocation flags not supported by the algorithm of the pool")
+            }
+            Error::InvalidMemoryType => f.write_str("memory type index out of range"),
+            Error::HeapLimitsLengthMismatch => {
+                f.write_str("heap size limits don't match the number of memory heaps")
+            }
+            Error::NotLinearPool => f.write_str("upper stack of a pool that is not a double stack"),
+            Error::InvalidSize => f.write_str("size, count or alignment is zero or out of range"),
+            Error::InvalidPoolCreateInfo => f.write_str("invalid pool create info"),
+            Error::NoCompatibleMemoryType => {
+                f.write_str("no memory type is supported by all the resources")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
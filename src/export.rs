@@ -0,0 +1,97 @@
+use crate::AllocatorPool;
+use ash::vk;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// What is needed to export the memory of an allocation, see `Allocator::export_memory_info`.
+///
+/// `device_memory` is what `vkGetMemoryFdKHR` or `vkGetMemoryWin32HandleKHR` export. The exported
+/// handle refers to the whole `ash::vk::DeviceMemory` block, so the importing API (e.g. OpenGL
+/// with `GL_EXT_memory_object`) must be given `offset` and `size` as well, and `offset` has to
+/// satisfy its alignment rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportInfo {
+    /// Memory block the allocation lives in.
+    pub device_memory: vk::DeviceMemory,
+
+    /// Offset of the allocation within `device_memory`, in bytes.
+    pub offset: vk::DeviceSize,
+
+    /// Size of the allocation, in bytes.
+    pub size: vk::DeviceSize,
+
+    /// Handle types `device_memory` was made exportable with, i.e.
+    /// `ash::vk::ExportMemoryAllocateInfo::handle_types` of the pool the allocation came from.
+    ///
+    /// Empty if the allocation is not known to come from such a pool, in which case the memory
+    /// cannot be exported. Otherwise pass one of these bits to the export call.
+    pub handle_type: vk::ExternalMemoryHandleTypeFlags,
+}
+
+/// Pools created with `ash::vk::ExportMemoryAllocateInfo` in their memory allocation chain.
+///
+/// Only pools with non-empty `handle_types` are registered. Allocating doesn't take the lock
+/// while no such pool exists.
+#[derive(Debug, Default)]
+pub(crate) struct ExportablePools {
+    pools: Mutex<HashMap<AllocatorPool, vk::ExternalMemoryHandleTypeFlags>>,
+    // Number of entries in `pools`.
+    len: AtomicUsize,
+}
+
+impl ExportablePools {
+    /// Registers `pool` if `memory_allocate_next` chains an `ash::vk::ExportMemoryAllocateInfo`
+    /// with non-empty `handle_types`.
+    pub(crate) unsafe fn insert(
+        &self,
+        pool: AllocatorPool,
+        memory_allocate_next: Option<*mut ::std::os::raw::c_void>,
+    ) {
+        let mut next =
+            memory_allocate_next.unwrap_or(std::ptr::null_mut()) as *const vk::BaseInStructure;
+        while let Some(structure) = next.as_ref() {
+            if structure.s_type == vk::StructureType::EXPORT_MEMORY_ALLOCATE_INFO {
+                let export_info = &*(next as *const vk::ExportMemoryAllocateInfo);
+                if !export_info.handle_types.is_empty()
+                    && self
+                        .pools
+                        .lock()
+                        .unwrap()
+                        .insert(pool, export_info.handle_types)
+                        .is_none()
+                {
+                    self.len.fetch_add(1, Ordering::Release);
+                }
+                return;
+            }
+            next = structure.p_next;
+        }
+    }
+
+    pub(crate) fn remove(&self, pool: AllocatorPool) {
+        if self.len.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        if self.pools.lock().unwrap().remove(&pool).is_some() {
+            self.len.fetch_sub(1, Ordering::Release);
+        }
+    }
+
+    /// Handle types allocations from `pool` can be exported with, empty if it is not exportable.
+    pub(crate) fn handle_types(
+        &self,
+        pool: Option<AllocatorPool>,
+    ) -> vk::ExternalMemoryHandleTypeFlags {
+        match pool {
+            Some(pool) if self.len.load(Ordering::Acquire) != 0 => self
+                .pools
+                .lock()
+                .unwrap()
+                .get(&pool)
+                .copied()
+                .unwrap_or_default(),
+            _ => vk::ExternalMemoryHandleTypeFlags::empty(),
+        }
+    }
+}
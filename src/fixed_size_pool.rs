@@ -0,0 +1,111 @@
+use crate::{
+    Allocation, AllocationCreateInfo, Allocator, AllocatorPool, AllocatorPoolCreateInfo, Error,
+};
+use ash::vk;
+use std::convert::TryFrom;
+
+/// Pool of allocations that all have the same size and alignment.
+///
+/// The underlying custom pool uses blocks sized to hold exactly `elements_per_block` elements.
+/// Freed allocations are kept on a free list instead of being returned to VMA, so once the pool
+/// is warmed up, `FixedSizePool::alloc` and `FixedSizePool::free` are O(1) and don't call into VMA.
+///
+/// Allocations must be returned with `FixedSizePool::free` before the pool is dropped; the ones
+/// on the free list are released on drop.
+#[derive(Debug)]
+pub struct FixedSizePool<'a> {
+    allocator: &'a Allocator,
+    pool: AllocatorPool,
+    requirements: vk::MemoryRequirements,
+    free_list: Vec<Allocation>,
+}
+
+impl<'a> FixedSizePool<'a> {
+    /// Creates a pool in memory type `memory_type_index` for elements of `element_size` bytes
+    /// aligned to `alignment`, with `elements_per_block` elements per `ash::vk::DeviceMemory` block.
+    ///
+    /// Returns `Error::InvalidSize` if `element_size` or `elements_per_block` is zero or
+    /// `alignment` is not a power of two, and `Error::SizeOverflow` if the block size doesn't fit
+    /// in `usize`.
+    pub unsafe fn new(
+        allocator: &'a Allocator,
+        memory_type_index: u32,
+        element_size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+        elements_per_block: usize,
+    ) -> Result<Self, Error> {
+        if element_size == 0 || elements_per_block == 0 || !alignment.is_power_of_two() {
+            return Err(Error::InvalidSize);
+        }
+        let block_size = element_size
+            .checked_next_multiple_of(alignment)
+            .and_then(|stride| usize::try_from(stride).ok())
+            .and_then(|stride| stride.checked_mul(elements_per_block))
+            .ok_or(Error::SizeOverflow)?;
+        let pool = allocator.create_pool(&AllocatorPoolCreateInfo {
+            memory_type_index,
+            block_size,
+            ..Default::default()
+        })?;
+
+        Ok(FixedSizePool {
+            allocator,
+            pool,
+            requirements: vk::MemoryRequirements {
+                size: element_size,
+                alignment,
+                memory_type_bits: 1 << memory_type_index,
+            },
+            free_list: Vec::new(),
+        })
+    }
+
+    /// Returns an allocation from the free list, or allocates a new one from the pool.
+    pub unsafe fn alloc(&mut self) -> Result<Allocation, Error> {
+        if let Some(allocation) = self.free_list.pop() {
+            return Ok(allocation);
+        }
+
+        let allocation_info = AllocationCreateInfo {
+            pool: Some(self.pool),
+            ..Default::default()
+        };
+        let (allocation, _) = self
+            .allocator
+            .allocate_memory(&self.requirements, &allocation_info)?;
+        Ok(allocation)
+    }
+
+    /// Puts `allocation`, which must have been returned by `FixedSizePool::alloc`, back on the free list.
+    pub fn free(&mut self, allocation: Allocation) {
+        self.free_list.push(allocation);
+    }
+
+    /// Releases all allocations on the free list back to VMA, allowing empty blocks to be freed.
+    pub unsafe fn trim(&mut self) {
+        for allocation in self.free_list.drain(..) {
+            self.allocator.free_memory(allocation);
+        }
+    }
+
+    /// Size of a single element in bytes.
+    #[inline(always)]
+    pub fn element_size(&self) -> vk::DeviceSize {
+        self.requirements.size
+    }
+
+    /// Underlying custom pool.
+    #[inline(always)]
+    pub fn pool(&self) -> AllocatorPool {
+        self.pool
+    }
+}
+
+impl Drop for FixedSizePool<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            self.trim();
+            self.allocator.destroy_pool(self.pool);
+        }
+    }
+}
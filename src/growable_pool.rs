@@ -0,0 +1,168 @@
+use crate::{
+    stats, Allocation, AllocationCreateInfo, AllocationInfo, Allocator, AllocatorPool,
+    AllocatorPoolCreateInfo, Error, PoolStats,
+};
+use ash::prelude::VkResult;
+use ash::vk;
+use std::sync::Mutex;
+
+/// Custom pools of growing block size, for memory that starts small but may need to hold a lot.
+///
+/// A single pool needs a `block_size` chosen up front: a large one wastes memory while little is
+/// allocated, a small one spreads large working sets over many blocks. `GrowablePool` starts with
+/// one pool created from an `AllocatorPoolCreateInfo`. Whenever none of its pools has room for an
+/// allocation, it creates another one whose `block_size` is twice the previous one, or larger if
+/// the allocation doesn't fit otherwise.
+///
+/// Every pool holds up to `AllocatorPoolCreateInfo::max_block_count` blocks, or a single one if it
+/// is 0. Allocations are tried in the newest, i.e. largest, pool first. Each pool that is full
+/// fails the attempt with `ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY` before the next one is
+/// tried, which also reaches `Allocator::set_allocation_failure_callback`.
+///
+/// The pools are only locked to look them up and to add a new one, not while allocating, so
+/// threads may allocate concurrently. Threads that find all pools full at the same time may each
+/// create a new pool.
+///
+/// Allocations may be freed with `GrowablePool::free` or `Allocator::free_memory`. All of them
+/// must be freed before the pool is dropped, which destroys the pools.
+#[derive(Debug)]
+pub struct GrowablePool<'a> {
+    allocator: &'a Allocator,
+    pool_info: PoolTemplate,
+    /// Pools with their block size, from the smallest to the largest.
+    pools: Mutex<Vec<(AllocatorPool, usize)>>,
+}
+
+/// `AllocatorPoolCreateInfo` new pools are created from.
+#[derive(Debug)]
+struct PoolTemplate(AllocatorPoolCreateInfo);
+
+// The only member that is not `Send` and `Sync` is the raw `memory_allocate_next` pointer. The
+// wrapper never dereferences it, it only passes it on to VMA, which reads the chain when
+// allocating a block of a new pool. `GrowablePool::new` requires the chain to stay valid and
+// unmodified for the lifetime of the pool, so these are shared reads of immutable data, from
+// whichever thread creates a pool.
+unsafe impl Send for PoolTemplate {}
+unsafe impl Sync for PoolTemplate {}
+
+impl<'a> GrowablePool<'a> {
+    /// Creates the first pool from `pool_info`.
+    ///
+    /// Returns `Error::InvalidSize` if `pool_info.block_size` is 0, as the growth starts from it. If `pool_info.memory_allocate_next` is set, the chain must stay
+    /// valid and unmodified for the lifetime of the pool.
+    pub unsafe fn new(
+        allocator: &'a Allocator,
+        pool_info: &AllocatorPoolCreateInfo,
+    ) -> Result<Self, Error> {
+        if pool_info.block_size == 0 {
+            return Err(Error::InvalidSize);
+        }
+
+        let pool_info = AllocatorPoolCreateInfo {
+            max_block_count: pool_info.max_block_count.max(1),
+            ..pool_info.clone()
+        };
+        let pool = allocator.create_pool(&pool_info)?;
+        let pools = Mutex::new(vec![(pool, pool_info.block_size)]);
+        Ok(GrowablePool {
+            allocator,
+            pool_info: PoolTemplate(pool_info),
+            pools,
+        })
+    }
+
+    /// Allocates memory for `memory_requirements` from the newest pool with room, creating a
+    /// larger pool if there is none.
+    ///
+    /// `allocation_info.pool` is replaced by the chosen pool. Errors other than
+    /// `ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY` are returned right away. If allocating from
+    /// a new pool fails, the pool is destroyed again. Fails with `Error::SizeOverflow` if the
+    /// block size of the new pool would not fit into `usize`.
+    pub unsafe fn alloc(
+        &self,
+        memory_requirements: &vk::MemoryRequirements,
+        allocation_info: &AllocationCreateInfo,
+    ) -> Result<(Allocation, AllocationInfo), Error> {
+        let allocate = |pool| {
+            let allocation_info = AllocationCreateInfo {
+                pool: Some(pool),
+                ..allocation_info.clone()
+            };
+            self.allocator
+                .allocate_memory(memory_requirements, &allocation_info)
+        };
+
+        let pools = self.pools.lock().unwrap().clone();
+        for &(pool, _) in pools.iter().rev() {
+            match allocate(pool) {
+                Err(Error::Vulkan(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)) => continue,
+                result => return result,
+            }
+        }
+
+        let double = |size: usize| size.checked_mul(2).ok_or(Error::SizeOverflow);
+        let mut block_size = double(pools.last().unwrap().1)?;
+        while (block_size as vk::DeviceSize) < memory_requirements.size {
+            block_size = double(block_size)?;
+        }
+        let pool = self.allocator.create_pool(&AllocatorPoolCreateInfo {
+            block_size,
+            ..self.pool_info.0.clone()
+        })?;
+        let result = allocate(pool);
+        if result.is_err() {
+            self.allocator.destroy_pool(pool);
+            return result;
+        }
+
+        let mut pools = self.pools.lock().unwrap();
+        let index = pools
+            .iter()
+            .rposition(|&(_, size)| size <= block_size)
+            .map_or(0, |index| index + 1);
+        pools.insert(index, (pool, block_size));
+        result
+    }
+
+    /// Frees an allocation made by `GrowablePool::alloc`.
+    ///
+    /// Empty pools are kept for later allocations until the `GrowablePool` is dropped.
+    pub unsafe fn free(&self, allocation: Allocation) {
+        self.allocator.free_memory(allocation);
+    }
+
+    /// Underlying custom pools, from the smallest to the largest.
+    pub fn pools(&self) -> Vec<AllocatorPool> {
+        self.pools
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|&(pool, _)| pool)
+            .collect()
+    }
+
+    /// Block size of the largest pool, which the next pool doubles.
+    pub fn current_block_size(&self) -> usize {
+        self.pools.lock().unwrap().last().unwrap().1
+    }
+
+    /// Returns the statistics of all pools, added together.
+    ///
+    /// `PoolStats::unused_range_size_max` is the largest free range of any pool.
+    pub unsafe fn stats(&self) -> Result<PoolStats, Error> {
+        let stats = self
+            .pools()
+            .into_iter()
+            .map(|pool| self.allocator.get_pool_stats(pool))
+            .collect::<VkResult<Vec<_>>>()?;
+        Ok(PoolStats::from(&stats::sum_pool_stats(&stats)))
+    }
+}
+
+impl Drop for GrowablePool<'_> {
+    fn drop(&mut self) {
+        for (pool, _) in self.pools.get_mut().unwrap().drain(..) {
+            unsafe { self.allocator.destroy_pool(pool) };
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use ash::vk;
+use std::fmt;
+
+/// Allocations still alive when `Allocator::assert_empty` was called.
+#[derive(Debug, Clone)]
+pub struct LeakReport {
+    /// Total size of all leaked allocations, in bytes.
+    pub leaked_bytes: vk::DeviceSize,
+
+    /// Number of leaked allocations.
+    pub leaked_allocations: u32,
+
+    /// Memory type index and number of leaked allocations, for every memory type with leaks.
+    pub per_type: Vec<(u32, usize)>,
+
+    /// Detailed JSON dump of the allocator state, as returned by `Allocator::build_stats_string`.
+    pub stats_string: String,
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} allocation(s) totalling {} bytes were not freed",
+            self.leaked_allocations, self.leaked_bytes
+        )?;
+        for (memory_type_index, count) in &self.per_type {
+            write!(
+                f,
+                "\n  memory type {}: {} allocation(s)",
+                memory_type_index, count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LeakReport {}
@@ -4,30 +4,237 @@ use bitflags::bitflags;
 
 use std::mem;
 
+mod allocation_state;
+mod constraints;
+mod device_group_pool;
+mod device_memory;
+mod double_stack_pool;
+mod error;
+mod export;
 pub mod ffi;
+mod fixed_size_pool;
+mod growable_pool;
+mod leak;
+mod mapping;
+mod reentrancy;
+mod resource;
+mod ring_pool;
+mod shared;
+mod staging;
+mod stats;
+mod sub_buffer_arena;
+mod thread_local_arena;
+mod trace;
+mod usage;
+mod virtual_block;
+use allocation_state::AllocationState;
 use ash::prelude::VkResult;
 use ash::vk;
 
+pub use constraints::AllocationConstraints;
+pub use device_group_pool::DeviceGroupPool;
+pub use device_memory::AllocationInfo2;
+pub use double_stack_pool::DoubleStackPool;
+pub use error::Error;
+pub use export::ExportInfo;
+pub use fixed_size_pool::FixedSizePool;
+pub use growable_pool::GrowablePool;
+pub use leak::LeakReport;
+pub use mapping::{
+    MapGuard, MappableAllocation, MappedRef, PersistentMap, ReadMapping, WriteMapping,
+};
+pub use resource::{Buffer, Image};
+pub use ring_pool::RingPool;
+pub use shared::SharedAllocation;
+pub use staging::StagingResources;
+pub use stats::{DetailedStatistics, PoolStats, StatInfo, Statistics, Stats, TotalStatistics};
+pub use sub_buffer_arena::SubBufferArena;
+pub use thread_local_arena::ThreadLocalArena;
+pub use virtual_block::VirtualBlock;
+
+/// Callback invoked by the wrapper when an allocation fails, see `Allocator::set_allocation_failure_callback`.
+pub type AllocationFailureCallback =
+    Box<dyn Fn(&Allocator, &AllocationCreateInfo, &vk::MemoryRequirements, Error) + Send + Sync>;
+
+/// Callback invoked by the wrapper after every successful allocation, see
+/// `Allocator::set_allocate_callback`.
+pub type AllocateCallback = Box<dyn Fn(Allocation, &AllocationInfo, Option<&str>) + Send + Sync>;
+
 /// Main allocator object
-#[repr(transparent)]
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Hash)]
-pub struct Allocator(ffi::VmaAllocator);
+///
+/// The allocator owns the VMA instance and the wrapper's bookkeeping of it, so it is not a
+/// `Copy` handle. Share it by reference, or wrap it in an `std::sync::Arc`.
+///
+/// # Safety
+///
+/// Functions that only query the state of the allocator, such as `Allocator::calculate_stats`,
+/// `Allocator::get_budget` or `Allocator::find_memory_type_index`, are safe. They rely solely on
+/// the invariant that the allocator has not been destroyed with the unsafe
+/// `Allocator::destroy_allocator`, and never pass handles or pointers from their arguments on to
+/// VMA or Vulkan: `Allocator::find_memory_type_index` and the other memory type queries only read
+/// the memory type selection members of `AllocationCreateInfo` and ignore `pool` and
+/// `user_data`, `Allocator::image_staging_size` ignores `p_next`, and functions such as
+/// `Allocator::is_bound` only look handles up in state kept by the wrapper.
+///
+/// Functions that hand an `Allocation`, an `AllocatorPool`, a raw Vulkan handle or a create info
+/// with a `p_next` chain to VMA or Vulkan are `unsafe`, as the wrapper cannot verify that these
+/// are still alive or valid, and misuse of them can produce dangling handles or pointers.
+pub struct Allocator {
+    /// Raw VMA allocator handle.
+    pub(crate) internal: ffi::VmaAllocator,
+
+    /// Vulkan instance the allocator was created with.
+    pub(crate) instance: ash::Instance,
+
+    /// Vulkan device the allocator was created with.
+    pub(crate) device: ash::Device,
+
+    /// Physical device the allocator was created with.
+    pub(crate) physical_device: vk::PhysicalDevice,
+
+    /// User callback invoked when an allocating function fails.
+    allocation_failure_callback: Option<AllocationFailureCallback>,
+
+    /// User callback invoked after every successful allocation.
+    allocate_callback: Option<AllocateCallback>,
+
+    /// Bit mask of memory types that are `HOST_COHERENT`, which implies `HOST_VISIBLE`.
+    coherent_memory_types: u32,
+
+    /// Bit mask of memory types that are `HOST_VISIBLE` but not `HOST_COHERENT`.
+    non_coherent_memory_types: u32,
+
+    /// Bit mask of memory types that are `DEVICE_LOCAL`.
+    device_local_memory_types: u32,
+
+    /// Bit mask of memory types VMA refuses to use: the `DEVICE_COHERENT_AMD` ones, unless
+    /// `AllocatorCreateFlags::AMD_DEVICE_COHERENT_MEMORY` is set.
+    disabled_memory_types: u32,
+
+    /// Bytes allocated in `DEVICE_LOCAL` memory types, for `Allocator::peak_usage`.
+    device_local_usage: usage::UsageCounter,
+    /// See `AllocatorCreateInfo::track_peak_usage`.
+    track_peak_usage: bool,
+
+    /// Memory types removed from every allocation, see `AllocatorCreateInfo::excluded_memory_types`.
+    excluded_memory_types: u32,
+
+    /// `AllocatorCreateInfo::preferred_large_heap_block_size`, 0 for VMA's default.
+    preferred_large_heap_block_size: vk::DeviceSize,
+
+    /// `vkGetBufferMemoryRequirements2` and `vkGetImageMemoryRequirements2`, if they can be
+    /// queried for `ash::vk::MemoryDedicatedRequirements`, for `AllocationCreateInfo::prefer_dedicated`.
+    memory_requirements2: Option<(
+        vk::PFN_vkGetBufferMemoryRequirements2,
+        vk::PFN_vkGetImageMemoryRequirements2,
+    )>,
+
+    /// What the wrapper remembers about live allocations, such as tags and chained pools.
+    allocation_states: allocation_state::AllocationStates,
+
+    /// Custom pools whose memory can be exported, for `Allocator::export_memory_info`.
+    exportable: export::ExportablePools,
+
+    /// Custom pools created by `Allocator::create_pool` and not destroyed yet, in creation order,
+    /// with the create info they were created from.
+    pools: std::sync::Mutex<Vec<(AllocatorPool, AllocatorPoolCreateInfo)>>,
+
+    /// Memory blocks allocated by VMA, filled by the device memory callbacks.
+    device_memory: Box<device_memory::DeviceMemoryBlocks>,
+
+    /// CPU allocation callbacks VMA was created with, boxed to keep their address stable.
+    /// Also used for the Vulkan objects this wrapper creates itself, as VMA does.
+    allocation_callbacks: Option<Box<vk::AllocationCallbacks>>,
+
+    /// Set by `Allocator::destroy_allocator`, checked in debug builds before every VMA call.
+    destroyed: std::sync::atomic::AtomicBool,
+}
 
 // Allocator is internally thread safe unless AllocatorCreateFlags::EXTERNALLY_SYNCHRONIZED is used (then you need to add synchronization!)
 unsafe impl Send for Allocator {}
 unsafe impl Sync for Allocator {}
 
+impl std::fmt::Debug for Allocator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Allocator")
+            .field("internal", &self.internal)
+            .field("device", &self.device.handle())
+            .finish()
+    }
+}
+
 /// Represents custom memory pool handle.
 ///
 /// Fill structure `AllocatorPoolCreateInfo` and call `Allocator::create_pool` to create it.
 /// Call `Allocator::destroy_pool` to destroy it.
-#[repr(transparent)]
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Hash)]
-pub struct AllocatorPool(ffi::VmaPool);
+///
+/// Pools are compared and hashed by their VMA handle only.
+#[derive(Debug, Clone, Copy)]
+pub struct AllocatorPool {
+    handle: ffi::VmaPool,
+    // Recorded when the pool is created, `None` for pools wrapped with `AllocatorPool::from_raw`.
+    constraints: Option<AllocationConstraints>,
+}
 
 unsafe impl Send for AllocatorPool {}
 unsafe impl Sync for AllocatorPool {}
 
+impl AllocatorPool {
+    /// Raw VMA handle of the pool, e.g. to pass it to C or C++ code sharing the allocator.
+    pub fn as_raw(&self) -> ffi::VmaPool {
+        self.handle
+    }
+
+    /// Wraps a raw VMA pool handle, e.g. received from C or C++ code sharing the allocator.
+    ///
+    /// This doesn't take ownership: the pool must have been created by the same VMA allocator,
+    /// and it is destroyed by whichever side calls `vmaDestroyPool` or `Allocator::destroy_pool`,
+    /// exactly once. Pools created outside of this wrapper are not known to `Allocator::pools`,
+    /// and have no `AllocatorPool::allocation_constraints`.
+    pub fn from_raw(pool: ffi::VmaPool) -> Self {
+        AllocatorPool {
+            handle: pool,
+            constraints: None,
+        }
+    }
+
+    /// Returns which allocation flags the algorithm of this pool supports, or `None` if the pool
+    /// was not created by the wrapper, e.g. one wrapped with `AllocatorPool::from_raw`.
+    ///
+    /// Allocating functions check `AllocationCreateInfo::flags` against these constraints when
+    /// `AllocationCreateInfo::pool` is set, and fail with `Error::IncompatibleFlags` instead of
+    /// hitting a VMA assertion. Allocations from pools without constraints are not checked.
+    pub fn allocation_constraints(&self) -> Option<AllocationConstraints> {
+        self.constraints
+    }
+}
+
+impl PartialEq for AllocatorPool {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl Eq for AllocatorPool {}
+
+impl PartialOrd for AllocatorPool {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AllocatorPool {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.handle.cmp(&other.handle)
+    }
+}
+
+impl std::hash::Hash for AllocatorPool {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.handle.hash(state);
+    }
+}
+
 /// Represents single memory allocation.
 ///
 /// It may be either dedicated block of `ash::vk::DeviceMemory` or a specific region of a
@@ -51,6 +258,35 @@ pub struct Allocation(ffi::VmaAllocation);
 unsafe impl Send for Allocation {}
 unsafe impl Sync for Allocation {}
 
+impl Allocation {
+    /// Raw VMA handle of the allocation, e.g. to pass it to C or C++ code sharing the allocator.
+    pub fn as_raw(&self) -> ffi::VmaAllocation {
+        self.0
+    }
+
+    /// Wraps a raw VMA allocation handle, e.g. received from C or C++ code sharing the
+    /// allocator.
+    ///
+    /// This doesn't take ownership: the allocation must have been made by the same VMA
+    /// allocator, and it is freed by whichever side calls `vmaFreeMemory` or
+    /// `Allocator::free_memory`, exactly once. Allocations made outside of this wrapper are not
+    /// tracked by it, e.g. by `Allocator::peak_usage`.
+    pub fn from_raw(allocation: ffi::VmaAllocation) -> Self {
+        Allocation(allocation)
+    }
+
+    /// The null allocation, e.g. as a placeholder in arrays of allocations. Freeing it does
+    /// nothing.
+    pub fn null() -> Self {
+        Allocation(std::ptr::null_mut())
+    }
+
+    /// Returns `true` if this is the null allocation.
+    pub fn is_null(&self) -> bool {
+        self.0.is_null()
+    }
+}
+
 /// Parameters of `Allocation` objects, that can be retrieved using `Allocator::get_allocation_info`.
 #[repr(transparent)]
 #[derive(Debug, Clone, Copy)]
@@ -66,6 +302,27 @@ impl AllocationInfo {
         self.0.memoryType
     }
 
+    /// Returns `true` if this allocation lives in `ash::vk::MemoryPropertyFlags::HOST_COHERENT` memory
+    /// of `allocator`, so flushing and invalidating it is not needed.
+    ///
+    /// This is answered from flags cached at allocator creation, without calling into VMA.
+    #[inline(always)]
+    pub fn is_host_coherent(&self, allocator: &Allocator) -> bool {
+        allocator.coherent_memory_types & (1 << self.0.memoryType) != 0
+    }
+
+    /// Returns `true` if this allocation lives in `ash::vk::MemoryPropertyFlags::HOST_VISIBLE` memory
+    /// of `allocator`, so it can be mapped and written directly instead of through a staging
+    /// buffer and a transfer.
+    ///
+    /// This is answered from flags cached at allocator creation, without calling into VMA.
+    #[inline(always)]
+    pub fn is_host_visible(&self, allocator: &Allocator) -> bool {
+        (allocator.coherent_memory_types | allocator.non_coherent_memory_types)
+            & (1 << self.0.memoryType)
+            != 0
+    }
+
     /// Handle to Vulkan memory object.
     ///
     /// Same memory object can be shared by multiple allocations.
@@ -84,19 +341,52 @@ impl AllocationInfo {
     ///
     /// It can change after call to `Allocator::defragment` if this allocation is passed
     /// to the function, or if allocation is lost.
+    ///
+    /// The value is truncated if it does not fit into `usize`, use `AllocationInfo::device_offset` instead
+    /// on targets where `usize` is narrower than `ash::vk::DeviceSize`.
+    #[deprecated(
+        since = "0.3.0",
+        note = "Truncates offsets that don't fit into `usize`. Use `AllocationInfo::device_offset` instead."
+    )]
     #[inline(always)]
     pub fn offset(&self) -> usize {
         self.0.offset as usize
     }
 
+    /// Same as `AllocationInfo::offset`, as the `ash::vk::DeviceSize` reported by VMA.
+    #[inline(always)]
+    pub fn device_offset(&self) -> vk::DeviceSize {
+        self.0.offset
+    }
+
     /// Size of this allocation, in bytes.
     ///
     /// It never changes, unless allocation is lost.
+    ///
+    /// The value is truncated if it does not fit into `usize`, use `AllocationInfo::device_size` instead
+    /// on targets where `usize` is narrower than `ash::vk::DeviceSize`.
+    #[deprecated(
+        since = "0.3.0",
+        note = "Truncates sizes that don't fit into `usize`. Use `AllocationInfo::device_size` instead."
+    )]
     #[inline(always)]
     pub fn size(&self) -> usize {
         self.0.size as usize
     }
 
+    /// Same as `AllocationInfo::size`, as the `ash::vk::DeviceSize` reported by VMA.
+    #[inline(always)]
+    pub fn device_size(&self) -> vk::DeviceSize {
+        self.0.size
+    }
+
+    /// Returns `(device_memory, offset, size)` of this allocation, with offset and size as
+    /// `ash::vk::DeviceSize`, ready to be used in Vulkan structures without casts.
+    #[inline(always)]
+    pub fn memory_range(&self) -> (vk::DeviceMemory, vk::DeviceSize, vk::DeviceSize) {
+        (self.0.deviceMemory, self.0.offset, self.0.size)
+    }
+
     /// Pointer to the beginning of this allocation as mapped data.
     ///
     /// If the allocation hasn't been mapped using `Allocator::map_memory` and hasn't been
@@ -276,7 +566,7 @@ pub struct AllocatorCreateInfo<'a> {
     ///
     /// If not empty, it must contain `ash::vk::PhysicalDeviceMemoryProperties::memory_heap_count` elements,
     /// defining limit on maximum number of bytes that can be allocated out of particular Vulkan
-    /// memory heap.
+    /// memory heap. `Allocator::new` fails with `Error::HeapLimitsLengthMismatch` otherwise.
     ///
     /// Any of the elements may be equal to `ash::vk::WHOLE_SIZE`, which means no limit on that
     /// heap. This is also the default in case of an empty slice.
@@ -304,6 +594,228 @@ pub struct AllocatorCreateInfo<'a> {
     /// 1.0, 1.1, 1.2 are supported by the current implementation.
     /// Leaving it initialized to zero is equivalent to `VK_API_VERSION_1_0`.
     pub vulkan_api_version: u32,
+
+    /// Detect `vulkan_api_version` from the physical device.
+    ///
+    /// When set, `Allocator::new` reads `ash::vk::PhysicalDeviceProperties::api_version` and uses it,
+    /// capped at the highest version supported by VMA (currently 1.2), instead of `vulkan_api_version`.
+    /// If `vulkan_api_version` is non-zero as well, it is used as an additional upper bound.
+    ///
+    /// The instance must have been created with an `apiVersion` at least as high as the detected version.
+    pub auto_detect_version: bool,
+
+    /// Bit mask of memory types the allocator must never use, e.g. a tiny BAR heap that causes
+    /// allocation churn.
+    ///
+    /// This is a filter of the wrapper, not a VMA feature: the bits are removed from
+    /// `AllocationCreateInfo::memory_type_bits` of every allocation and memory type lookup. If
+    /// no memory type is left, `ash::vk::Result::ERROR_FEATURE_NOT_PRESENT` is returned. Custom
+    /// pools use the memory type they were created with regardless of this mask.
+    pub excluded_memory_types: u32,
+
+    /// Track the bytes allocated in `ash::vk::MemoryPropertyFlags::DEVICE_LOCAL` memory for
+    /// `Allocator::peak_usage`.
+    ///
+    /// Off by default: the size of every device-local allocation is then recorded when it is made
+    /// and looked up when it is freed, under a lock shared by all threads.
+    pub track_peak_usage: bool,
+}
+
+impl<'a> AllocatorCreateInfo<'a> {
+    /// Starts building an `AllocatorCreateInfo` for the given mandatory handles.
+    ///
+    /// All other members start with their default values: no flags, default block size,
+    /// no callbacks and no heap limits, and Vulkan 1.0.
+    pub fn builder(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: ash::vk::PhysicalDevice,
+    ) -> AllocatorCreateInfoBuilder<'a> {
+        AllocatorCreateInfoBuilder {
+            inner: AllocatorCreateInfo {
+                flags: AllocatorCreateFlags::NONE,
+                physical_device,
+                device: device.clone(),
+                instance: instance.clone(),
+                preferred_large_heap_block_size: 0,
+                allocation_callbacks: None,
+                frame_in_use_count: 0,
+                heap_size_limits: None,
+                vulkan_api_version: 0,
+                auto_detect_version: false,
+                excluded_memory_types: 0,
+                track_peak_usage: false,
+            },
+        }
+    }
+}
+
+/// Builder for `AllocatorCreateInfo`, see `AllocatorCreateInfo::builder`.
+pub struct AllocatorCreateInfoBuilder<'a> {
+    inner: AllocatorCreateInfo<'a>,
+}
+
+impl<'a> AllocatorCreateInfoBuilder<'a> {
+    /// Sets `AllocatorCreateInfo::flags`.
+    pub fn flags(mut self, flags: AllocatorCreateFlags) -> Self {
+        self.inner.flags = flags;
+        self
+    }
+
+    /// Sets `AllocatorCreateInfo::preferred_large_heap_block_size`.
+    pub fn preferred_large_heap_block_size(mut self, size: vk::DeviceSize) -> Self {
+        self.inner.preferred_large_heap_block_size = size;
+        self
+    }
+
+    /// Sets `AllocatorCreateInfo::allocation_callbacks` to `Some(callbacks)`.
+    pub fn allocation_callbacks(mut self, callbacks: vk::AllocationCallbacks) -> Self {
+        self.inner.allocation_callbacks = Some(callbacks);
+        self
+    }
+
+    /// Sets `AllocatorCreateInfo::frame_in_use_count`.
+    pub fn frame_in_use_count(mut self, count: u32) -> Self {
+        self.inner.frame_in_use_count = count;
+        self
+    }
+
+    /// Sets `AllocatorCreateInfo::heap_size_limits` to `Some(limits)`.
+    pub fn heap_size_limits(mut self, limits: &'a [ash::vk::DeviceSize]) -> Self {
+        self.inner.heap_size_limits = Some(limits);
+        self
+    }
+
+    /// Sets `AllocatorCreateInfo::vulkan_api_version`.
+    pub fn vulkan_api_version(mut self, version: u32) -> Self {
+        self.inner.vulkan_api_version = version;
+        self
+    }
+
+    /// Sets `AllocatorCreateInfo::auto_detect_version`.
+    pub fn auto_detect_version(mut self, auto_detect: bool) -> Self {
+        self.inner.auto_detect_version = auto_detect;
+        self
+    }
+
+    /// Sets `AllocatorCreateInfo::excluded_memory_types`.
+    pub fn excluded_memory_types(mut self, memory_types: u32) -> Self {
+        self.inner.excluded_memory_types = memory_types;
+        self
+    }
+
+    /// Sets `AllocatorCreateInfo::track_peak_usage`.
+    pub fn track_peak_usage(mut self, track: bool) -> Self {
+        self.inner.track_peak_usage = track;
+        self
+    }
+
+    /// Returns the configured `AllocatorCreateInfo`.
+    pub fn build(self) -> AllocatorCreateInfo<'a> {
+        self.inner
+    }
+}
+
+impl<'a> std::ops::Deref for AllocatorCreateInfoBuilder<'a> {
+    type Target = AllocatorCreateInfo<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// `VMA_DEBUG_MARGIN` as defined by the build script, i.e. the bytes VMA reserves before and
+/// after every allocation in a block.
+#[cfg(feature = "detect_corruption")]
+const DEBUG_MARGIN: vk::DeviceSize = 16;
+#[cfg(not(feature = "detect_corruption"))]
+const DEBUG_MARGIN: vk::DeviceSize = 0;
+
+/// Highest Vulkan version supported by the vendored VMA.
+const MAX_SUPPORTED_API_VERSION: u32 = vk::API_VERSION_1_2;
+
+/// Returns the API version to pass to VMA for the given create info.
+unsafe fn detect_vulkan_api_version(create_info: &AllocatorCreateInfo) -> u32 {
+    if !create_info.auto_detect_version {
+        return create_info.vulkan_api_version;
+    }
+
+    let device_version = create_info
+        .instance
+        .get_physical_device_properties(create_info.physical_device)
+        .api_version;
+    let mut version = device_version.min(MAX_SUPPORTED_API_VERSION);
+    if create_info.vulkan_api_version != 0 {
+        version = version.min(create_info.vulkan_api_version);
+    }
+
+    // Only major and minor versions are considered by VMA.
+    vk::make_api_version(
+        0,
+        vk::api_version_major(version),
+        vk::api_version_minor(version),
+        0,
+    )
+}
+
+/// Routes the `VK_KHR_get_memory_requirements2` and `VK_KHR_bind_memory2` entry points of a
+/// Vulkan 1.0 `device` into `functions`.
+///
+/// `ash::Device` only loads these under their Vulkan 1.1 core names, which a 1.0 device does not
+/// expose, leaving stubs that panic when called. The extension aliases are loaded instead where
+/// available. If they are not, the stubs are kept, as VMA only calls them when `flags` request
+/// the extensions, in which case `ash::vk::Result::ERROR_EXTENSION_NOT_PRESENT` is returned.
+unsafe fn route_khr_functions(
+    instance: &ash::Instance,
+    device: &ash::Device,
+    flags: AllocatorCreateFlags,
+    functions: &mut ffi::VmaVulkanFunctions,
+) -> VkResult<()> {
+    type VoidFunction = unsafe extern "system" fn();
+    let load = |name: &[u8]| {
+        instance.get_device_proc_addr(
+            device.handle(),
+            name.as_ptr() as *const std::os::raw::c_char,
+        )
+    };
+
+    match (
+        load(b"vkGetBufferMemoryRequirements2KHR\0"),
+        load(b"vkGetImageMemoryRequirements2KHR\0"),
+    ) {
+        (Some(get_buffer_memory_requirements2), Some(get_image_memory_requirements2)) => {
+            functions.vkGetBufferMemoryRequirements2KHR =
+                mem::transmute::<VoidFunction, vk::PFN_vkGetBufferMemoryRequirements2>(
+                    get_buffer_memory_requirements2,
+                );
+            functions.vkGetImageMemoryRequirements2KHR =
+                mem::transmute::<VoidFunction, vk::PFN_vkGetImageMemoryRequirements2>(
+                    get_image_memory_requirements2,
+                );
+        }
+        _ if flags.contains(AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION) => {
+            return Err(vk::Result::ERROR_EXTENSION_NOT_PRESENT);
+        }
+        _ => {}
+    }
+
+    match (
+        load(b"vkBindBufferMemory2KHR\0"),
+        load(b"vkBindImageMemory2KHR\0"),
+    ) {
+        (Some(bind_buffer_memory2), Some(bind_image_memory2)) => {
+            functions.vkBindBufferMemory2KHR =
+                mem::transmute::<VoidFunction, vk::PFN_vkBindBufferMemory2>(bind_buffer_memory2);
+            functions.vkBindImageMemory2KHR =
+                mem::transmute::<VoidFunction, vk::PFN_vkBindImageMemory2>(bind_image_memory2);
+        }
+        _ if flags.contains(AllocatorCreateFlags::KHR_BIND_MEMORY2) => {
+            return Err(vk::Result::ERROR_EXTENSION_NOT_PRESENT);
+        }
+        _ => {}
+    }
+
+    Ok(())
 }
 
 /// Converts a raw result into an ash result.
@@ -315,10 +827,66 @@ fn ffi_to_result(result: vk::Result) -> VkResult<()> {
     }
 }
 
+/// Converts a host-side size into a `vk::DeviceSize`.
+///
+/// `usize::MAX`, which is what `vk::WHOLE_SIZE as usize` yields on every target, maps back
+/// to `vk::WHOLE_SIZE` instead of `u32::MAX` on 32-bit targets.
+#[inline]
+fn host_size_to_device(size: usize) -> vk::DeviceSize {
+    if size == usize::MAX {
+        vk::WHOLE_SIZE
+    } else {
+        size as vk::DeviceSize
+    }
+}
+
+/// Converts a host-side range of an allocation into `vk::DeviceSize`s, failing with
+/// `Error::SizeOverflow` if the end of the range does not fit into a `vk::DeviceSize`.
+#[inline]
+fn host_range_to_device(
+    offset: usize,
+    size: usize,
+) -> Result<(vk::DeviceSize, vk::DeviceSize), Error> {
+    let offset = offset as vk::DeviceSize;
+    let size = host_size_to_device(size);
+    if size != vk::WHOLE_SIZE && offset.checked_add(size).is_none() {
+        return Err(Error::SizeOverflow);
+    }
+    Ok((offset, size))
+}
+
+/// Converts a `vk::DeviceSize` into a host-side size, failing with `Error::SizeOverflow` if it
+/// does not fit into the host address space.
+#[inline]
+fn device_size_to_host(size: vk::DeviceSize) -> Result<usize, Error> {
+    use std::convert::TryFrom;
+    usize::try_from(size).map_err(|_| Error::SizeOverflow)
+}
+
 /// Converts an `AllocationCreateInfo` struct into the raw representation.
-fn allocation_create_info_to_ffi(info: &AllocationCreateInfo) -> ffi::VmaAllocationCreateInfo {
-    ffi::VmaAllocationCreateInfo {
-        flags: info.flags.bits(),
+///
+/// The memory types in `excluded_memory_types` are removed from `memory_type_bits`, failing with
+/// `ash::vk::Result::ERROR_FEATURE_NOT_PRESENT` if none is left.
+fn allocation_create_info_to_ffi(
+    info: &AllocationCreateInfo,
+    excluded_memory_types: u32,
+) -> VkResult<ffi::VmaAllocationCreateInfo> {
+    let memory_type_bits = if excluded_memory_types == 0 || info.pool.is_some() {
+        info.memory_type_bits
+    } else {
+        // 0 means any memory type to VMA.
+        let allowed = match info.memory_type_bits {
+            0 => !0,
+            bits => bits,
+        } & !excluded_memory_types;
+        if allowed == 0 {
+            return Err(vk::Result::ERROR_FEATURE_NOT_PRESENT);
+        }
+        allowed
+    };
+
+    Ok(ffi::VmaAllocationCreateInfo {
+        flags: info.ffi_flags().bits(),
         usage: match info.usage {
             MemoryUsage::Unknown => ffi::VmaMemoryUsage_VMA_MEMORY_USAGE_UNKNOWN,
             MemoryUsage::GpuOnly => ffi::VmaMemoryUsage_VMA_MEMORY_USAGE_GPU_ONLY,
@@ -332,13 +900,78 @@ fn allocation_create_info_to_ffi(info: &AllocationCreateInfo) -> ffi::VmaAllocat
         },
         requiredFlags: info.required_flags,
         preferredFlags: info.preferred_flags,
-        memoryTypeBits: info.memory_type_bits,
+        memoryTypeBits: memory_type_bits,
         pool: match info.pool {
-            Some(pool) => pool.0 as _,
+            Some(pool) => pool.handle as _,
             None => unsafe { mem::zeroed() },
         },
         pUserData: info.user_data.unwrap_or(::std::ptr::null_mut()),
         priority: info.priority,
+    })
+}
+
+/// Rejects images with a zero extent, mip level count or array layer count with
+/// `Error::InvalidImageExtent`, before VMA gets to create them.
+fn validate_image_info(image_info: &vk::ImageCreateInfo) -> Result<(), Error> {
+    let extent = image_info.extent;
+    if extent.width == 0
+        || extent.height == 0
+        || extent.depth == 0
+        || image_info.mip_levels == 0
+        || image_info.array_layers == 0
+    {
+        return Err(Error::InvalidImageExtent);
+    }
+    Ok(())
+}
+
+/// Aspects of an image of `format` that `vkCmdCopyImage` has to copy.
+///
+/// Multi-planar formats are not supported and are treated as color formats.
+fn format_aspect_mask(format: vk::Format) -> vk::ImageAspectFlags {
+    match format {
+        vk::Format::D16_UNORM | vk::Format::X8_D24_UNORM_PACK32 | vk::Format::D32_SFLOAT => {
+            vk::ImageAspectFlags::DEPTH
+        }
+        vk::Format::S8_UINT => vk::ImageAspectFlags::STENCIL,
+        vk::Format::D16_UNORM_S8_UINT
+        | vk::Format::D24_UNORM_S8_UINT
+        | vk::Format::D32_SFLOAT_S8_UINT => {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        }
+        _ => vk::ImageAspectFlags::COLOR,
+    }
+}
+
+/// One `ash::vk::ImageCopy` region per mip level, covering all array layers of `image_info`.
+fn image_copy_regions(image_info: &vk::ImageCreateInfo) -> Vec<vk::ImageCopy> {
+    let subresource = |mip_level| vk::ImageSubresourceLayers {
+        aspect_mask: format_aspect_mask(image_info.format),
+        mip_level,
+        base_array_layer: 0,
+        layer_count: image_info.array_layers,
+    };
+    (0..image_info.mip_levels)
+        .map(|mip_level| vk::ImageCopy {
+            src_subresource: subresource(mip_level),
+            src_offset: vk::Offset3D::default(),
+            dst_subresource: subresource(mip_level),
+            dst_offset: vk::Offset3D::default(),
+            extent: vk::Extent3D {
+                width: (image_info.extent.width >> mip_level).max(1),
+                height: (image_info.extent.height >> mip_level).max(1),
+                depth: (image_info.extent.depth >> mip_level).max(1),
+            },
+        })
+        .collect()
+}
+
+/// Converts the result of an incremental defragmentation call, for which `NOT_READY` and
+/// `INCOMPLETE` only mean that there is more to move.
+fn incremental_defragmentation_result(result: vk::Result) -> VkResult<()> {
+    match result {
+        vk::Result::SUCCESS | vk::Result::NOT_READY | vk::Result::INCOMPLETE => Ok(()),
+        _ => Err(result),
     }
 }
 
@@ -426,6 +1059,55 @@ pub enum MemoryUsage {
     GpuLazilyAllocated,
 }
 
+impl MemoryUsage {
+    /// Returns the `AllocationCreateFlags` that usually go together with this usage.
+    ///
+    /// Memory of the host-visible usages `CpuOnly`, `CpuToGpu` and `GpuToCpu` is meant to be
+    /// accessed through a pointer, so they get `AllocationCreateFlags::MAPPED` and
+    /// `AllocationInfo::mapped_data` is set right away. Note that usage alone never maps
+    /// anything. `GpuLazilyAllocated` gets `AllocationCreateFlags::DEDICATED_MEMORY`, which
+    /// it implies anyway. The other usages need no flags.
+    pub fn recommended_flags(&self) -> AllocationCreateFlags {
+        match self {
+            MemoryUsage::CpuOnly | MemoryUsage::CpuToGpu | MemoryUsage::GpuToCpu => {
+                AllocationCreateFlags::MAPPED
+            }
+            MemoryUsage::GpuLazilyAllocated => AllocationCreateFlags::DEDICATED_MEMORY,
+            MemoryUsage::Unknown | MemoryUsage::GpuOnly | MemoryUsage::CpuCopy => {
+                AllocationCreateFlags::NONE
+            }
+        }
+    }
+}
+
+/// Memory type picked by `Allocator::find_memory_type`, with its properties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryTypeChoice {
+    /// Index of the memory type, as returned by `Allocator::find_memory_type_index`.
+    pub index: u32,
+
+    /// Property flags of the memory type.
+    pub properties: vk::MemoryPropertyFlags,
+
+    /// Index of the memory heap the memory type belongs to.
+    pub heap: u32,
+}
+
+impl MemoryTypeChoice {
+    /// Returns `true` if the memory type is `ash::vk::MemoryPropertyFlags::HOST_VISIBLE`, i.e. it
+    /// can be mapped.
+    pub fn is_host_visible(&self) -> bool {
+        self.properties
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+    }
+
+    /// Returns `true` if the memory type is `ash::vk::MemoryPropertyFlags::DEVICE_LOCAL`.
+    pub fn is_device_local(&self) -> bool {
+        self.properties
+            .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+    }
+}
+
 bitflags! {
     /// Flags for configuring `AllocatorPool` construction.
     pub struct AllocatorPoolCreateFlags: u32 {
@@ -534,6 +1216,7 @@ bitflags! {
         /// Allocation will be created from upper stack in a double stack pool.
         ///
         /// This flag is only allowed for custom pools created with `AllocatorPoolCreateFlags::LINEAR_ALGORITHM` flag.
+        /// See `DoubleStackPool` for a helper managing such a pool.
         const UPPER_ADDRESS = 0x0000_0040;
 
         /// Create both buffer/image and allocation, but don't bind them together.
@@ -571,10 +1254,57 @@ bitflags! {
     }
 }
 
+/// Strategy used to search for a free range of memory for an allocation.
+///
+/// Each variant maps to exactly one of the `AllocationCreateFlags::STRATEGY_*` bits, several of
+/// which share a value, so at most one strategy can be selected at a time.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AllocationStrategy {
+    /// Chooses the smallest possible free range for the allocation.
+    BestFit,
+
+    /// Chooses the biggest possible free range for the allocation.
+    WorstFit,
+
+    /// Chooses the first suitable free range, i.e. the one that is easiest and fastest to find.
+    FirstFit,
+
+    /// Tries to minimize memory usage. Same as `AllocationStrategy::BestFit`.
+    MinMemory,
+
+    /// Tries to minimize allocation time. Same as `AllocationStrategy::FirstFit`.
+    MinTime,
+
+    /// Tries to minimize memory fragmentation. Same as `AllocationStrategy::WorstFit`.
+    MinFragmentation,
+}
+
+impl AllocationStrategy {
+    /// Returns the `AllocationCreateFlags::STRATEGY_*` bit selecting this strategy.
+    pub fn flags(self) -> AllocationCreateFlags {
+        match self {
+            AllocationStrategy::BestFit => AllocationCreateFlags::STRATEGY_BEST_FIT,
+            AllocationStrategy::WorstFit => AllocationCreateFlags::STRATEGY_WORST_FIT,
+            AllocationStrategy::FirstFit => AllocationCreateFlags::STRATEGY_FIRST_FIT,
+            AllocationStrategy::MinMemory => AllocationCreateFlags::STRATEGY_MIN_MEMORY,
+            AllocationStrategy::MinTime => AllocationCreateFlags::STRATEGY_MIN_TIME,
+            AllocationStrategy::MinFragmentation => {
+                AllocationCreateFlags::STRATEGY_MIN_FRAGMENTATION
+            }
+        }
+    }
+}
+
 /// Description of an `Allocation` to be created.
 #[derive(Debug, Clone)]
 pub struct AllocationCreateInfo {
     /// Flags for configuring the allocation
+    ///
+    /// Allocating functions fail with `Error::InvalidFlags` before
+    /// calling into VMA if mutually exclusive flags are combined: `AllocationCreateFlags::MAPPED`
+    /// with `AllocationCreateFlags::CAN_BECOME_LOST`, or `AllocationCreateFlags::DEDICATED_MEMORY`
+    /// with `AllocationCreateFlags::NEVER_ALLOCATE`. `AllocationCreateFlags::UPPER_ADDRESS` is
+    /// rejected as well if `pool` is `None`.
     pub flags: AllocationCreateFlags,
 
     /// Intended usage of memory.
@@ -601,7 +1331,7 @@ pub struct AllocationCreateInfo {
 
     /// Bit mask containing one bit set for every memory type acceptable for this allocation.
     ///
-    /// Value 0 is equivalent to `std::u32::MAX` - it means any memory type is accepted if
+    /// Value 0 is equivalent to `u32::MAX` - it means any memory type is accepted if
     /// it meets other requirements specified by this structure, with no further restrictions
     /// on memory type index.
     ///
@@ -629,6 +1359,50 @@ pub struct AllocationCreateInfo {
     /// and this allocation ends up as dedicated or is explicitly forced as dedicated using #VMA_ALLOCATION_CREATE_DEDICATED_MEMORY_BIT.
     /// Otherwise, it has the priority of a memory block where it is placed and this variable is ignored.
     pub priority: f32,
+
+    /// Honor the driver's dedicated allocation hint for this allocation.
+    ///
+    /// VMA only honors the driver's `prefersDedicatedAllocation` hint allocator-wide, when
+    /// `AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION` is used or `vulkan_api_version` is 1.1+.
+    /// It cannot express a per-allocation preference, so this is emulated by the wrapper, which
+    /// queries `ash::vk::MemoryDedicatedRequirements` of the buffer or image itself:
+    ///
+    /// - if the driver requires a dedicated allocation, `AllocationCreateFlags::DEDICATED_MEMORY`
+    ///   is added;
+    /// - if it prefers one, the allocation is first attempted with
+    ///   `AllocationCreateFlags::DEDICATED_MEMORY`, and if that fails with
+    ///   `ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY` or `ash::vk::Result::ERROR_TOO_MANY_OBJECTS`,
+    ///   it is retried as a regular sub-allocation;
+    /// - otherwise, the allocation is made as if this was `false`.
+    ///
+    /// The hint can't be queried for `Allocator::allocate_memory`, which has no resource, nor on
+    /// Vulkan 1.0 without `AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION`, where chaining the
+    /// structure is not allowed. There, a dedicated block is treated as preferred. Setting
+    /// `AllocationCreateFlags::DEDICATED_MEMORY` directly instead makes the dedicated block
+    /// mandatory.
+    ///
+    /// Used by `Allocator::allocate_memory`, `Allocator::allocate_memory_for_buffer`,
+    /// `Allocator::allocate_memory_for_image`, `Allocator::create_buffer` and `Allocator::create_image`.
+    /// Ignored if `pool` is not `None` or `AllocationCreateFlags::NEVER_ALLOCATE` is used.
+    pub prefer_dedicated: bool,
+
+    /// Strategy used to place the allocation, or `None` to let VMA choose.
+    ///
+    /// Prefer this over the `AllocationCreateFlags::STRATEGY_*` bits. Allocating functions fail with
+    /// `Error::InvalidFlags` if both are used at the same time.
+    pub strategy: Option<AllocationStrategy>,
+
+    /// Fill the memory of a new allocation with zeros, e.g. for security-sensitive staging buffers.
+    ///
+    /// This is emulated by the wrapper. If the allocation ends up in
+    /// `ash::vk::MemoryPropertyFlags::HOST_VISIBLE` memory, the allocating functions map it, write
+    /// zeros over the whole allocation, flush and unmap it before returning, which costs time
+    /// proportional to the allocation size. Other memory is left untouched, except by
+    /// `Allocator::create_buffer_zeroed`, which records a `vkCmdFillBuffer` instead.
+    ///
+    /// Allocations created with `AllocationCreateFlags::CAN_BECOME_LOST` cannot be mapped, and
+    /// fail to allocate when placed in host-visible memory with this set.
+    pub zero_initialize: bool,
 }
 
 /// Construct `AllocationCreateInfo` with default values
@@ -643,41 +1417,195 @@ impl Default for AllocationCreateInfo {
             pool: None,
             user_data: None,
             priority: 0.0,
+            prefer_dedicated: false,
+            strategy: None,
+            zero_initialize: false,
         }
     }
 }
 
-/// Description of an `AllocationPool` to be created.
-#[derive(Debug, Clone)]
-pub struct AllocatorPoolCreateInfo {
-    /// Vulkan memory type index to allocate this pool from.
-    pub memory_type_index: u32,
+impl AllocationCreateInfo {
+    /// Starts building an `AllocationCreateInfo` with default values.
+    pub fn builder() -> AllocationCreateInfoBuilder {
+        AllocationCreateInfoBuilder {
+            inner: Default::default(),
+        }
+    }
 
-    /// Use combination of `AllocatorPoolCreateFlags`
-    pub flags: AllocatorPoolCreateFlags,
+    /// Creates an `AllocationCreateInfo` for `usage` with `MemoryUsage::recommended_flags`,
+    /// and default values otherwise.
+    pub fn for_usage(usage: MemoryUsage) -> Self {
+        AllocationCreateInfo {
+            usage,
+            flags: usage.recommended_flags(),
+            ..Default::default()
+        }
+    }
 
-    /// Size of a single `ash::vk::DeviceMemory` block to be allocated as part of this
-    /// pool, in bytes.
-    ///
-    /// Specify non-zero to set explicit, constant size of memory blocks used by
-    /// this pool.
-    ///
-    /// Leave 0 to use default and let the library manage block sizes automatically.
-    /// Sizes of particular blocks may vary.
-    pub block_size: usize,
+    /// Checks this description for inconsistent combinations of members.
+    ///
+    /// Returns `Error::ConflictingPool` if `pool` is set together with any of `usage`,
+    /// `required_flags`, `preferred_flags` or `memory_type_bits`, which would be silently
+    /// ignored, and `Error::InvalidFlags` if `flags` are invalid, see
+    /// `AllocationCreateInfo::validate_flags`.
+    fn validate(&self) -> Result<(), Error> {
+        if self.pool.is_some()
+            && (self.usage != MemoryUsage::Unknown
+                || !self.required_flags.is_empty()
+                || !self.preferred_flags.is_empty()
+                || self.memory_type_bits != 0)
+        {
+            return Err(Error::ConflictingPool);
+        }
+        self.validate_flags()
+    }
 
-    /// Minimum number of blocks to be always allocated in this pool, even if they stay empty.
-    ///
-    /// Set to 0 to have no preallocated blocks and allow the pool be completely empty.
-    pub min_block_count: usize,
+    /// Rejects combinations of `flags` that VMA does not support or that can never succeed
+    /// with `Error::InvalidFlags`.
+    fn validate_flags(&self) -> Result<(), Error> {
+        let exclusive = [
+            AllocationCreateFlags::MAPPED | AllocationCreateFlags::CAN_BECOME_LOST,
+            AllocationCreateFlags::DEDICATED_MEMORY | AllocationCreateFlags::NEVER_ALLOCATE,
+        ];
+        if exclusive.iter().any(|&pair| self.flags.contains(pair)) {
+            return Err(Error::InvalidFlags);
+        }
+        if self.strategy.is_some() && self.flags.intersects(AllocationCreateFlags::STRATEGY_MASK) {
+            return Err(Error::InvalidFlags);
+        }
+        // Default pools never use the linear algorithm, so they have no upper stack.
+        if self.flags.contains(AllocationCreateFlags::UPPER_ADDRESS) && self.pool.is_none() {
+            return Err(Error::InvalidFlags);
+        }
+        Ok(())
+    }
 
-    /// Maximum number of blocks that can be allocated in this pool.
-    ///
-    /// Set to 0 to use default, which is no limit.
-    ///
-    /// Set to same value as `AllocatorPoolCreateInfo::min_block_count` to have fixed amount
-    /// of memory allocated throughout whole lifetime of this pool.
-    pub max_block_count: usize,
+    /// Returns the flags passed to VMA, with `strategy` applied.
+    fn ffi_flags(&self) -> AllocationCreateFlags {
+        match self.strategy {
+            Some(strategy) => {
+                (self.flags - AllocationCreateFlags::STRATEGY_MASK) | strategy.flags()
+            }
+            None => self.flags,
+        }
+    }
+}
+
+/// Builder for `AllocationCreateInfo`, see `AllocationCreateInfo::builder`.
+#[derive(Debug, Clone)]
+pub struct AllocationCreateInfoBuilder {
+    inner: AllocationCreateInfo,
+}
+
+impl AllocationCreateInfoBuilder {
+    /// Sets `AllocationCreateInfo::flags`.
+    pub fn flags(mut self, flags: AllocationCreateFlags) -> Self {
+        self.inner.flags = flags;
+        self
+    }
+
+    /// Sets `AllocationCreateInfo::usage`.
+    pub fn usage(mut self, usage: MemoryUsage) -> Self {
+        self.inner.usage = usage;
+        self
+    }
+
+    /// Sets `AllocationCreateInfo::required_flags`.
+    pub fn required_flags(mut self, flags: ash::vk::MemoryPropertyFlags) -> Self {
+        self.inner.required_flags = flags;
+        self
+    }
+
+    /// Sets `AllocationCreateInfo::preferred_flags`.
+    pub fn preferred_flags(mut self, flags: ash::vk::MemoryPropertyFlags) -> Self {
+        self.inner.preferred_flags = flags;
+        self
+    }
+
+    /// Sets `AllocationCreateInfo::memory_type_bits`.
+    pub fn memory_type_bits(mut self, bits: u32) -> Self {
+        self.inner.memory_type_bits = bits;
+        self
+    }
+
+    /// Sets `AllocationCreateInfo::pool` to `Some(pool)`.
+    pub fn pool(mut self, pool: AllocatorPool) -> Self {
+        self.inner.pool = Some(pool);
+        self
+    }
+
+    /// Sets `AllocationCreateInfo::user_data` to `Some(user_data)`.
+    pub fn user_data(mut self, user_data: *mut ::std::os::raw::c_void) -> Self {
+        self.inner.user_data = Some(user_data);
+        self
+    }
+
+    /// Sets `AllocationCreateInfo::priority`.
+    pub fn priority(mut self, priority: f32) -> Self {
+        self.inner.priority = priority;
+        self
+    }
+
+    /// Sets `AllocationCreateInfo::prefer_dedicated`.
+    pub fn prefer_dedicated(mut self, prefer_dedicated: bool) -> Self {
+        self.inner.prefer_dedicated = prefer_dedicated;
+        self
+    }
+
+    /// Sets `AllocationCreateInfo::strategy` to `Some(strategy)`.
+    pub fn strategy(mut self, strategy: AllocationStrategy) -> Self {
+        self.inner.strategy = Some(strategy);
+        self
+    }
+
+    /// Sets `AllocationCreateInfo::zero_initialize`.
+    pub fn zero_initialize(mut self, zero_initialize: bool) -> Self {
+        self.inner.zero_initialize = zero_initialize;
+        self
+    }
+
+    /// Returns the configured `AllocationCreateInfo`.
+    ///
+    /// Fails with `Error::ConflictingPool` if `pool` is set together with e.g. `usage`, and
+    /// with `Error::InvalidFlags` if e.g. `AllocationCreateFlags::MAPPED` is combined with
+    /// `AllocationCreateFlags::CAN_BECOME_LOST`.
+    pub fn build(self) -> Result<AllocationCreateInfo, Error> {
+        self.inner.validate()?;
+        Ok(self.inner)
+    }
+}
+
+/// Description of an `AllocationPool` to be created.
+#[derive(Debug, Clone)]
+pub struct AllocatorPoolCreateInfo {
+    /// Vulkan memory type index to allocate this pool from.
+    pub memory_type_index: u32,
+
+    /// Use combination of `AllocatorPoolCreateFlags`
+    pub flags: AllocatorPoolCreateFlags,
+
+    /// Size of a single `ash::vk::DeviceMemory` block to be allocated as part of this
+    /// pool, in bytes.
+    ///
+    /// Specify non-zero to set explicit, constant size of memory blocks used by
+    /// this pool.
+    ///
+    /// Leave 0 to use default and let the library manage block sizes automatically.
+    /// Sizes of particular blocks may vary.
+    pub block_size: usize,
+
+    /// Minimum number of blocks to be always allocated in this pool, even if they stay empty.
+    ///
+    /// Set to 0 to have no preallocated blocks and allow the pool be completely empty.
+    pub min_block_count: usize,
+
+    /// Maximum number of blocks that can be allocated in this pool.
+    ///
+    /// Set to 0 to use default, which is no limit.
+    ///
+    /// Set to same value as `AllocatorPoolCreateInfo::min_block_count` to have fixed amount
+    /// of memory allocated throughout whole lifetime of this pool.
+    pub max_block_count: usize,
 
     /// Maximum number of additional frames that are in use at the same time as current frame.
     /// This value is used only when you make allocations with `AllocationCreateFlags::CAN_BECOME_LOST` flag.
@@ -734,11 +1662,118 @@ impl Default for AllocatorPoolCreateInfo {
     }
 }
 
+impl AllocatorPoolCreateInfo {
+    /// Starts building an `AllocatorPoolCreateInfo` with default values.
+    pub fn builder() -> AllocatorPoolCreateInfoBuilder {
+        AllocatorPoolCreateInfoBuilder {
+            inner: Default::default(),
+        }
+    }
+}
+
+/// Builder for `AllocatorPoolCreateInfo`, see `AllocatorPoolCreateInfo::builder`.
+#[derive(Debug, Clone)]
+pub struct AllocatorPoolCreateInfoBuilder {
+    inner: AllocatorPoolCreateInfo,
+}
+
+impl AllocatorPoolCreateInfoBuilder {
+    /// Sets `AllocatorPoolCreateInfo::memory_type_index`.
+    pub fn memory_type_index(mut self, index: u32) -> Self {
+        self.inner.memory_type_index = index;
+        self
+    }
+
+    /// Sets `AllocatorPoolCreateInfo::flags`.
+    pub fn flags(mut self, flags: AllocatorPoolCreateFlags) -> Self {
+        self.inner.flags = flags;
+        self
+    }
+
+    /// Sets `AllocatorPoolCreateInfo::block_size`.
+    pub fn block_size(mut self, size: usize) -> Self {
+        self.inner.block_size = size;
+        self
+    }
+
+    /// Sets `AllocatorPoolCreateInfo::min_block_count`.
+    pub fn min_block_count(mut self, count: usize) -> Self {
+        self.inner.min_block_count = count;
+        self
+    }
+
+    /// Sets `AllocatorPoolCreateInfo::max_block_count`.
+    pub fn max_block_count(mut self, count: usize) -> Self {
+        self.inner.max_block_count = count;
+        self
+    }
+
+    /// Sets `AllocatorPoolCreateInfo::frame_in_use_count`.
+    pub fn frame_in_use_count(mut self, count: u32) -> Self {
+        self.inner.frame_in_use_count = count;
+        self
+    }
+
+    /// Sets `AllocatorPoolCreateInfo::priority`.
+    pub fn priority(mut self, priority: f32) -> Self {
+        self.inner.priority = priority;
+        self
+    }
+
+    /// Sets `AllocatorPoolCreateInfo::min_allocation_alignment`.
+    pub fn min_allocation_alignment(mut self, alignment: vk::DeviceSize) -> Self {
+        self.inner.min_allocation_alignment = alignment;
+        self
+    }
+
+    /// Sets `AllocatorPoolCreateInfo::memory_allocate_next` to `Some(next)`.
+    pub fn memory_allocate_next(mut self, next: *mut ::std::os::raw::c_void) -> Self {
+        self.inner.memory_allocate_next = Some(next);
+        self
+    }
+
+    /// Returns the configured `AllocatorPoolCreateInfo`.
+    ///
+    /// Fails with `Error::InvalidPoolCreateInfo` if `min_block_count` exceeds a non-zero
+    /// `max_block_count`, `min_allocation_alignment` is not zero or a power of two, or `priority`
+    /// is outside of `[0, 1]`.
+    pub fn build(self) -> Result<AllocatorPoolCreateInfo, Error> {
+        let info = &self.inner;
+        if (info.max_block_count != 0 && info.min_block_count > info.max_block_count)
+            || (info.min_allocation_alignment != 0
+                && !info.min_allocation_alignment.is_power_of_two())
+            || !(0.0..=1.0).contains(&info.priority)
+        {
+            return Err(Error::InvalidPoolCreateInfo);
+        }
+        Ok(self.inner)
+    }
+}
+
+/// Opaque state of a defragmentation, from `Allocator::defragmentation_begin` until
+/// `Allocator::defragmentation_end`.
+///
+/// The statistics and changed allocations VMA writes into it are only valid once the
+/// defragmentation ended, so they are only exposed through the result of
+/// `Allocator::defragmentation_end`.
 #[derive(Debug)]
 pub struct DefragmentationContext {
-    pub(crate) internal: ffi::VmaDefragmentationContext,
-    pub(crate) stats: ffi::VmaDefragmentationStats,
-    pub(crate) changed: Vec<ash::vk::Bool32>,
+    internal: ffi::VmaDefragmentationContext,
+
+    /// Boxed, because VMA keeps the pointer to it until the defragmentation ends, while the
+    /// context is moved around.
+    stats: Box<ffi::VmaDefragmentationStats>,
+    changed: Vec<ash::vk::Bool32>,
+}
+
+impl DefragmentationContext {
+    unsafe fn new(allocation_count: usize) -> Self {
+        DefragmentationContext {
+            internal: mem::zeroed(),
+            stats: Box::new(mem::zeroed()),
+            changed: vec![ash::vk::FALSE; allocation_count],
+        }
+    }
 }
 
 /// Optional configuration parameters to be passed to `Allocator::defragment`
@@ -754,7 +1789,7 @@ pub struct DefragmentationInfo {
 
     /// Maximum number of allocations that can be moved to different place.
     ///
-    /// Default is `std::u32::MAX`, which means no limit.
+    /// Default is `u32::MAX`, which means no limit.
     pub max_allocations_to_move: u32,
 }
 
@@ -763,7 +1798,7 @@ impl Default for DefragmentationInfo {
     fn default() -> Self {
         DefragmentationInfo {
             max_bytes_to_move: ash::vk::WHOLE_SIZE as usize,
-            max_allocations_to_move: std::u32::MAX,
+            max_allocations_to_move: u32::MAX,
         }
     }
 }
@@ -801,7 +1836,7 @@ pub struct DefragmentationInfo2<'a> {
 
     /// Maximum number of allocations that can be moved to a different place using transfers on CPU side, like `memcpy()`, `memmove()`.
     ///
-    /// `std::u32::MAX` means no limit.
+    /// `u32::MAX` means no limit.
     pub max_cpu_allocations_to_move: u32,
 
     /// Maximum total numbers of bytes that can be copied while moving allocations to different places using transfers on GPU side, posted to `command_buffer`.
@@ -811,7 +1846,7 @@ pub struct DefragmentationInfo2<'a> {
 
     /// Maximum number of allocations that can be moved to a different place using transfers on GPU side, posted to `command_buffer`.
     ///
-    /// `std::u32::MAX` means no limit.
+    /// `u32::MAX` means no limit.
     pub max_gpu_allocations_to_move: u32,
 
     /// Command buffer where GPU copy commands will be posted.
@@ -840,13 +1875,32 @@ pub struct DefragmentationStats {
     pub device_memory_blocks_freed: u32,
 }
 
+impl From<&ffi::VmaDefragmentationStats> for DefragmentationStats {
+    fn from(stats: &ffi::VmaDefragmentationStats) -> Self {
+        DefragmentationStats {
+            bytes_moved: stats.bytesMoved as usize,
+            bytes_freed: stats.bytesFreed as usize,
+            allocations_moved: stats.allocationsMoved,
+            device_memory_blocks_freed: stats.deviceMemoryBlocksFreed,
+        }
+    }
+}
+
 impl Allocator {
     /// Constructor a new `Allocator` using the provided options.
-    pub unsafe fn new(create_info: &AllocatorCreateInfo) -> VkResult<Self> {
+    ///
+    /// On Vulkan 1.0, the functions of `VK_KHR_get_memory_requirements2` and `VK_KHR_bind_memory2`
+    /// are routed to VMA under their extension names. Fails with
+    /// `ash::vk::Result::ERROR_EXTENSION_NOT_PRESENT` if `AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION`
+    /// or `AllocatorCreateFlags::KHR_BIND_MEMORY2` is used but the device does not provide them.
+    ///
+    /// Fails with `Error::HeapLimitsLengthMismatch` if `AllocatorCreateInfo::heap_size_limits` is
+    /// neither empty nor has one element per memory heap.
+    pub unsafe fn new(create_info: &AllocatorCreateInfo) -> Result<Self, Error> {
         let instance = create_info.instance.clone();
         let device = create_info.device.clone();
 
-        let routed_functions = ffi::VmaVulkanFunctions {
+        let mut routed_functions = ffi::VmaVulkanFunctions {
             vkGetPhysicalDeviceProperties: instance.fp_v1_0().get_physical_device_properties,
             vkGetPhysicalDeviceMemoryProperties: instance
                 .fp_v1_0()
@@ -874,12 +1928,42 @@ impl Allocator {
                 .fp_v1_1()
                 .get_physical_device_memory_properties2,
         };
+        let vulkan_api_version = detect_vulkan_api_version(create_info);
+        if vulkan_api_version < vk::API_VERSION_1_1 {
+            route_khr_functions(&instance, &device, create_info.flags, &mut routed_functions)?;
+        }
+        // On Vulkan 1.0, `ash::vk::MemoryDedicatedRequirements` may only be chained if
+        // `VK_KHR_dedicated_allocation` is enabled, which the flag promises.
+        let memory_requirements2 = if vulkan_api_version >= vk::API_VERSION_1_1
+            || create_info
+                .flags
+                .contains(AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION)
+        {
+            Some((
+                routed_functions.vkGetBufferMemoryRequirements2KHR,
+                routed_functions.vkGetImageMemoryRequirements2KHR,
+            ))
+        } else {
+            None
+        };
+
+        // Owned by the allocator, so the pointer given to VMA stays valid for its lifetime.
+        let allocation_callbacks = create_info.allocation_callbacks.map(Box::new);
 
-        let allocation_callbacks = match create_info.allocation_callbacks {
-            None => std::ptr::null(),
-            Some(ref cb) => cb as *const _,
+        let memory_properties =
+            instance.get_physical_device_memory_properties(create_info.physical_device);
+        // VMA reads `memory_heap_count` limits from the pointer without knowing the slice length.
+        let heap_size_limits = match create_info.heap_size_limits {
+            Some([]) => None,
+            Some(limits) if limits.len() != memory_properties.memory_heap_count as usize => {
+                return Err(Error::HeapLimitsLengthMismatch);
+            }
+            limits => limits,
         };
 
+        let device_memory = Box::<device_memory::DeviceMemoryBlocks>::default();
+        let device_memory_callbacks = device_memory.callbacks();
+
         let ffi_create_info = ffi::VmaAllocatorCreateInfo {
             physicalDevice: create_info.physical_device,
             device: create_info.device.handle(),
@@ -887,15 +1971,18 @@ impl Allocator {
             flags: create_info.flags.bits(),
             frameInUseCount: create_info.frame_in_use_count,
             preferredLargeHeapBlockSize: create_info.preferred_large_heap_block_size as u64,
-            pHeapSizeLimit: match &create_info.heap_size_limits {
+            pHeapSizeLimit: match heap_size_limits {
                 None => ::std::ptr::null(),
                 Some(limits) => limits.as_ptr(),
             },
             pVulkanFunctions: &routed_functions,
-            pAllocationCallbacks: allocation_callbacks,
-            pDeviceMemoryCallbacks: ::std::ptr::null(), // TODO: Add support
-            pRecordSettings: ::std::ptr::null(),        // TODO: Add support
-            vulkanApiVersion: create_info.vulkan_api_version,
+            pAllocationCallbacks: match &allocation_callbacks {
+                None => std::ptr::null(),
+                Some(callbacks) => &**callbacks,
+            },
+            pDeviceMemoryCallbacks: &device_memory_callbacks,
+            pRecordSettings: ::std::ptr::null(), // TODO: Add support
+            vulkanApiVersion: vulkan_api_version,
             pTypeExternalMemoryHandleTypes: std::ptr::null(),
         };
 
@@ -905,24 +1992,467 @@ impl Allocator {
             &mut handle,
         ))?;
 
-        Ok(Allocator(handle))
+        let mut coherent_memory_types = 0;
+        let mut non_coherent_memory_types = 0;
+        let mut device_local_memory_types = 0;
+        let mut disabled_memory_types = 0;
+        for (index, memory_type) in memory_properties.memory_types
+            [..memory_properties.memory_type_count as usize]
+            .iter()
+            .enumerate()
+        {
+            let flags = memory_type.property_flags;
+            if flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT) {
+                coherent_memory_types |= 1 << index;
+            } else if flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+                non_coherent_memory_types |= 1 << index;
+            }
+            if flags.contains(vk::MemoryPropertyFlags::DEVICE_LOCAL) {
+                device_local_memory_types |= 1 << index;
+            }
+            if flags.contains(vk::MemoryPropertyFlags::DEVICE_COHERENT_AMD)
+                && !create_info
+                    .flags
+                    .contains(AllocatorCreateFlags::AMD_DEVICE_COHERENT_MEMORY)
+            {
+                disabled_memory_types |= 1 << index;
+            }
+        }
+
+        Ok(Allocator {
+            internal: handle,
+            instance,
+            device,
+            physical_device: create_info.physical_device,
+            allocation_failure_callback: None,
+            allocate_callback: None,
+            coherent_memory_types,
+            non_coherent_memory_types,
+            device_local_memory_types,
+            disabled_memory_types,
+            device_local_usage: Default::default(),
+            track_peak_usage: create_info.track_peak_usage,
+            excluded_memory_types: create_info.excluded_memory_types,
+            preferred_large_heap_block_size: create_info.preferred_large_heap_block_size,
+            memory_requirements2,
+            allocation_states: Default::default(),
+            exportable: Default::default(),
+            pools: Default::default(),
+            device_memory,
+            allocation_callbacks,
+            destroyed: Default::default(),
+        })
+    }
+
+    /// Registers a callback invoked whenever an allocating function of this wrapper fails.
+    ///
+    /// The callback receives the failing `AllocationCreateInfo`, the memory requirements of the
+    /// resource and the returned error. It is called after VMA returned, so it may query the
+    /// allocator, e.g. to dump `Allocator::get_budget` into a crash report. Allocating, freeing or
+    /// mapping memory from the callback panics, as the failed call has not returned yet; without
+    /// this check, retrying the allocation from the callback would recurse endlessly.
+    ///
+    /// For `Allocator::create_buffer` and `Allocator::create_image`, the memory requirements are
+    /// queried from a temporary resource only when the callback is registered and the call failed.
+    /// If they can't be determined, default (zeroed) requirements are passed.
+    ///
+    /// Pass `None` to remove a previously registered callback.
+    pub fn set_allocation_failure_callback(&mut self, callback: Option<AllocationFailureCallback>) {
+        self.allocation_failure_callback = callback;
+    }
+
+    /// Registers a callback invoked after every successful allocation made through this wrapper,
+    /// e.g. to report GPU memory to a profiler such as Tracy.
+    ///
+    /// The callback receives the new allocation, its `AllocationInfo` and its name: the user data
+    /// string if the allocation was created with `AllocationCreateFlags::USER_DATA_COPY_STRING`
+    /// and it is valid UTF-8, `None` otherwise. It is called after VMA returned, when no VMA lock
+    /// is held, so it may query the allocator. Allocating, freeing or mapping memory from the
+    /// callback panics: a new buffer or image is not bound to the allocation yet, and allocating
+    /// would invoke the callback again.
+    ///
+    /// Pass `None` to remove a previously registered callback.
+    pub fn set_allocate_callback(&mut self, callback: Option<AllocateCallback>) {
+        self.allocate_callback = callback;
+    }
+
+    /// Invokes the allocation failure callback, if any, when `result` is an error.
+    unsafe fn check_allocation<T>(
+        &self,
+        result: Result<T, Error>,
+        allocation_info: &AllocationCreateInfo,
+        memory_requirements: impl FnOnce() -> vk::MemoryRequirements,
+    ) -> Result<T, Error> {
+        if let (Err(err), Some(callback)) = (&result, &self.allocation_failure_callback) {
+            let memory_requirements = memory_requirements();
+            let _scope = reentrancy::CallbackScope::enter();
+            callback(self, allocation_info, &memory_requirements, *err);
+        }
+        result
+    }
+
+    /// Calls `allocate` with the raw representation of `info`, after rejecting invalid flags,
+    /// including flags the algorithm of `AllocationCreateInfo::pool` doesn't support.
+    ///
+    /// If `AllocationCreateInfo::prefer_dedicated` applies, the driver's hint is taken from
+    /// `dedicated_requirements`, see `AllocationCreateInfo::prefer_dedicated`. When a dedicated
+    /// allocation is preferred but fails for lack of memory, `allocate` is called once more
+    /// without `AllocationCreateFlags::DEDICATED_MEMORY`.
+    fn allocate_with_dedicated_preference<T>(
+        &self,
+        info: &AllocationCreateInfo,
+        dedicated_requirements: impl FnOnce() -> Option<vk::MemoryDedicatedRequirements>,
+        mut allocate: impl FnMut(&ffi::VmaAllocationCreateInfo) -> VkResult<T>,
+    ) -> Result<T, Error> {
+        reentrancy::assert_outside_callback();
+        info.validate_flags()?;
+        self.validate_pool_flags(info)?;
+        let mut create_info = allocation_create_info_to_ffi(info, self.excluded_memory_types)?;
+        let mut allocate = |create_info: &ffi::VmaAllocationCreateInfo| {
+            allocate(create_info).map_err(Error::Vulkan)
+        };
+        let prefer_dedicated = info.prefer_dedicated
+            && info.pool.is_none()
+            && !info.flags.intersects(
+                AllocationCreateFlags::DEDICATED_MEMORY | AllocationCreateFlags::NEVER_ALLOCATE,
+            );
+        if !prefer_dedicated {
+            return allocate(&create_info);
+        }
+
+        create_info.flags |= AllocationCreateFlags::DEDICATED_MEMORY.bits();
+        match dedicated_requirements() {
+            Some(requirements) if requirements.requires_dedicated_allocation == vk::TRUE => {
+                return allocate(&create_info);
+            }
+            Some(requirements) if requirements.prefers_dedicated_allocation != vk::TRUE => {
+                create_info.flags &= !AllocationCreateFlags::DEDICATED_MEMORY.bits();
+                return allocate(&create_info);
+            }
+            _ => {}
+        }
+        match allocate(&create_info) {
+            Err(Error::Vulkan(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY))
+            | Err(Error::Vulkan(vk::Result::ERROR_TOO_MANY_OBJECTS)) => {
+                create_info.flags &= !AllocationCreateFlags::DEDICATED_MEMORY.bits();
+                allocate(&create_info)
+            }
+            result => result,
+        }
+    }
+
+    /// Queries the driver's dedicated allocation hint for `buffer`, or `None` if it can't be
+    /// queried, see `Allocator::memory_requirements2`.
+    unsafe fn buffer_dedicated_requirements(
+        &self,
+        buffer: vk::Buffer,
+    ) -> Option<vk::MemoryDedicatedRequirements> {
+        let (get_buffer_memory_requirements2, _) = self.memory_requirements2?;
+        let mut dedicated_requirements = vk::MemoryDedicatedRequirements::default();
+        {
+            let mut memory_requirements =
+                vk::MemoryRequirements2::builder().push_next(&mut dedicated_requirements);
+            let requirements_info = vk::BufferMemoryRequirementsInfo2::builder().buffer(buffer);
+            get_buffer_memory_requirements2(
+                self.device.handle(),
+                &*requirements_info,
+                &mut *memory_requirements,
+            );
+        }
+        Some(dedicated_requirements)
+    }
+
+    /// Same as `Allocator::buffer_dedicated_requirements`, for a temporary buffer created from
+    /// `buffer_info`.
+    unsafe fn buffer_info_dedicated_requirements(
+        &self,
+        buffer_info: &vk::BufferCreateInfo,
+    ) -> Option<vk::MemoryDedicatedRequirements> {
+        self.memory_requirements2?;
+        let buffer = self
+            .device
+            .create_buffer(buffer_info, self.allocation_callbacks.as_deref())
+            .ok()?;
+        let dedicated_requirements = self.buffer_dedicated_requirements(buffer);
+        self.device
+            .destroy_buffer(buffer, self.allocation_callbacks.as_deref());
+        dedicated_requirements
+    }
+
+    /// Queries the driver's dedicated allocation hint for `image`, or `None` if it can't be
+    /// queried, see `Allocator::memory_requirements2`.
+    unsafe fn image_dedicated_requirements(
+        &self,
+        image: vk::Image,
+    ) -> Option<vk::MemoryDedicatedRequirements> {
+        let (_, get_image_memory_requirements2) = self.memory_requirements2?;
+        let mut dedicated_requirements = vk::MemoryDedicatedRequirements::default();
+        {
+            let mut memory_requirements =
+                vk::MemoryRequirements2::builder().push_next(&mut dedicated_requirements);
+            let requirements_info = vk::ImageMemoryRequirementsInfo2::builder().image(image);
+            get_image_memory_requirements2(
+                self.device.handle(),
+                &*requirements_info,
+                &mut *memory_requirements,
+            );
+        }
+        Some(dedicated_requirements)
+    }
+
+    /// Same as `Allocator::image_dedicated_requirements`, for a temporary image created from
+    /// `image_info`.
+    unsafe fn image_info_dedicated_requirements(
+        &self,
+        image_info: &vk::ImageCreateInfo,
+    ) -> Option<vk::MemoryDedicatedRequirements> {
+        self.memory_requirements2?;
+        let image = self
+            .device
+            .create_image(image_info, self.allocation_callbacks.as_deref())
+            .ok()?;
+        let dedicated_requirements = self.image_dedicated_requirements(image);
+        self.device
+            .destroy_image(image, self.allocation_callbacks.as_deref());
+        dedicated_requirements
+    }
+
+    /// Rejects flags of `info` that the algorithm of its pool doesn't support, see
+    /// `AllocatorPool::allocation_constraints`. Pools wrapped with `AllocatorPool::from_raw` are
+    /// not checked.
+    fn validate_pool_flags(&self, info: &AllocationCreateInfo) -> Result<(), Error> {
+        match info.pool.and_then(|pool| pool.constraints) {
+            Some(constraints) => constraints.check(info.ffi_flags()),
+            None => Ok(()),
+        }
+    }
+
+    /// Queries memory requirements of a temporary buffer created from `buffer_info`.
+    unsafe fn buffer_info_memory_requirements(
+        &self,
+        buffer_info: &vk::BufferCreateInfo,
+    ) -> VkResult<vk::MemoryRequirements> {
+        let buffer = self
+            .device
+            .create_buffer(buffer_info, self.allocation_callbacks.as_deref())?;
+        let memory_requirements = self.device.get_buffer_memory_requirements(buffer);
+        self.device
+            .destroy_buffer(buffer, self.allocation_callbacks.as_deref());
+        Ok(memory_requirements)
+    }
+
+    /// Bookkeeping after a successful allocation described by `allocation_info`.
+    ///
+    /// On error, the caller frees `allocation` again.
+    unsafe fn finish_allocation(
+        &self,
+        allocation_info: &AllocationCreateInfo,
+        allocation: Allocation,
+        info: &AllocationInfo,
+    ) -> VkResult<()> {
+        let state = AllocationState {
+            export_handle_types: self.exportable.handle_types(allocation_info.pool),
+            device_local_size: if self.track_peak_usage && self.is_device_local(info) {
+                Some(info.device_size())
+            } else {
+                None
+            },
+            ..Default::default()
+        };
+        self.allocation_states.insert(allocation, state);
+        if let Some(size) = state.device_local_size {
+            self.device_local_usage.add(size);
+        }
+        trace::allocated(allocation, info, allocation_info.pool);
+        self.zero_initialize(allocation_info, allocation, info)?;
+
+        if let Some(callback) = &self.allocate_callback {
+            let name = if allocation_info
+                .flags
+                .contains(AllocationCreateFlags::USER_DATA_COPY_STRING)
+                && !info.user_data().is_null()
+            {
+                std::ffi::CStr::from_ptr(info.user_data() as *const std::os::raw::c_char)
+                    .to_str()
+                    .ok()
+            } else {
+                None
+            };
+            let _scope = reentrancy::CallbackScope::enter();
+            callback(allocation, info, name);
+        }
+        Ok(())
+    }
+
+    /// Forgets the wrapper's bookkeeping of `allocation`, right before VMA frees it.
+    ///
+    /// Returns what was remembered about `allocation`, for `Allocator::finish_free` once VMA has
+    /// freed it.
+    unsafe fn release_allocation(&self, allocation: Allocation) -> AllocationState {
+        reentrancy::assert_outside_callback();
+        if allocation.is_null() {
+            return AllocationState::default();
+        }
+        let state = self.allocation_states.remove(allocation);
+        if let Some(size) = state.device_local_size {
+            self.device_local_usage.sub(size);
+        }
+        #[cfg(feature = "tracing")]
+        if let Ok(info) = self.get_allocation_info(allocation) {
+            trace::freed(allocation, &info);
+        }
+        state
+    }
+
+    /// Bookkeeping after VMA has freed `allocation`, with `state` as returned by
+    /// `Allocator::release_allocation`: destroys the chained pool the allocation owned, and
+    /// asserts that it was not made for a resource that was never bound to it.
+    unsafe fn finish_free(&self, allocation: Allocation, state: AllocationState) {
+        if let Some(pool) = state.chained_pool {
+            self.destroy_unlisted_pool(pool);
+        }
+        debug_assert!(
+            !state.unbound,
+            "{:?} was allocated for a resource that was never bound to it",
+            allocation
+        );
+    }
+
+    fn is_device_local(&self, info: &AllocationInfo) -> bool {
+        self.device_local_memory_types & (1 << info.memory_type()) != 0
+    }
+
+    /// Clears a new host-visible allocation if `AllocationCreateInfo::zero_initialize` is set.
+    unsafe fn zero_initialize(
+        &self,
+        allocation_info: &AllocationCreateInfo,
+        allocation: Allocation,
+        info: &AllocationInfo,
+    ) -> VkResult<()> {
+        if !allocation_info.zero_initialize || !info.is_host_visible(self) {
+            return Ok(());
+        }
+
+        let size = device_size_to_host(info.device_size())?;
+        let mapped_data = self.map_memory(allocation)?;
+        std::ptr::write_bytes(mapped_data, 0, size);
+        let result = if info.is_host_coherent(self) {
+            Ok(())
+        } else {
+            self.flush_allocation(allocation, 0, vk::WHOLE_SIZE as usize)
+        };
+        self.unmap_memory(allocation);
+        result.map_err(vk::Result::from)
+    }
+
+    /// Queries memory requirements of a temporary image created from `image_info`.
+    unsafe fn image_info_memory_requirements(
+        &self,
+        image_info: &vk::ImageCreateInfo,
+    ) -> VkResult<vk::MemoryRequirements> {
+        let image = self
+            .device
+            .create_image(image_info, self.allocation_callbacks.as_deref())?;
+        let memory_requirements = self.device.get_image_memory_requirements(image);
+        self.device
+            .destroy_image(image, self.allocation_callbacks.as_deref());
+        Ok(memory_requirements)
+    }
+
+    /// Creates a pool with a single block that fits exactly one allocation with
+    /// `memory_requirements`, whose `ash::vk::MemoryAllocateInfo` chains `allocate_next`.
+    ///
+    /// The pool is not listed by `Allocator::pools`.
+    unsafe fn create_chained_pool(
+        &self,
+        memory_type_index: u32,
+        memory_requirements: &vk::MemoryRequirements,
+        allocate_next: *mut ::std::os::raw::c_void,
+    ) -> VkResult<AllocatorPool> {
+        // With a debug margin, VMA aligns the allocation after the leading margin.
+        let margin = if DEBUG_MARGIN == 0 {
+            0
+        } else {
+            2 * DEBUG_MARGIN + memory_requirements.alignment
+        };
+        Ok(self.create_unlisted_pool(&AllocatorPoolCreateInfo {
+            memory_type_index,
+            block_size: device_size_to_host(memory_requirements.size + margin)?,
+            max_block_count: 1,
+            memory_allocate_next: Some(allocate_next),
+            ..Default::default()
+        })?)
+    }
+
+    /// Allocates through `create` from a chained pool, see `Allocator::create_chained_pool`,
+    /// which is then owned by the allocation.
+    unsafe fn allocate_chained<T>(
+        &self,
+        memory_type_index: u32,
+        memory_requirements: &vk::MemoryRequirements,
+        allocation_info: &AllocationCreateInfo,
+        allocate_next: *mut ::std::os::raw::c_void,
+        create: impl FnOnce(&AllocationCreateInfo) -> Result<(T, Allocation, AllocationInfo), Error>,
+    ) -> Result<(T, Allocation, AllocationInfo), Error> {
+        // Checked before creating the pool rather than by `create`, so that nothing needs to be
+        // cleaned up.
+        AllocationConstraints::for_pool(&AllocatorPoolCreateInfo::default())
+            .check(allocation_info.ffi_flags())?;
+        let pool =
+            self.create_chained_pool(memory_type_index, memory_requirements, allocate_next)?;
+        let pool_allocation_info = AllocationCreateInfo {
+            pool: Some(pool),
+            ..allocation_info.clone()
+        };
+        match create(&pool_allocation_info) {
+            Ok((resource, allocation, info)) => {
+                self.allocation_states
+                    .update(allocation, |state| state.chained_pool = Some(pool));
+                Ok((resource, allocation, info))
+            }
+            Err(err) => {
+                self.destroy_unlisted_pool(pool);
+                Err(err)
+            }
+        }
     }
 
     /// The allocator fetches `ash::vk::PhysicalDeviceProperties` from the physical device.
     /// You can get it here, without fetching it again on your own.
-    pub unsafe fn get_physical_device_properties(&self) -> VkResult<vk::PhysicalDeviceProperties> {
-        let mut properties = vk::PhysicalDeviceProperties::default();
-        ffi::vmaGetPhysicalDeviceProperties(self.0, &mut properties as *mut _ as *mut *const _);
-
-        Ok(properties)
+    pub fn get_physical_device_properties(&self) -> VkResult<vk::PhysicalDeviceProperties> {
+        let mut properties: *const vk::PhysicalDeviceProperties = ::std::ptr::null();
+        unsafe {
+            ffi::vmaGetPhysicalDeviceProperties(self.handle(), &mut properties);
+            Ok(*properties)
+        }
     }
 
     /// The allocator fetches `ash::vk::PhysicalDeviceMemoryProperties` from the physical device.
     /// You can get it here, without fetching it again on your own.
-    pub unsafe fn get_memory_properties(&self) -> VkResult<vk::PhysicalDeviceMemoryProperties> {
-        let mut properties = vk::PhysicalDeviceMemoryProperties::default();
-        ffi::vmaGetMemoryProperties(self.0, &mut properties as *mut _ as *mut *const _);
+    pub fn get_memory_properties(&self) -> VkResult<vk::PhysicalDeviceMemoryProperties> {
+        let mut properties: *const vk::PhysicalDeviceMemoryProperties = ::std::ptr::null();
+        unsafe {
+            ffi::vmaGetMemoryProperties(self.handle(), &mut properties);
+            Ok(*properties)
+        }
+    }
 
+    /// Queries `ash::vk::PhysicalDeviceMemoryProperties2` of the physical device, filling `next`,
+    /// e.g. `ash::vk::PhysicalDeviceMemoryBudgetPropertiesEXT`, through its `pNext` chain.
+    ///
+    /// Unlike `Allocator::get_memory_properties`, this is not cached and calls
+    /// `vkGetPhysicalDeviceMemoryProperties2` on every invocation. It requires Vulkan 1.1,
+    /// and `next` must be supported by the physical device. The `p_next` member of the returned
+    /// struct points to `next`.
+    pub unsafe fn get_memory_properties2<T: vk::ExtendsPhysicalDeviceMemoryProperties2>(
+        &self,
+        next: &mut T,
+    ) -> VkResult<vk::PhysicalDeviceMemoryProperties2> {
+        let mut properties = vk::PhysicalDeviceMemoryProperties2::builder()
+            .push_next(next)
+            .build();
+        self.instance
+            .get_physical_device_memory_properties2(self.physical_device, &mut properties);
         Ok(properties)
     }
 
@@ -930,16 +2460,34 @@ impl Allocator {
     ///
     /// This is just a convenience function; the same information can be obtained using
     /// `Allocator::get_memory_properties`.
-    pub unsafe fn get_memory_type_properties(
+    ///
+    /// Returns `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` if `memory_type_index` is out of range.
+    pub fn get_memory_type_properties(
         &self,
         memory_type_index: u32,
     ) -> VkResult<vk::MemoryPropertyFlags> {
+        if memory_type_index >= self.get_memory_properties()?.memory_type_count {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+        }
         let mut flags = vk::MemoryPropertyFlags::empty();
-        ffi::vmaGetMemoryTypeProperties(self.0, memory_type_index, &mut flags);
+        unsafe { ffi::vmaGetMemoryTypeProperties(self.handle(), memory_type_index, &mut flags) };
 
         Ok(flags)
     }
 
+    /// Returns `ash::vk::MemoryPropertyFlags` of every memory type, indexed by memory type index.
+    ///
+    /// The returned vector contains `ash::vk::PhysicalDeviceMemoryProperties::memory_type_count` elements.
+    pub fn all_memory_type_properties(&self) -> VkResult<Vec<vk::MemoryPropertyFlags>> {
+        let memory_properties = self.get_memory_properties()?;
+        Ok(
+            memory_properties.memory_types[..memory_properties.memory_type_count as usize]
+                .iter()
+                .map(|memory_type| memory_type.property_flags)
+                .collect(),
+        )
+    }
+
     /// Sets index of the current frame.
     ///
     /// This function must be used if you make allocations with `AllocationCreateFlags::CAN_BECOME_LOST` and
@@ -947,30 +2495,298 @@ impl Allocator {
     /// Allocations queried using `Allocator::get_allocation_info` cannot become lost
     /// in the current frame.
     pub unsafe fn set_current_frame_index(&self, frame_index: u32) {
-        ffi::vmaSetCurrentFrameIndex(self.0, frame_index);
+        ffi::vmaSetCurrentFrameIndex(self.handle(), frame_index);
     }
 
     /// Retrieves statistics from current state of the `Allocator`.
-    pub unsafe fn calculate_stats(&self) -> VkResult<ffi::VmaStats> {
-        let mut vma_stats: ffi::VmaStats = mem::zeroed();
-        ffi::vmaCalculateStats(self.0, &mut vma_stats);
-        Ok(vma_stats)
+    ///
+    /// `Stats::memory_type` and `Stats::memory_heap` only contain the memory types and heaps of
+    /// the physical device. In debug builds, this asserts that the statistics are internally
+    /// consistent, e.g. that the memory types and heaps add up to `Stats::total`.
+    pub fn calculate_stats(&self) -> VkResult<Stats> {
+        debug_assert!(!self.internal.is_null());
+        let memory_properties = self.get_memory_properties()?;
+        let stats = unsafe {
+            let mut vma_stats: ffi::VmaStats = mem::zeroed();
+            ffi::vmaCalculateStats(self.handle(), &mut vma_stats);
+            Stats::new(
+                &vma_stats,
+                memory_properties.memory_type_count,
+                memory_properties.memory_heap_count,
+            )
+        };
+        debug_assert!(stats.is_consistent(), "inconsistent {:?}", stats);
+        Ok(stats)
+    }
+
+    /// Same as `Allocator::calculate_stats`, in the layout of VMA 3's `vmaCalculateStatistics`.
+    ///
+    /// The vendored VMA predates `vmaCalculateStatistics`, so this is computed from
+    /// `vmaCalculateStats`. Code written against it keeps working when VMA is upgraded.
+    pub fn calculate_statistics(&self) -> VkResult<TotalStatistics> {
+        Ok(TotalStatistics::from(&self.calculate_stats()?))
+    }
+
+    /// Bytes of `ash::vk::DeviceMemory` allocated by VMA in each memory heap, indexed by heap
+    /// index.
+    ///
+    /// This is the size of all blocks, used or not, from `Allocator::calculate_stats`, so unlike
+    /// `Allocator::get_budget` it doesn't need `AllocatorCreateFlags::EXT_MEMORY_BUDGET`. It
+    /// doesn't include memory allocated by other components of the process.
+    pub fn allocated_bytes_per_heap(&self) -> VkResult<Vec<u64>> {
+        Ok(self
+            .calculate_stats()?
+            .memory_heap
+            .iter()
+            .map(|heap| heap.used_bytes + heap.unused_bytes)
+            .collect())
+    }
+
+    /// Counts the allocations of each size range, in default and custom pools, including
+    /// dedicated allocations.
+    ///
+    /// `buckets` are the inclusive upper bounds of the ranges, in increasing order. The returned
+    /// vector has one more element than `buckets`: element `i` counts allocations larger than
+    /// `buckets[i - 1]` (or any size, for the first one) and at most `buckets[i]`, and the last
+    /// element counts allocations larger than all bounds. For example, `[256, 4096, 65536]`
+    /// separates allocations up to 256 bytes, which may be better served by a fixed-size pool,
+    /// from larger ones. Fails with `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` if `buckets`
+    /// is not strictly increasing.
+    ///
+    /// The sizes are read from the detailed map of `Allocator::build_stats_string`, so this is
+    /// meant for tuning rather than for every frame.
+    pub fn allocation_size_histogram(&self, buckets: &[vk::DeviceSize]) -> VkResult<Vec<u32>> {
+        if buckets.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+        }
+        Ok(stats::allocation_size_histogram(
+            &self.build_stats_string(true)?,
+            buckets,
+        ))
+    }
+
+    /// Estimates how many bytes of memory blocks are lost to alignment padding between
+    /// allocations, in default and custom pools.
+    ///
+    /// VMA doesn't record the size originally requested for an allocation, so this is derived
+    /// from the detailed map of `Allocator::build_stats_string`: a free range directly in front
+    /// of an allocation counts as padding if it is smaller than the largest power of two dividing
+    /// the allocation's offset. Small holes left by freed allocations can be counted as well, so
+    /// treat the result as an upper bound. Padding at the end of allocations, e.g. sizes rounded
+    /// up by the driver, is not included. A large value compared to
+    /// `StatInfo::unused_bytes` hints at tuning `AllocatorPoolCreateInfo::min_allocation_alignment`
+    /// or grouping resources of similar alignment into pools.
+    pub fn alignment_overhead(&self) -> VkResult<u64> {
+        Ok(stats::alignment_padding(&self.build_stats_string(true)?))
+    }
+
+    /// Size of the `ash::vk::DeviceMemory` blocks VMA allocates for the default pool of
+    /// `memory_type`, in bytes.
+    ///
+    /// VMA doesn't expose it, so this mirrors its rule: heaps of at most 1 GiB (after
+    /// `AllocatorCreateInfo::heap_size_limits`) use an eighth of the heap size, larger heaps use
+    /// `AllocatorCreateInfo::preferred_large_heap_block_size` (256 MiB by default), aligned up
+    /// to 32 bytes. While a memory type has few blocks, VMA may start with blocks down to an
+    /// eighth of this size, and allocations larger than half of it get dedicated memory of their
+    /// own size. If these don't fit your allocations, create a custom pool with an explicit
+    /// `AllocatorPoolCreateInfo::block_size`.
+    ///
+    /// Returns `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` if `memory_type` is out of range.
+    pub fn effective_block_size(&self, memory_type: u32) -> VkResult<vk::DeviceSize> {
+        const SMALL_HEAP_MAX_SIZE: vk::DeviceSize = 1024 * 1024 * 1024;
+        const DEFAULT_LARGE_HEAP_BLOCK_SIZE: vk::DeviceSize = 256 * 1024 * 1024;
+
+        let memory_properties = self.get_memory_properties()?;
+        if memory_type >= memory_properties.memory_type_count {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+        }
+        let heap_index = memory_properties.memory_types[memory_type as usize].heap_index;
+        let heap_size = memory_properties.memory_heaps[heap_index as usize].size;
+        let block_size = if heap_size <= SMALL_HEAP_MAX_SIZE {
+            heap_size / 8
+        } else if self.preferred_large_heap_block_size != 0 {
+            self.preferred_large_heap_block_size
+        } else {
+            DEFAULT_LARGE_HEAP_BLOCK_SIZE
+        };
+        Ok(block_size.next_multiple_of(32))
+    }
+
+    /// Highest number of bytes allocated in `ash::vk::MemoryPropertyFlags::DEVICE_LOCAL` memory
+    /// at any time since the allocator was created or `Allocator::reset_peak` was called.
+    ///
+    /// Always zero unless the allocator was created with `AllocatorCreateInfo::track_peak_usage`.
+    /// Then this is the sum of the sizes of live allocations, updated by every allocating and
+    /// freeing function of the wrapper, so it is cheap to query. It doesn't include unused space
+    /// of memory blocks, see `Allocator::calculate_stats` for that.
+    pub fn peak_usage(&self) -> u64 {
+        self.device_local_usage.peak()
+    }
+
+    /// Restarts `Allocator::peak_usage` from the number of bytes allocated right now.
+    pub fn reset_peak(&self) {
+        self.device_local_usage.reset_peak();
+    }
+
+    /// Makes the default pool of `memory_type_index` allocate a memory block up front, e.g.
+    /// before loading a level, so that later allocations don't stall on `vkAllocateMemory`.
+    ///
+    /// This allocates `bytes` from the memory type and frees them right away. VMA keeps one empty
+    /// block per memory type instead of freeing it, so at most one block is kept reserved, and
+    /// none if `bytes` is large enough for VMA to give it a dedicated allocation (more than half
+    /// of `AllocatorCreateInfo::preferred_large_heap_block_size`), or if the block would exceed
+    /// the heap budget. To reserve more, create a custom pool with
+    /// `AllocatorPoolCreateInfo::min_block_count` instead.
+    ///
+    /// Returns the number of bytes of device memory actually reserved by this call, which is 0
+    /// if an empty block was already reserved, and may be more than `bytes` as whole blocks are
+    /// allocated.
+    pub unsafe fn preallocate(
+        &self,
+        memory_type_index: u32,
+        bytes: vk::DeviceSize,
+    ) -> Result<vk::DeviceSize, Error> {
+        let memory_properties = self.get_memory_properties()?;
+        if memory_type_index >= memory_properties.memory_type_count {
+            return Err(Error::InvalidMemoryType);
+        }
+        if bytes == 0 {
+            return Ok(0);
+        }
+
+        let block_bytes = |stats: &Stats| {
+            let info = &stats.memory_type[memory_type_index as usize];
+            info.used_bytes + info.unused_bytes
+        };
+        let before = block_bytes(&self.calculate_stats()?);
+
+        let memory_requirements = vk::MemoryRequirements {
+            size: bytes,
+            alignment: 1,
+            memory_type_bits: 1 << memory_type_index,
+        };
+        let (allocation, _) =
+            self.allocate_memory(&memory_requirements, &AllocationCreateInfo::default())?;
+        self.free_memory(allocation);
+
+        let after = block_bytes(&self.calculate_stats()?);
+        Ok(after.saturating_sub(before))
+    }
+
+    /// Checks that all allocations made from this allocator have been freed.
+    ///
+    /// Meant to be called right before `Allocator::destroy_allocator`, typically in debug builds,
+    /// to turn leaked GPU memory into a clear error. If any allocation is still alive, returns a
+    /// `LeakReport` with the number and total size of leaked allocations, a per memory type
+    /// breakdown, and a detailed stats string. The outer result reports errors calculating the
+    /// statistics, see `Allocator::calculate_stats`.
+    pub fn assert_empty(&self) -> VkResult<Result<(), LeakReport>> {
+        let stats = self.calculate_stats()?;
+        if stats.total.allocation_count == 0 {
+            return Ok(Ok(()));
+        }
+
+        let per_type = stats
+            .memory_type
+            .iter()
+            .enumerate()
+            .filter(|(_, info)| info.allocation_count > 0)
+            .map(|(index, info)| (index as u32, info.allocation_count as usize))
+            .collect();
+        Ok(Err(LeakReport {
+            leaked_bytes: stats.total.used_bytes,
+            leaked_allocations: stats.total.allocation_count,
+            per_type,
+            stats_string: self.build_stats_string(true).unwrap_or_default(),
+        }))
+    }
+
+    /// Retrieves information about current memory budget for all memory heaps.
+    ///
+    /// The returned vector contains one element per memory heap, i.e.
+    /// `ash::vk::PhysicalDeviceMemoryProperties::memory_heap_count` elements.
+    ///
+    /// This function is called "get" not "calculate" because it is very fast, suitable to be called
+    /// every frame or every allocation. For more detailed statistics use `Allocator::calculate_stats`.
+    ///
+    /// Note that when using allocator from multiple threads, returned information may immediately
+    /// become outdated.
+    pub fn get_budget(&self) -> VkResult<Vec<ffi::VmaBudget>> {
+        let heap_count = self.get_memory_properties()?.memory_heap_count as usize;
+        let mut budgets: Vec<ffi::VmaBudget> = vec![unsafe { mem::zeroed() }; vk::MAX_MEMORY_HEAPS];
+        unsafe { ffi::vmaGetBudget(self.handle(), budgets.as_mut_ptr()) };
+        budgets.truncate(heap_count);
+        Ok(budgets)
+    }
+
+    /// Returns indices of memory heaps whose current usage exceeds `threshold` fraction of their budget.
+    ///
+    /// For example, a `threshold` of `0.9` returns all heaps that are more than 90% full.
+    /// This is a thin layer over `Allocator::get_budget`, useful for deciding when to evict
+    /// resources from a heap.
+    ///
+    /// When `AllocatorCreateFlags::EXT_MEMORY_BUDGET` is not used, usage and budget are estimated
+    /// by the library from its own allocations. Heaps reporting a budget of 0 carry no budget
+    /// information and are never returned.
+    pub fn heaps_over_budget(&self, threshold: f32) -> VkResult<Vec<u32>> {
+        let heaps = self
+            .get_budget()?
+            .iter()
+            .enumerate()
+            .filter(|(_, budget)| {
+                budget.budget > 0 && budget.usage as f64 > budget.budget as f64 * threshold as f64
+            })
+            .map(|(heap_index, _)| heap_index as u32)
+            .collect();
+        Ok(heaps)
     }
 
     /// Builds and returns statistics in `JSON` format.
-    pub unsafe fn build_stats_string(&self, detailed_map: bool) -> VkResult<String> {
+    pub fn build_stats_string(&self, detailed_map: bool) -> VkResult<String> {
         let mut stats_string: *mut ::std::os::raw::c_char = ::std::ptr::null_mut();
-        ffi::vmaBuildStatsString(self.0, &mut stats_string, if detailed_map { 1 } else { 0 });
+        unsafe {
+            ffi::vmaBuildStatsString(
+                self.handle(),
+                &mut stats_string,
+                if detailed_map { 1 } else { 0 },
+            );
+
+            Ok(if stats_string.is_null() {
+                String::new()
+            } else {
+                let result = std::ffi::CStr::from_ptr(stats_string)
+                    .to_string_lossy()
+                    .into_owned();
+                ffi::vmaFreeStatsString(self.handle(), stats_string);
+                result
+            })
+        }
+    }
 
-        Ok(if stats_string.is_null() {
-            String::new()
-        } else {
-            let result = std::ffi::CStr::from_ptr(stats_string)
-                .to_string_lossy()
-                .into_owned();
-            ffi::vmaFreeStatsString(self.0, stats_string);
-            result
-        })
+    /// Computes the size of a staging buffer holding all mip levels and array layers of an image
+    /// created from `image_info`, and the regions to pass to `vkCmdCopyBufferToImage`.
+    ///
+    /// There is one region per mip level and aspect, covering all array layers. Rows and slices
+    /// are tightly packed in texel blocks of the format (`buffer_row_length` and
+    /// `buffer_image_height` are 0), and each region starts at an offset aligned to 4, to the
+    /// texel block size, and to `optimal_buffer_copy_offset_alignment` of the physical device.
+    /// The regions of depth/stencil formats are split per aspect, with the buffer layout
+    /// required for copies: depth in 2 or 4 bytes, stencil in 1 byte.
+    ///
+    /// Returns `ash::vk::Result::ERROR_FORMAT_NOT_SUPPORTED` for formats outside of core
+    /// Vulkan 1.0, including multi-planar formats, and the `ash::vk::Result` of
+    /// `Error::InvalidImageExtent` for zero-sized images.
+    pub fn image_staging_size(
+        &self,
+        image_info: &vk::ImageCreateInfo,
+    ) -> VkResult<(vk::DeviceSize, Vec<vk::BufferImageCopy>)> {
+        validate_image_info(image_info)?;
+        let limits = unsafe {
+            self.instance
+                .get_physical_device_properties(self.physical_device)
+                .limits
+        };
+        staging::image_staging_layout(image_info, limits.optimal_buffer_copy_offset_alignment)
     }
 
     /// Helps to find memory type index, given memory type bits and allocation info.
@@ -987,23 +2803,110 @@ impl Allocator {
     /// device doesn't support any memory type with requested features for the specific
     /// type of resource you want to use it for. Please check parameters of your
     /// resource, like image layout (OPTIMAL versus LINEAR) or mip level count.
-    pub unsafe fn find_memory_type_index(
+    pub fn find_memory_type_index(
         &self,
         memory_type_bits: u32,
         allocation_info: &AllocationCreateInfo,
     ) -> VkResult<u32> {
-        let create_info = allocation_create_info_to_ffi(&allocation_info);
+        let create_info =
+            allocation_create_info_to_ffi(&allocation_info, self.excluded_memory_types)?;
         let mut memory_type_index: u32 = 0;
-        ffi_to_result(ffi::vmaFindMemoryTypeIndex(
-            self.0,
-            memory_type_bits,
-            &create_info,
-            &mut memory_type_index,
-        ))?;
+        ffi_to_result(unsafe {
+            ffi::vmaFindMemoryTypeIndex(
+                self.handle(),
+                memory_type_bits,
+                &create_info,
+                &mut memory_type_index,
+            )
+        })?;
 
         Ok(memory_type_index)
     }
 
+    /// Same as `Allocator::find_memory_type_index`, but also returns the property flags and heap
+    /// of the chosen memory type, so they don't need to be looked up again.
+    ///
+    /// As in VMA, `AllocationCreateInfo::strategy` and the `AllocationCreateFlags::STRATEGY_*`
+    /// flags don't influence which memory type is chosen, only where in it an allocation is
+    /// placed.
+    pub fn find_memory_type(
+        &self,
+        memory_type_bits: u32,
+        allocation_info: &AllocationCreateInfo,
+    ) -> VkResult<MemoryTypeChoice> {
+        let index = self.find_memory_type_index(memory_type_bits, allocation_info)?;
+        let memory_type = self.get_memory_properties()?.memory_types[index as usize];
+        Ok(MemoryTypeChoice {
+            index,
+            properties: memory_type.property_flags,
+            heap: memory_type.heap_index,
+        })
+    }
+
+    /// Returns `true` if some memory type of the device satisfies `usage`, e.g. whether
+    /// `MemoryUsage::GpuLazilyAllocated` has an `ash::vk::MemoryPropertyFlags::LAZILY_ALLOCATED`
+    /// type to use, which desktop GPUs usually lack.
+    ///
+    /// This only looks at the memory properties, like `Allocator::find_memory_type_index` with
+    /// any memory type bits, so no allocation is attempted. A resource may still restrict its
+    /// memory types in a way that rules the usage out.
+    pub fn supports_usage(&self, usage: MemoryUsage) -> bool {
+        self.find_memory_type_index(!0, &AllocationCreateInfo::for_usage(usage))
+            .is_ok()
+    }
+
+    /// Returns the memory types among `memory_type_bits` that are acceptable for `usage`, from
+    /// the most to the least preferred, e.g. to create a pool per tier.
+    ///
+    /// This is the order VMA falls back in when allocating from a memory type fails: each
+    /// index is what `Allocator::find_memory_type_index` returns once the previous ones are
+    /// removed from `memory_type_bits`. As there, 0 means any memory type. Fails with
+    /// `ash::vk::Result::ERROR_FEATURE_NOT_PRESENT` if no memory type is acceptable.
+    pub fn ranked_memory_types(
+        &self,
+        usage: MemoryUsage,
+        memory_type_bits: u32,
+    ) -> VkResult<Vec<u32>> {
+        let allocation_info = AllocationCreateInfo::for_usage(usage);
+        let mut remaining = match memory_type_bits {
+            0 => !0,
+            bits => bits,
+        };
+        let mut ranked = Vec::new();
+        while remaining != 0 {
+            match self.find_memory_type_index(remaining, &allocation_info) {
+                Ok(memory_type_index) => {
+                    ranked.push(memory_type_index);
+                    remaining &= !(1 << memory_type_index);
+                }
+                Err(vk::Result::ERROR_FEATURE_NOT_PRESENT) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        if ranked.is_empty() {
+            Err(vk::Result::ERROR_FEATURE_NOT_PRESENT)
+        } else {
+            Ok(ranked)
+        }
+    }
+
+    /// Returns the index of the memory heap an allocation described by `allocation_info` and
+    /// `memory_type_bits` would be placed in.
+    ///
+    /// This is the heap of the memory type picked by `Allocator::find_memory_type_index`, and can
+    /// be used to check the corresponding entry of `Allocator::get_budget` before making a large
+    /// allocation. VMA may still fall back to another memory type if the allocation fails.
+    pub fn predict_heap(
+        &self,
+        allocation_info: &AllocationCreateInfo,
+        memory_type_bits: u32,
+    ) -> VkResult<u32> {
+        Ok(self
+            .find_memory_type(memory_type_bits, allocation_info)?
+            .heap)
+    }
+
     /// Helps to find memory type index, given buffer info and allocation info.
     ///
     /// It can be useful e.g. to determine value to be used as `AllocatorPoolCreateInfo::memory_type_index`.
@@ -1019,10 +2922,11 @@ impl Allocator {
         buffer_info: &ash::vk::BufferCreateInfo,
         allocation_info: &AllocationCreateInfo,
     ) -> VkResult<u32> {
-        let allocation_create_info = allocation_create_info_to_ffi(&allocation_info);
+        let allocation_create_info =
+            allocation_create_info_to_ffi(&allocation_info, self.excluded_memory_types)?;
         let mut memory_type_index: u32 = 0;
         ffi_to_result(ffi::vmaFindMemoryTypeIndexForBufferInfo(
-            self.0,
+            self.handle(),
             buffer_info,
             &allocation_create_info,
             &mut memory_type_index,
@@ -1046,10 +2950,12 @@ impl Allocator {
         image_info: ash::vk::ImageCreateInfo,
         allocation_info: &AllocationCreateInfo,
     ) -> VkResult<u32> {
-        let allocation_create_info = allocation_create_info_to_ffi(&allocation_info);
+        validate_image_info(&image_info)?;
+        let allocation_create_info =
+            allocation_create_info_to_ffi(&allocation_info, self.excluded_memory_types)?;
         let mut memory_type_index: u32 = 0;
         ffi_to_result(ffi::vmaFindMemoryTypeIndexForImageInfo(
-            self.0,
+            self.handle(),
             &image_info,
             &allocation_create_info,
             &mut memory_type_index,
@@ -1058,36 +2964,283 @@ impl Allocator {
         Ok(memory_type_index)
     }
 
+    /// Helps to find a memory type index suitable for all of the given buffers at once.
+    ///
+    /// It can be useful to determine `AllocatorPoolCreateInfo::memory_type_index` for a pool that
+    /// must hold several kinds of buffers, e.g. both vertex and index buffers.
+    /// It internally creates a temporary, dummy buffer for every element of `buffer_infos`,
+    /// ANDs their `memory_type_bits` together and passes the result to `Allocator::find_memory_type_index`.
+    ///
+    /// Returns `ash::vk::Result::ERROR_FEATURE_NOT_PRESENT` if the buffers have no memory type in common.
+    pub unsafe fn common_memory_type_index(
+        &self,
+        buffer_infos: &[ash::vk::BufferCreateInfo],
+        allocation_info: &AllocationCreateInfo,
+    ) -> VkResult<u32> {
+        let mut memory_type_bits = u32::MAX;
+        for buffer_info in buffer_infos {
+            memory_type_bits &= self
+                .buffer_info_memory_requirements(buffer_info)?
+                .memory_type_bits;
+        }
+
+        if memory_type_bits == 0 {
+            return Err(vk::Result::ERROR_FEATURE_NOT_PRESENT);
+        }
+
+        self.find_memory_type_index(memory_type_bits, allocation_info)
+    }
+
     /// Allocates Vulkan device memory and creates `AllocatorPool` object.
+    ///
+    /// Fails with `Error::InvalidMemoryType` if `AllocatorPoolCreateInfo::memory_type_index` is
+    /// not a memory type of the physical device, and with
+    /// `ash::vk::Result::ERROR_FEATURE_NOT_PRESENT` if it is a
+    /// `ash::vk::MemoryPropertyFlags::DEVICE_COHERENT_AMD` type while the allocator was created
+    /// without `AllocatorCreateFlags::AMD_DEVICE_COHERENT_MEMORY`.
+    ///
+    /// With the `tracing` feature, a warning event is emitted when the pool uses
+    /// `AllocatorPoolCreateFlags::IGNORE_BUFFER_IMAGE_GRANULARITY` on a device whose
+    /// `Allocator::buffer_image_granularity` is larger than 1, where mixing buffers and optimal
+    /// images in the pool can make them alias.
     pub unsafe fn create_pool(
         &self,
         pool_info: &AllocatorPoolCreateInfo,
-    ) -> VkResult<AllocatorPool> {
+    ) -> Result<AllocatorPool, Error> {
+        if pool_info
+            .flags
+            .contains(AllocatorPoolCreateFlags::IGNORE_BUFFER_IMAGE_GRANULARITY)
+        {
+            let granularity = self.buffer_image_granularity()?;
+            if granularity > 1 {
+                trace::ignored_buffer_image_granularity(pool_info.memory_type_index, granularity);
+            }
+        }
+        self.create_pool_unchecked(pool_info)
+    }
+
+    /// Same as `Allocator::create_pool`, without the buffer-image granularity warning.
+    unsafe fn create_pool_unchecked(
+        &self,
+        pool_info: &AllocatorPoolCreateInfo,
+    ) -> Result<AllocatorPool, Error> {
+        let pool = self.create_unlisted_pool(pool_info)?;
+        self.pools.lock().unwrap().push((pool, pool_info.clone()));
+        Ok(pool)
+    }
+
+    /// Same as `Allocator::create_pool_unchecked`, without adding the pool to
+    /// `Allocator::pools`. Destroy it with `Allocator::destroy_unlisted_pool`.
+    unsafe fn create_unlisted_pool(
+        &self,
+        pool_info: &AllocatorPoolCreateInfo,
+    ) -> Result<AllocatorPool, Error> {
+        self.validate_pool_memory_type(pool_info.memory_type_index)?;
         let mut ffi_pool: ffi::VmaPool = mem::zeroed();
         let create_info = pool_create_info_to_ffi(&pool_info);
-        ffi_to_result(ffi::vmaCreatePool(self.0, &create_info, &mut ffi_pool))?;
-        Ok(AllocatorPool(ffi_pool as _))
+        ffi_to_result(ffi::vmaCreatePool(
+            self.handle(),
+            &create_info,
+            &mut ffi_pool,
+        ))?;
+        let pool = AllocatorPool {
+            handle: ffi_pool as _,
+            constraints: Some(AllocationConstraints::for_pool(pool_info)),
+        };
+        self.exportable.insert(pool, pool_info.memory_allocate_next);
+        Ok(pool)
+    }
+
+    fn validate_pool_memory_type(&self, memory_type_index: u32) -> Result<(), Error> {
+        if memory_type_index >= self.get_memory_properties()?.memory_type_count {
+            return Err(Error::InvalidMemoryType);
+        }
+        if self.disabled_memory_types & (1 << memory_type_index) != 0 {
+            return Err(vk::Result::ERROR_FEATURE_NOT_PRESENT.into());
+        }
+        Ok(())
+    }
+
+    /// `bufferImageGranularity` of the physical device, from the properties cached by VMA.
+    ///
+    /// Linear resources (buffers and linear images) and optimal images placed closer than this
+    /// in the same `ash::vk::DeviceMemory` may alias. VMA keeps them apart unless a pool is
+    /// created with `AllocatorPoolCreateFlags::IGNORE_BUFFER_IMAGE_GRANULARITY`, which is always
+    /// safe if this is 1, and otherwise only if the pool never mixes both kinds.
+    pub fn buffer_image_granularity(&self) -> VkResult<vk::DeviceSize> {
+        Ok(self
+            .get_physical_device_properties()?
+            .limits
+            .buffer_image_granularity)
+    }
+
+    /// Creates a custom pool for images similar to `sample_image_info`.
+    ///
+    /// The memory type is chosen with `Allocator::find_memory_type_index_for_image_info` for
+    /// `usage`. If the sample uses `ash::vk::ImageTiling::OPTIMAL` or `ash::vk::ImageTiling::LINEAR`,
+    /// the pool is created with `AllocatorPoolCreateFlags::IGNORE_BUFFER_IMAGE_GRANULARITY`, so
+    /// allocations are packed without buffer-image granularity padding. For that to be valid,
+    /// only images with the same tiling as the sample (or, for linear tiling, buffers) may be
+    /// allocated from the pool. Other tilings, such as
+    /// `ash::vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT`, whose layout is not known to be either
+    /// linear or optimal, keep the granularity checks.
+    pub unsafe fn create_image_pool(
+        &self,
+        sample_image_info: &vk::ImageCreateInfo,
+        usage: MemoryUsage,
+    ) -> VkResult<AllocatorPool> {
+        let memory_type_index = self.find_memory_type_index_for_image_info(
+            *sample_image_info,
+            &AllocationCreateInfo::for_usage(usage),
+        )?;
+
+        let flags = match sample_image_info.tiling {
+            vk::ImageTiling::OPTIMAL | vk::ImageTiling::LINEAR => {
+                AllocatorPoolCreateFlags::IGNORE_BUFFER_IMAGE_GRANULARITY
+            }
+            _ => AllocatorPoolCreateFlags::NONE,
+        };
+        Ok(self.create_pool_unchecked(&AllocatorPoolCreateInfo {
+            memory_type_index,
+            flags,
+            ..Default::default()
+        })?)
     }
 
     /// Destroys `AllocatorPool` object and frees Vulkan device memory.
     pub unsafe fn destroy_pool(&self, pool: AllocatorPool) {
-        ffi::vmaDestroyPool(self.0, pool.0 as *mut _);
+        self.pools.lock().unwrap().retain(|(p, _)| *p != pool);
+        self.destroy_unlisted_pool(pool);
+    }
+
+    /// Destroys a pool created with `Allocator::create_unlisted_pool`.
+    unsafe fn destroy_unlisted_pool(&self, pool: AllocatorPool) {
+        self.exportable.remove(pool);
+        ffi::vmaDestroyPool(self.handle(), pool.handle as *mut _);
+    }
+
+    /// Returns every custom pool created with this allocator and not destroyed yet, in creation
+    /// order.
+    ///
+    /// This includes pools created internally by helpers such as `ThreadLocalArena`, but not the
+    /// single-block pools backing allocations made with a memory allocation chain, e.g. by
+    /// `Allocator::create_buffer_with_allocate_next`.
+    pub fn pools(&self) -> Vec<AllocatorPool> {
+        self.pools
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|&(pool, _)| pool)
+            .collect()
+    }
+
+    /// Destroys every custom pool returned by `Allocator::pools`, e.g. at shutdown.
+    ///
+    /// All allocations made from these pools must have been freed. Helpers that own pools, such
+    /// as `ThreadLocalArena`, destroy them when dropped, so they must be dropped before calling
+    /// this.
+    pub unsafe fn destroy_all_pools(&self) {
+        let pools = mem::take(&mut *self.pools.lock().unwrap());
+        for (pool, _) in pools {
+            self.destroy_unlisted_pool(pool);
+        }
+    }
+
+    /// Returns a copy of the `AllocatorPoolCreateInfo` `pool` was created with, e.g. to create a
+    /// matching pool with `Allocator::create_pool`.
+    ///
+    /// VMA doesn't expose the parameters of a pool, so this returns what the wrapper recorded in
+    /// `Allocator::create_pool`, or `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` if `pool` was
+    /// not created by this allocator. If `AllocatorPoolCreateInfo::memory_allocate_next` is set,
+    /// the copy points to the same chain, which must still be valid when it is used.
+    pub fn clone_pool_config(&self, pool: AllocatorPool) -> VkResult<AllocatorPoolCreateInfo> {
+        self.pools
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(p, _)| *p == pool)
+            .map(|(_, pool_info)| pool_info.clone())
+            .ok_or(vk::Result::ERROR_VALIDATION_FAILED_EXT)
+    }
+
+    /// Wraps `memory`, e.g. imported with `VK_KHR_external_memory`, in a `VirtualBlock` of
+    /// `size` bytes, to suballocate ranges of it.
+    ///
+    /// The vendored VMA cannot register externally allocated `ash::vk::DeviceMemory` as a block of
+    /// a pool, so the memory is not managed by VMA: it doesn't appear in statistics or budgets,
+    /// and `Allocation` handles cannot be made from it. Instead, the returned block hands out
+    /// offsets into `memory`, e.g. for `ash::Device::bind_buffer_memory` with
+    /// `VirtualBlock::device_memory`. `memory` stays owned by the caller and must outlive the
+    /// resources bound to it.
+    pub fn wrap_external_memory(
+        &self,
+        memory: vk::DeviceMemory,
+        size: vk::DeviceSize,
+    ) -> VirtualBlock {
+        VirtualBlock::with_device_memory(memory, size)
+    }
+
+    /// Fragmentation of the free space of all memory blocks, between 0 and 1.
+    ///
+    /// This is `1 - largest / unused`, where `unused` is the number of unused bytes in all blocks
+    /// and `largest` is the sum over memory types of the largest free range in blocks of that
+    /// type, as reported by `Allocator::calculate_stats`. 0 means the free space of each memory
+    /// type is contiguous (or there is none), values close to 1 that it is scattered in small
+    /// ranges, so that larger allocations need new blocks and defragmenting may help. Like
+    /// `Allocator::calculate_stats`, this includes the blocks of custom pools.
+    pub fn fragmentation(&self) -> VkResult<f32> {
+        let stats = self.calculate_stats()?;
+        let largest_free = stats
+            .memory_type
+            .iter()
+            .map(|info| info.unused_range_size_max)
+            .sum();
+        Ok(stats::fragmentation(largest_free, stats.total.unused_bytes))
+    }
+
+    /// Same as `Allocator::fragmentation`, for the blocks of `pool` only.
+    pub unsafe fn pool_fragmentation(&self, pool: AllocatorPool) -> VkResult<f32> {
+        let stats = self.get_pool_stats(pool)?;
+        Ok(stats::fragmentation(
+            stats.unusedRangeSizeMax,
+            stats.unusedSize,
+        ))
     }
 
     /// Retrieves statistics of existing `AllocatorPool` object.
     pub unsafe fn get_pool_stats(&self, pool: AllocatorPool) -> VkResult<ffi::VmaPoolStats> {
         let mut pool_stats: ffi::VmaPoolStats = mem::zeroed();
-        ffi::vmaGetPoolStats(self.0, pool.0 as *mut _, &mut pool_stats);
+        ffi::vmaGetPoolStats(self.handle(), pool.handle as *mut _, &mut pool_stats);
         Ok(pool_stats)
     }
 
+    /// Size of the largest contiguous free range in the existing blocks of `pool`, in bytes.
+    ///
+    /// An allocation up to this size fits without allocating a new block. If a larger allocation
+    /// is needed while the pool has plenty of unused bytes, defragmenting the pool first may help.
+    pub unsafe fn pool_largest_free_range(&self, pool: AllocatorPool) -> VkResult<vk::DeviceSize> {
+        Ok(self.get_pool_stats(pool)?.unusedRangeSizeMax)
+    }
+
+    /// Size of the largest contiguous free range in the existing blocks of memory type
+    /// `memory_type`, in bytes, as reported by `Allocator::calculate_stats`.
+    ///
+    /// The statistics cover blocks of both the default pool and custom pools of that memory type.
+    pub fn default_largest_free_range(&self, memory_type: u32) -> VkResult<vk::DeviceSize> {
+        let stats = self.calculate_stats()?;
+        match stats.memory_type.get(memory_type as usize) {
+            Some(info) => Ok(info.unused_range_size_max),
+            None => Err(vk::Result::ERROR_VALIDATION_FAILED_EXT),
+        }
+    }
+
     /// Marks all allocations in given pool as lost if they are not used in current frame
     /// or AllocatorPoolCreateInfo::frame_in_use_count` back from now.
     ///
     /// Returns the number of allocations marked as lost.
     pub unsafe fn make_pool_allocations_lost(&self, pool: AllocatorPool) -> VkResult<usize> {
         let mut lost_count: usize = 0;
-        ffi::vmaMakePoolAllocationsLost(self.0, pool.0 as *mut _, &mut lost_count);
+        ffi::vmaMakePoolAllocationsLost(self.handle(), pool.handle as *mut _, &mut lost_count);
         Ok(lost_count as usize)
     }
 
@@ -1105,7 +3258,7 @@ impl Allocator {
     /// - Other value: Error returned by Vulkan, e.g. memory mapping failure.
     #[cfg(feature = "detect_corruption")]
     pub unsafe fn check_pool_corruption(&self, pool: AllocatorPool) -> VkResult<()> {
-        ffi_to_result(ffi::vmaCheckPoolCorruption(self.0, pool))
+        ffi_to_result(ffi::vmaCheckPoolCorruption(self.handle(), pool.handle))
     }
 
     /// General purpose memory allocation.
@@ -1118,19 +3271,91 @@ impl Allocator {
         &self,
         memory_requirements: &ash::vk::MemoryRequirements,
         allocation_info: &AllocationCreateInfo,
-    ) -> VkResult<(Allocation, AllocationInfo)> {
-        let create_info = allocation_create_info_to_ffi(&allocation_info);
+    ) -> Result<(Allocation, AllocationInfo), Error> {
         let mut allocation: Allocation = mem::zeroed();
-        let mut allocation_info: AllocationInfo = mem::zeroed();
-        ffi_to_result(ffi::vmaAllocateMemory(
-            self.0,
-            memory_requirements,
-            &create_info,
-            &mut allocation.0,
-            &mut allocation_info.0,
-        ))?;
+        let mut info: AllocationInfo = mem::zeroed();
+        let result = self.allocate_with_dedicated_preference(
+            allocation_info,
+            || None,
+            |create_info| {
+                ffi_to_result(ffi::vmaAllocateMemory(
+                    self.handle(),
+                    memory_requirements,
+                    create_info,
+                    &mut allocation.0,
+                    &mut info.0,
+                ))
+            },
+        );
+        self.check_allocation(result, allocation_info, || *memory_requirements)?;
+        if let Err(err) = self.finish_allocation(allocation_info, allocation, &info) {
+            self.free_memory(allocation);
+            return Err(err.into());
+        }
+
+        Ok((allocation, info))
+    }
+
+    /// Returns the allocations currently placed in `memory`, a block of the default pools or of
+    /// a custom pool, or a dedicated allocation.
+    ///
+    /// VMA can't enumerate the allocations of a block, so the wrapper keeps an index of all
+    /// allocations made through it, updated on every allocation and free. This costs a hash map
+    /// insertion and removal behind a mutex per allocation, so the index and this function are
+    /// only built with the `allocation_index` feature. The block of each allocation is looked up
+    /// when this is called, so the result stays correct after defragmentation, but the call takes
+    /// time linear in the number of live allocations.
+    #[cfg(feature = "allocation_index")]
+    pub fn allocations_in_block(&self, memory: vk::DeviceMemory) -> Vec<Allocation> {
+        let mut allocations = Vec::new();
+        self.allocation_states
+            .for_each(|allocation, _| allocations.push(allocation));
+        allocations.retain(|&allocation| {
+            matches!(
+                unsafe { self.get_allocation_info(allocation) },
+                Ok(info) if info.device_memory() == memory
+            )
+        });
+        allocations
+    }
+
+    /// Same as `Allocator::allocate_memory`, but tags the allocation with the user-defined
+    /// category `tag`, e.g. textures or geometry, accounted for in `Allocator::bytes_by_tag`.
+    pub unsafe fn allocate_tagged(
+        &self,
+        memory_requirements: &ash::vk::MemoryRequirements,
+        allocation_info: &AllocationCreateInfo,
+        tag: u32,
+    ) -> Result<(Allocation, AllocationInfo), Error> {
+        let (allocation, info) = self.allocate_memory(memory_requirements, allocation_info)?;
+        self.allocation_states
+            .update(allocation, |state| state.tag = Some((tag, info.0.size)));
+        Ok((allocation, info))
+    }
 
-        Ok((allocation, allocation_info))
+    /// Tags an existing allocation, e.g. one made with `Allocator::create_image`, with the
+    /// user-defined category `tag`, replacing any previous tag.
+    ///
+    /// Tags are dropped when the allocation is freed through this allocator.
+    pub unsafe fn set_allocation_tag(&self, allocation: Allocation, tag: u32) -> VkResult<()> {
+        let info = self.get_allocation_info(allocation)?;
+        self.allocation_states
+            .update(allocation, |state| state.tag = Some((tag, info.0.size)));
+        Ok(())
+    }
+
+    /// Returns the total size in bytes of all live tagged allocations, per tag.
+    ///
+    /// This is a lightweight per-category breakdown maintained by the wrapper. Untagged
+    /// allocations are not included.
+    pub fn bytes_by_tag(&self) -> std::collections::HashMap<u32, u64> {
+        let mut bytes = std::collections::HashMap::new();
+        self.allocation_states.for_each(|_, state| {
+            if let Some((tag, size)) = state.tag {
+                *bytes.entry(tag).or_insert(0) += size;
+            }
+        });
+        bytes
     }
 
     /// General purpose memory allocation for multiple allocation objects at once.
@@ -1147,28 +3372,74 @@ impl Allocator {
         memory_requirements: &ash::vk::MemoryRequirements,
         allocation_info: &AllocationCreateInfo,
         allocation_count: usize,
-    ) -> VkResult<Vec<(Allocation, AllocationInfo)>> {
-        let create_info = allocation_create_info_to_ffi(&allocation_info);
+    ) -> Result<Vec<(Allocation, AllocationInfo)>, Error> {
+        reentrancy::assert_outside_callback();
+        allocation_info.validate_flags()?;
+        self.validate_pool_flags(allocation_info)?;
+        let create_info =
+            allocation_create_info_to_ffi(&allocation_info, self.excluded_memory_types)?;
         let mut allocations: Vec<ffi::VmaAllocation> = vec![mem::zeroed(); allocation_count];
-        let mut allocation_info: Vec<ffi::VmaAllocationInfo> =
-            vec![mem::zeroed(); allocation_count];
-        ffi_to_result(ffi::vmaAllocateMemoryPages(
-            self.0,
+        let mut infos: Vec<ffi::VmaAllocationInfo> = vec![mem::zeroed(); allocation_count];
+        let result = ffi_to_result(ffi::vmaAllocateMemoryPages(
+            self.handle(),
             memory_requirements,
             &create_info,
             allocation_count,
             allocations.as_mut_ptr(),
-            allocation_info.as_mut_ptr(),
-        ))?;
+            infos.as_mut_ptr(),
+        ))
+        .map_err(Error::Vulkan);
+        self.check_allocation(result, allocation_info, || *memory_requirements)?;
 
-        let it = allocations.iter().zip(allocation_info.iter());
+        let it = allocations.iter().zip(infos.iter());
         let allocations: Vec<(Allocation, AllocationInfo)> = it
             .map(|(alloc, info)| (Allocation(*alloc), AllocationInfo(*info)))
             .collect();
+        for (allocation, info) in &allocations {
+            if let Err(err) = self.finish_allocation(allocation_info, *allocation, info) {
+                let handles: Vec<Allocation> = allocations.iter().map(|(a, _)| *a).collect();
+                self.free_memory_pages(&handles);
+                return Err(err.into());
+            }
+        }
 
         Ok(allocations)
     }
 
+    /// Allocates `page_count` memory pages for binding to the sparse `image` with
+    /// `vkQueueBindSparse`.
+    ///
+    /// For sparse resources, `ash::vk::MemoryRequirements::alignment` is the sparse block size,
+    /// so each page is exactly one block in size and aligned to it, and every page can be bound
+    /// to any block of `image` at the offset reported in its `AllocationInfo`. The pages are
+    /// allocated with `Allocator::allocate_memory_pages`.
+    ///
+    /// Fails with `ash::vk::Result::ERROR_FEATURE_NOT_PRESENT` if `image` reports no sparse
+    /// memory requirements, i.e. was not created with `ash::vk::ImageCreateFlags::SPARSE_BINDING`
+    /// and `ash::vk::ImageCreateFlags::SPARSE_RESIDENCY`.
+    pub unsafe fn allocate_sparse_pages(
+        &self,
+        image: vk::Image,
+        page_count: usize,
+        allocation_info: &AllocationCreateInfo,
+    ) -> Result<Vec<(Allocation, AllocationInfo)>, Error> {
+        if self
+            .device
+            .get_image_sparse_memory_requirements(image)
+            .is_empty()
+        {
+            return Err(Error::Vulkan(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+        }
+
+        let image_requirements = self.device.get_image_memory_requirements(image);
+        let page_requirements = vk::MemoryRequirements {
+            size: image_requirements.alignment,
+            alignment: image_requirements.alignment,
+            memory_type_bits: image_requirements.memory_type_bits,
+        };
+        self.allocate_memory_pages(&page_requirements, allocation_info, page_count)
+    }
+
     /// Buffer specialized memory allocation.
     ///
     /// You should free the memory using `Allocator::free_memory` or 'Allocator::free_memory_pages'.
@@ -1176,19 +3447,54 @@ impl Allocator {
         &self,
         buffer: ash::vk::Buffer,
         allocation_info: &AllocationCreateInfo,
-    ) -> VkResult<(Allocation, AllocationInfo)> {
-        let create_info = allocation_create_info_to_ffi(&allocation_info);
+    ) -> Result<(Allocation, AllocationInfo), Error> {
         let mut allocation: Allocation = mem::zeroed();
-        let mut allocation_info: AllocationInfo = mem::zeroed();
-        ffi_to_result(ffi::vmaAllocateMemoryForBuffer(
-            self.0,
-            buffer,
-            &create_info,
-            &mut allocation.0,
-            &mut allocation_info.0,
-        ))?;
+        let mut info: AllocationInfo = mem::zeroed();
+        let result = self.allocate_with_dedicated_preference(
+            allocation_info,
+            || self.buffer_dedicated_requirements(buffer),
+            |create_info| {
+                ffi_to_result(ffi::vmaAllocateMemoryForBuffer(
+                    self.handle(),
+                    buffer,
+                    create_info,
+                    &mut allocation.0,
+                    &mut info.0,
+                ))
+            },
+        );
+        self.check_allocation(result, allocation_info, || {
+            self.device.get_buffer_memory_requirements(buffer)
+        })?;
+        if let Err(err) = self.finish_allocation(allocation_info, allocation, &info) {
+            self.free_memory(allocation);
+            return Err(err.into());
+        }
+        self.allocation_states
+            .update(allocation, |state| state.unbound = true);
 
-        Ok((allocation, allocation_info))
+        Ok((allocation, info))
+    }
+
+    /// Allocates memory for a buffer that has not been created yet.
+    ///
+    /// Memory requirements are queried once from a temporary buffer created from `buffer_info`,
+    /// and the allocation is made against them with `Allocator::allocate_memory`. The requirements
+    /// are returned as well, so the real buffer can be created and bound to the allocation later
+    /// without another query.
+    ///
+    /// Since VMA only sees the requirements, it cannot apply dedicated allocation hints of the
+    /// driver, and treats the allocation conservatively regarding buffer-image granularity.
+    pub unsafe fn allocate_memory_for_buffer_info(
+        &self,
+        buffer_info: &vk::BufferCreateInfo,
+        allocation_info: &AllocationCreateInfo,
+    ) -> Result<(Allocation, AllocationInfo, vk::MemoryRequirements), Error> {
+        let memory_requirements = self.buffer_info_memory_requirements(buffer_info)?;
+        let (allocation, info) = self.allocate_memory(&memory_requirements, allocation_info)?;
+        self.allocation_states
+            .update(allocation, |state| state.unbound = true);
+        Ok((allocation, info, memory_requirements))
     }
 
     /// Image specialized memory allocation.
@@ -1198,25 +3504,46 @@ impl Allocator {
         &self,
         image: ash::vk::Image,
         allocation_info: &AllocationCreateInfo,
-    ) -> VkResult<(Allocation, AllocationInfo)> {
-        let create_info = allocation_create_info_to_ffi(&allocation_info);
+    ) -> Result<(Allocation, AllocationInfo), Error> {
         let mut allocation: Allocation = mem::zeroed();
-        let mut allocation_info: AllocationInfo = mem::zeroed();
-        ffi_to_result(ffi::vmaAllocateMemoryForImage(
-            self.0,
-            image,
-            &create_info,
-            &mut allocation.0,
-            &mut allocation_info.0,
-        ))?;
+        let mut info: AllocationInfo = mem::zeroed();
+        let result = self.allocate_with_dedicated_preference(
+            allocation_info,
+            || self.image_dedicated_requirements(image),
+            |create_info| {
+                ffi_to_result(ffi::vmaAllocateMemoryForImage(
+                    self.handle(),
+                    image,
+                    create_info,
+                    &mut allocation.0,
+                    &mut info.0,
+                ))
+            },
+        );
+        self.check_allocation(result, allocation_info, || {
+            self.device.get_image_memory_requirements(image)
+        })?;
+        if let Err(err) = self.finish_allocation(allocation_info, allocation, &info) {
+            self.free_memory(allocation);
+            return Err(err.into());
+        }
+        self.allocation_states
+            .update(allocation, |state| state.unbound = true);
 
-        Ok((allocation, allocation_info))
+        Ok((allocation, info))
     }
 
     /// Frees memory previously allocated using `Allocator::allocate_memory`,
     /// `Allocator::allocate_memory_for_buffer`, or `Allocator::allocate_memory_for_image`.
+    ///
+    /// It is safe to pass null as `allocation`, which does nothing.
     pub unsafe fn free_memory(&self, allocation: Allocation) {
-        ffi::vmaFreeMemory(self.0, allocation.0);
+        if allocation.is_null() {
+            return;
+        }
+        let state = self.release_allocation(allocation);
+        ffi::vmaFreeMemory(self.handle(), allocation.0);
+        self.finish_free(allocation, state);
     }
 
     /// Frees memory and destroys multiple allocations.
@@ -1227,9 +3554,46 @@ impl Allocator {
     ///
     /// It may be internally optimized to be more efficient than calling 'Allocator::free_memory` `allocations.len()` times.
     ///
-    /// Allocations in 'allocations' slice can come from any memory pools and types.
+    /// Allocations in 'allocations' slice can come from any memory pools and types. Null entries
+    /// are skipped.
     pub unsafe fn free_memory_pages(&self, allocations: &[Allocation]) {
-        ffi::vmaFreeMemoryPages(self.0, allocations.len(), allocations.as_ptr() as *mut _);
+        let states: Vec<AllocationState> = allocations
+            .iter()
+            .map(|&allocation| self.release_allocation(allocation))
+            .collect();
+        // VMA skips null entries as well.
+        ffi::vmaFreeMemoryPages(
+            self.handle(),
+            allocations.len(),
+            allocations.as_ptr() as *mut _,
+        );
+        for (&allocation, state) in allocations.iter().zip(states) {
+            self.finish_free(allocation, state);
+        }
+    }
+
+    /// Same as `Allocator::free_memory_pages`, but first sorts `allocations` by memory block and
+    /// offset, so that VMA frees neighbouring allocations one after another.
+    ///
+    /// Freeing thousands of allocations in a scattered order touches VMA's per-block metadata
+    /// at random. Sorting costs one `Allocator::get_allocation_info` per allocation, and pays off
+    /// for large batches. The order of `allocations` is changed: null entries, which are
+    /// skipped, are moved to the front.
+    pub unsafe fn free_memory_pages_sorted(&self, allocations: &mut [Allocation]) {
+        let mut null_count = 0;
+        for index in 0..allocations.len() {
+            if allocations[index].is_null() {
+                allocations.swap(index, null_count);
+                null_count += 1;
+            }
+        }
+        let allocations = &mut allocations[null_count..];
+        allocations.sort_by_cached_key(|&allocation| {
+            let mut info: ffi::VmaAllocationInfo = mem::zeroed();
+            ffi::vmaGetAllocationInfo(self.handle(), allocation.0, &mut info);
+            (info.deviceMemory, info.offset)
+        });
+        self.free_memory_pages(allocations);
     }
 
     /// Returns current information about specified allocation and atomically marks it as used in current frame.
@@ -1247,10 +3611,50 @@ impl Allocator {
     /// If you just want to check if allocation is not lost, `Allocator::touch_allocation` will work faster.
     pub unsafe fn get_allocation_info(&self, allocation: Allocation) -> VkResult<AllocationInfo> {
         let mut allocation_info: AllocationInfo = mem::zeroed();
-        ffi::vmaGetAllocationInfo(self.0, allocation.0, &mut allocation_info.0);
+        ffi::vmaGetAllocationInfo(self.handle(), allocation.0, &mut allocation_info.0);
         Ok(allocation_info)
     }
 
+    /// Returns the memory block, offset, size and handle types needed to export `allocation`,
+    /// e.g. with `vkGetMemoryFdKHR` or `vkGetMemoryWin32HandleKHR`.
+    ///
+    /// Exporting requires the memory to be allocated with `ash::vk::ExportMemoryAllocateInfo`,
+    /// which VMA only supports for custom pools: chain it to
+    /// `AllocatorPoolCreateInfo::memory_allocate_next` before `Allocator::create_pool` and allocate
+    /// from that pool. If `allocation` did not come from such a pool, `ExportInfo::handle_type`
+    /// is empty, warning that the memory cannot be exported.
+    pub unsafe fn export_memory_info(&self, allocation: Allocation) -> VkResult<ExportInfo> {
+        let info = self.get_allocation_info(allocation)?;
+        Ok(ExportInfo {
+            device_memory: info.device_memory(),
+            offset: info.device_offset(),
+            size: info.device_size(),
+            handle_type: self.allocation_states.get(allocation).export_handle_types,
+        })
+    }
+
+    /// Returns the parameters of `allocation`, the size of its memory block and whether the block
+    /// is dedicated to it.
+    ///
+    /// The vendored VMA predates `vmaGetAllocationInfo2`, so the block sizes are tracked with
+    /// device memory callbacks, and whether the block is dedicated is only a heuristic: an
+    /// allocation is reported as dedicated if it starts at offset 0 and covers its whole block.
+    /// An allocation that happens to fill a block of a default or custom pool on its own is
+    /// reported as dedicated as well.
+    pub unsafe fn get_allocation_info2(&self, allocation: Allocation) -> VkResult<AllocationInfo2> {
+        let allocation_info = self.get_allocation_info(allocation)?;
+        let block_size = self
+            .device_memory
+            .size(allocation_info.device_memory())
+            .unwrap_or(0);
+        Ok(AllocationInfo2 {
+            allocation_info,
+            block_size,
+            dedicated_memory: allocation_info.device_offset() == 0
+                && allocation_info.device_size() == block_size,
+        })
+    }
+
     /// Returns `true` if allocation is not lost and atomically marks it as used in current frame.
     ///
     /// If the allocation has been created with `AllocationCreateFlags::CAN_BECOME_LOST` flag,
@@ -1265,7 +3669,7 @@ impl Allocator {
     /// If the allocation has been created without `AllocationCreateFlags::CAN_BECOME_LOST` flag,
     /// this function always returns `true`.
     pub unsafe fn touch_allocation(&self, allocation: Allocation) -> VkResult<bool> {
-        let result = ffi::vmaTouchAllocation(self.0, allocation.0);
+        let result = ffi::vmaTouchAllocation(self.handle(), allocation.0);
         Ok(result == ash::vk::TRUE)
     }
 
@@ -1286,7 +3690,33 @@ impl Allocator {
         allocation: Allocation,
         user_data: *mut ::std::os::raw::c_void,
     ) {
-        ffi::vmaSetAllocationUserData(self.0, allocation.0, user_data);
+        ffi::vmaSetAllocationUserData(self.handle(), allocation.0, user_data);
+    }
+
+    /// Same as `Allocator::set_allocation_user_data`, but returns the previous user data.
+    ///
+    /// The previous value is read with `Allocator::get_allocation_info` right before setting the
+    /// new one. The two steps are not atomic with respect to other threads setting user data of
+    /// the same allocation, so synchronize those externally. For allocations created with
+    /// `AllocationCreateFlags::USER_DATA_COPY_STRING`, the returned string has already been
+    /// freed by VMA and must not be dereferenced.
+    pub unsafe fn replace_allocation_user_data(
+        &self,
+        allocation: Allocation,
+        user_data: *mut ::std::os::raw::c_void,
+    ) -> VkResult<*mut ::std::os::raw::c_void> {
+        let previous = self.get_allocation_info(allocation)?.user_data();
+        self.set_allocation_user_data(allocation, user_data);
+        Ok(previous)
+    }
+
+    /// Returns `true` if `allocation` lives in `ash::vk::MemoryPropertyFlags::HOST_VISIBLE`
+    /// memory, so `Allocator::map_memory` can succeed for it.
+    ///
+    /// Unlike attempting `Allocator::map_memory`, this has no side effects. Same as
+    /// `AllocationInfo::is_host_visible` on the result of `Allocator::get_allocation_info`.
+    pub unsafe fn is_mappable(&self, allocation: Allocation) -> VkResult<bool> {
+        Ok(self.get_allocation_info(allocation)?.is_host_visible(self))
     }
 
     /// Creates new allocation that is in lost state from the beginning.
@@ -1300,7 +3730,7 @@ impl Allocator {
     /// a real, non-empty allocation.
     pub unsafe fn create_lost_allocation(&self) -> VkResult<Allocation> {
         let mut allocation: Allocation = mem::zeroed();
-        ffi::vmaCreateLostAllocation(self.0, &mut allocation.0);
+        ffi::vmaCreateLostAllocation(self.handle(), &mut allocation.0);
         Ok(allocation)
     }
 
@@ -1336,18 +3766,154 @@ impl Allocator {
     /// This function fails when used on allocation made in memory type that is not
     /// `ash::vk::MemoryPropertyFlags::HOST_VISIBLE`.
     ///
-    /// This function always fails when called for allocation that was created with
-    /// `AllocationCreateFlags::CAN_BECOME_LOST` flag. Such allocations cannot be mapped.
-    pub unsafe fn map_memory(&self, allocation: Allocation) -> VkResult<*mut u8> {
-        let mut mapped_data: *mut ::std::os::raw::c_void = ::std::ptr::null_mut();
-        ffi_to_result(ffi::vmaMapMemory(self.0, allocation.0, &mut mapped_data))?;
+    /// This function always fails when called for allocation that was created with
+    /// `AllocationCreateFlags::CAN_BECOME_LOST` flag. Such allocations cannot be mapped.
+    pub unsafe fn map_memory(&self, allocation: Allocation) -> VkResult<*mut u8> {
+        reentrancy::assert_outside_callback();
+        let mut mapped_data: *mut ::std::os::raw::c_void = ::std::ptr::null_mut();
+        ffi_to_result(ffi::vmaMapMemory(
+            self.handle(),
+            allocation.0,
+            &mut mapped_data,
+        ))?;
+        trace::mapped(allocation);
+
+        Ok(mapped_data as *mut u8)
+    }
+
+    /// Same as `Allocator::map_memory`, but also returns current information about the allocation,
+    /// as `Allocator::get_allocation_info` would.
+    ///
+    /// Useful when the size or offset of the allocation is needed right after mapping it.
+    pub unsafe fn map_memory_with_info(
+        &self,
+        allocation: Allocation,
+    ) -> VkResult<(*mut u8, AllocationInfo)> {
+        let mapped_data = self.map_memory(allocation)?;
+        let allocation_info = self.get_allocation_info(allocation)?;
+
+        Ok((mapped_data, allocation_info))
+    }
+
+    /// Maps memory represented by given allocation for reading on the host.
+    ///
+    /// The whole allocation is invalidated after mapping, so data written by the device is visible
+    /// through the returned guard even on memory that is not `ash::vk::MemoryPropertyFlags::HOST_COHERENT`.
+    /// This is the intended way to read back `MemoryUsage::GpuToCpu` allocations.
+    ///
+    /// The memory is unmapped when the returned `ReadMapping` is dropped. Returns
+    /// `ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY` if the allocation does not fit into the host address space.
+    pub unsafe fn map_memory_for_read(&self, allocation: Allocation) -> VkResult<ReadMapping<'_>> {
+        let (mapped_data, allocation_info) = self.map_memory_with_info(allocation)?;
+        let size = device_size_to_host(allocation_info.device_size()).and_then(|size| {
+            if !allocation_info.is_host_coherent(self) {
+                self.invalidate_allocation(allocation, 0, vk::WHOLE_SIZE as usize)?;
+            }
+            Ok(size)
+        });
+        let size = match size {
+            Ok(size) => size,
+            Err(err) => {
+                self.unmap_memory(allocation);
+                return Err(err.into());
+            }
+        };
+
+        Ok(ReadMapping::new(self, allocation, mapped_data, size))
+    }
+
+    /// Maps memory represented by given allocation for writing on the host.
+    ///
+    /// The whole allocation is flushed and unmapped when the returned `WriteMapping` is dropped,
+    /// so host writes become visible to the device even on memory that is not
+    /// `ash::vk::MemoryPropertyFlags::HOST_COHERENT`. Returns `ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY`
+    /// if the allocation does not fit into the host address space.
+    pub unsafe fn map_memory_for_write(
+        &self,
+        allocation: Allocation,
+    ) -> VkResult<WriteMapping<'_>> {
+        let (mapped_data, allocation_info) = self.map_memory_with_info(allocation)?;
+        let size = match device_size_to_host(allocation_info.device_size()) {
+            Ok(size) => size,
+            Err(err) => {
+                self.unmap_memory(allocation);
+                return Err(err.into());
+            }
+        };
+
+        Ok(WriteMapping::new(
+            self,
+            allocation,
+            mapped_data,
+            size,
+            allocation_info.is_host_coherent(self),
+        ))
+    }
+
+    /// Maps memory represented by given allocation and returns it as a `T`.
+    ///
+    /// The allocation must be at least `size_of::<T>()` bytes large and the mapped pointer must be
+    /// suitably aligned for `T`, otherwise the memory is unmapped again and
+    /// `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` is returned.
+    ///
+    /// If the memory is not `ash::vk::MemoryPropertyFlags::HOST_COHERENT`, the `size_of::<T>()`
+    /// bytes are flushed when the returned `MappedRef` is dropped. The memory is unmapped as well.
+    pub unsafe fn mapped_as<T: Copy>(&self, allocation: Allocation) -> VkResult<MappedRef<'_, T>> {
+        let (mapped_data, allocation_info) = self.map_memory_with_info(allocation)?;
+        if mem::size_of::<T>() as vk::DeviceSize > allocation_info.device_size()
+            || mapped_data as usize % mem::align_of::<T>() != 0
+        {
+            self.unmap_memory(allocation);
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+        }
+
+        Ok(MappedRef::new(
+            self,
+            allocation,
+            mapped_data as *mut T,
+            allocation_info.is_host_coherent(self),
+        ))
+    }
+
+    /// Maps memory represented by given allocation and returns a guard that unmaps it when
+    /// dropped.
+    ///
+    /// Same as `Allocator::map_memory`, but the matching `Allocator::unmap_memory` can neither be
+    /// forgotten nor called twice. The guard dereferences to the mapped pointer.
+    pub unsafe fn map_guarded(&self, allocation: Allocation) -> VkResult<MapGuard<'_>> {
+        let mapped_data = self.map_memory(allocation)?;
+        Ok(MapGuard::new(self, allocation, mapped_data))
+    }
 
-        Ok(mapped_data as *mut u8)
+    /// Maps memory represented by given allocation for as long as the returned `PersistentMap`
+    /// lives, e.g. for a dynamic buffer written every frame.
+    ///
+    /// The guard holds one map reference of the allocation until it is dropped, so repeated
+    /// access doesn't go through `Allocator::map_memory` and `Allocator::unmap_memory` each time.
+    /// Writes are only flushed by `PersistentMap::flush`.
+    pub unsafe fn map_persistent(&self, allocation: Allocation) -> VkResult<PersistentMap<'_>> {
+        let (mapped_data, allocation_info) = self.map_memory_with_info(allocation)?;
+        let size = match device_size_to_host(allocation_info.device_size()) {
+            Ok(size) => size,
+            Err(err) => {
+                self.unmap_memory(allocation);
+                return Err(err.into());
+            }
+        };
+
+        Ok(PersistentMap::new(
+            self,
+            allocation,
+            mapped_data,
+            size,
+            allocation_info.is_host_coherent(self),
+        ))
     }
 
     /// Unmaps memory represented by given allocation, mapped previously using `Allocator::map_memory`.
     pub unsafe fn unmap_memory(&self, allocation: Allocation) {
-        ffi::vmaUnmapMemory(self.0, allocation.0);
+        ffi::vmaUnmapMemory(self.handle(), allocation.0);
+        trace::unmapped(allocation);
     }
 
     /// Flushes memory of given allocation.
@@ -1355,22 +3921,31 @@ impl Allocator {
     /// Calls `ash::vk::Device::FlushMappedMemoryRanges` for memory associated with given range of given allocation.
     ///
     /// - `offset` must be relative to the beginning of allocation.
-    /// - `size` can be `ash::vk::WHOLE_SIZE`. It means all memory from `offset` the the end of given allocation.
+    /// - `size` can be `ash::vk::WHOLE_SIZE as usize`. It means all memory from `offset` the the end of given allocation.
     /// - `offset` and `size` don't have to be aligned; hey are internally rounded down/up to multiple of `nonCoherentAtomSize`.
     /// - If `size` is 0, this call is ignored.
     /// - If memory type that the `allocation` belongs to is not `ash::vk::MemoryPropertyFlags::HOST_VISIBLE` or it is `ash::vk::MemoryPropertyFlags::HOST_COHERENT`, this call is ignored.
+    ///   If the device has no such memory types at all, the call returns without calling into VMA.
+    ///   Otherwise, check `AllocationInfo::is_host_coherent` to skip the call for a particular
+    ///   allocation, as the mapping guards of this crate do.
+    /// - Returns `Error::SizeOverflow` if `offset + size` overflows `ash::vk::DeviceSize`.
     pub unsafe fn flush_allocation(
         &self,
         allocation: Allocation,
         offset: usize,
         size: usize,
-    ) -> VkResult<()> {
+    ) -> Result<(), Error> {
+        let (offset, size) = host_range_to_device(offset, size)?;
+        if self.non_coherent_memory_types == 0 {
+            return Ok(());
+        }
         ffi_to_result(ffi::vmaFlushAllocation(
-            self.0,
+            self.handle(),
             allocation.0,
-            offset as vk::DeviceSize,
-            size as vk::DeviceSize,
-        ))
+            offset,
+            size,
+        ))?;
+        Ok(())
     }
 
     /// Invalidates memory of given allocation.
@@ -1378,22 +3953,69 @@ impl Allocator {
     /// Calls `ash::vk::Device::invalidate_mapped_memory_ranges` for memory associated with given range of given allocation.
     ///
     /// - `offset` must be relative to the beginning of allocation.
-    /// - `size` can be `ash::vk::WHOLE_SIZE`. It means all memory from `offset` the the end of given allocation.
+    /// - `size` can be `ash::vk::WHOLE_SIZE as usize`. It means all memory from `offset` the the end of given allocation.
     /// - `offset` and `size` don't have to be aligned. They are internally rounded down/up to multiple of `nonCoherentAtomSize`.
     /// - If `size` is 0, this call is ignored.
     /// - If memory type that the `allocation` belongs to is not `ash::vk::MemoryPropertyFlags::HOST_VISIBLE` or it is `ash::vk::MemoryPropertyFlags::HOST_COHERENT`, this call is ignored.
+    ///   If the device has no such memory types at all, the call returns without calling into VMA.
+    ///   Otherwise, check `AllocationInfo::is_host_coherent` to skip the call for a particular
+    ///   allocation, as the mapping guards of this crate do.
+    /// - Returns `Error::SizeOverflow` if `offset + size` overflows `ash::vk::DeviceSize`.
     pub unsafe fn invalidate_allocation(
         &self,
         allocation: Allocation,
         offset: usize,
         size: usize,
-    ) -> VkResult<()> {
+    ) -> Result<(), Error> {
+        let (offset, size) = host_range_to_device(offset, size)?;
+        if self.non_coherent_memory_types == 0 {
+            return Ok(());
+        }
         ffi_to_result(ffi::vmaInvalidateAllocation(
-            self.0,
+            self.handle(),
             allocation.0,
-            offset as vk::DeviceSize,
-            size as vk::DeviceSize,
-        ))
+            offset,
+            size,
+        ))?;
+        Ok(())
+    }
+
+    /// Rounds a range of `allocation` the way `Allocator::flush_allocation` and
+    /// `Allocator::invalidate_allocation` do, returning the aligned offset and size.
+    ///
+    /// The offset is rounded down and the end up to a multiple of `nonCoherentAtomSize`, taken
+    /// from the physical device properties cached by VMA, and the range is clamped to the end of
+    /// the allocation. `size` can be `ash::vk::WHOLE_SIZE`, meaning up to the end of the
+    /// allocation. VMA aligns allocations in non-coherent memory to `nonCoherentAtomSize`, so
+    /// ranges of the same allocation rounded this way can be merged where they touch or overlap
+    /// before flushing them, and flushing the result covers the same memory as flushing each
+    /// range on its own.
+    pub unsafe fn align_flush_range(
+        &self,
+        allocation: Allocation,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    ) -> VkResult<(vk::DeviceSize, vk::DeviceSize)> {
+        let atom_size = self
+            .get_physical_device_properties()?
+            .limits
+            .non_coherent_atom_size
+            .max(1);
+        let allocation_size = self.get_allocation_info(allocation)?.device_size();
+        let aligned_offset = offset / atom_size * atom_size;
+        if aligned_offset >= allocation_size {
+            return Ok((aligned_offset, 0));
+        }
+        let available = allocation_size - aligned_offset;
+        let aligned_size = if size == vk::WHOLE_SIZE {
+            available
+        } else {
+            let end = (offset - aligned_offset).saturating_add(size);
+            end.checked_add(atom_size - 1)
+                .map_or(available, |end| end / atom_size * atom_size)
+                .min(available)
+        };
+        Ok((aligned_offset, aligned_size))
     }
 
     /// Checks magic number in margins around all allocations in given memory types (in both default and custom pools) in search for corruptions.
@@ -1414,7 +4036,23 @@ impl Allocator {
         &self,
         memory_types: ash::vk::MemoryPropertyFlags,
     ) -> VkResult<()> {
-        ffi_to_result(ffi::vmaCheckCorruption(self.0, memory_types.as_raw()))
+        ffi_to_result(ffi::vmaCheckCorruption(
+            self.handle(),
+            memory_types.as_raw(),
+        ))
+    }
+
+    /// Checks magic number in margins around the given allocation in search for corruptions.
+    ///
+    /// This is `Allocator::check_corruption` narrowed down to the single memory type the allocation
+    /// was made from. All allocations in that memory type are checked, so the corruption reported
+    /// may come from a neighboring allocation in the same memory type.
+    ///
+    /// Possible error values are the same as for `Allocator::check_corruption`.
+    #[cfg(feature = "detect_corruption")]
+    pub unsafe fn check_allocation_corruption(&self, allocation: Allocation) -> VkResult<()> {
+        let memory_type = self.get_allocation_info(allocation)?.memory_type();
+        ffi_to_result(ffi::vmaCheckCorruption(self.handle(), 1 << memory_type))
     }
 
     /// Begins defragmentation process.
@@ -1448,18 +4086,14 @@ impl Allocator {
             None => ash::vk::CommandBuffer::null(),
         };
 
-        let mut context = DefragmentationContext {
-            internal: mem::zeroed(),
-            stats: ffi::VmaDefragmentationStats {
-                bytesMoved: 0,
-                bytesFreed: 0,
-                allocationsMoved: 0,
-                deviceMemoryBlocksFreed: 0,
-            },
-            changed: vec![ash::vk::FALSE; info.allocations.len()],
-        };
+        let mut context = DefragmentationContext::new(info.allocations.len());
 
-        let pools = info.pools.unwrap_or(&[]);
+        let pools: Vec<ffi::VmaPool> = info
+            .pools
+            .unwrap_or(&[])
+            .iter()
+            .map(|pool| pool.handle)
+            .collect();
 
         let ffi_info = ffi::VmaDefragmentationInfo2 {
             flags: 0, // Reserved for future use
@@ -1476,11 +4110,12 @@ impl Allocator {
         };
 
         ffi_to_result(ffi::vmaDefragmentationBegin(
-            self.0,
+            self.handle(),
             &ffi_info,
-            &mut context.stats as *mut _,
+            &mut *context.stats,
             &mut context.internal,
         ))?;
+        trace::defragmentation_begun(info.allocations.len(), pools.len());
 
         Ok(context)
     }
@@ -1492,18 +4127,255 @@ impl Allocator {
         &self,
         context: &mut DefragmentationContext,
     ) -> VkResult<(DefragmentationStats, Vec<bool>)> {
-        ffi_to_result(ffi::vmaDefragmentationEnd(self.0, context.internal))?;
+        ffi_to_result(ffi::vmaDefragmentationEnd(self.handle(), context.internal))?;
 
         let changed: Vec<bool> = context.changed.iter().map(|change| *change == 1).collect();
+        let stats = DefragmentationStats::from(&*context.stats);
+        trace::defragmented(&stats);
+
+        Ok((stats, changed))
+    }
+
+    /// Returns the current mapped pointer of each of `allocations`, in the same order.
+    ///
+    /// Moving an allocation during defragmentation changes its `AllocationInfo::mapped_data`.
+    /// Call this after `Allocator::defragmentation_end` to refresh pointers cached for allocations
+    /// created with `AllocationCreateFlags::MAPPED`. Allocations that are not mapped yield null.
+    pub unsafe fn refresh_mapped_pointers(
+        &self,
+        allocations: &[Allocation],
+    ) -> VkResult<Vec<*mut u8>> {
+        allocations
+            .iter()
+            .map(|&allocation| Ok(self.get_allocation_info(allocation)?.mapped_data()))
+            .collect()
+    }
+
+    /// Begins defragmenting the memory of images, moving them with `vkCmdCopyImage`.
+    ///
+    /// Each entry is an image, the allocation it is bound to, and the `ash::vk::ImageCreateInfo`
+    /// it was created with. VMA picks which allocations to move. For each of them, a new image is
+    /// created from the same create info and bound to the new place, and a copy of all mip levels
+    /// and array layers from the old image to the new one is recorded in `command_buffer`.
+    /// Returns the index in `entries` and the new image of every moved entry.
+    ///
+    /// This runs a single pass of an incremental defragmentation, which
+    /// `Allocator::end_image_defragmentation` ends together with the defragmentation, since the
+    /// copies have to finish executing before VMA can plan the next pass. To defragment further,
+    /// replace the old images of moved entries with the new ones, transition them to
+    /// `ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL`, and call this function again until no entry
+    /// is moved.
+    ///
+    /// Layout requirements, since the copy cannot change them:
+    ///
+    /// - Every image in `entries` must be in `ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL` by the
+    ///   time the copies execute, with prior writes made visible to transfers. Record these
+    ///   barriers before calling this function.
+    /// - The new images are transitioned from `ash::vk::ImageLayout::UNDEFINED` to
+    ///   `ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL` by a barrier recorded here, and are left in
+    ///   that layout. Record barriers to the layouts they are used in after this function.
+    /// - Their `usage` must include `ash::vk::ImageUsageFlags::TRANSFER_SRC` and
+    ///   `ash::vk::ImageUsageFlags::TRANSFER_DST`.
+    ///
+    /// Then submit `command_buffer`, wait for it to finish, and call
+    /// `Allocator::end_image_defragmentation`. Only after that the old images of moved entries
+    /// can be destroyed (with `ash::Device::destroy_image`, not `Allocator::destroy_image`, as
+    /// the allocation now belongs to the new image). Until then, the same restrictions as between
+    /// `Allocator::defragmentation_begin` and `Allocator::defragmentation_end` apply.
+    ///
+    /// Multi-planar formats are not supported.
+    pub unsafe fn defragment_images(
+        &self,
+        entries: &[(Allocation, vk::Image, vk::ImageCreateInfo)],
+        command_buffer: vk::CommandBuffer,
+    ) -> VkResult<(DefragmentationContext, Vec<(usize, vk::Image)>)> {
+        let allocations: Vec<Allocation> = entries.iter().map(|entry| entry.0).collect();
+        let mut context = DefragmentationContext::new(entries.len());
+
+        let ffi_info = ffi::VmaDefragmentationInfo2 {
+            flags: ffi::VmaDefragmentationFlagBits_VMA_DEFRAGMENTATION_FLAG_INCREMENTAL as _,
+            allocationCount: allocations.len() as u32,
+            pAllocations: allocations.as_ptr() as *mut _,
+            pAllocationsChanged: context.changed.as_mut_ptr(),
+            poolCount: 0,
+            pPools: std::ptr::null_mut(),
+            maxCpuBytesToMove: 0,
+            maxCpuAllocationsToMove: 0,
+            maxGpuBytesToMove: vk::WHOLE_SIZE,
+            maxGpuAllocationsToMove: u32::MAX,
+            commandBuffer: vk::CommandBuffer::null(),
+        };
+        incremental_defragmentation_result(ffi::vmaDefragmentationBegin(
+            self.handle(),
+            &ffi_info,
+            &mut *context.stats,
+            &mut context.internal,
+        ))?;
+        trace::defragmentation_begun(entries.len(), 0);
 
-        let stats = DefragmentationStats {
-            bytes_moved: context.stats.bytesMoved as usize,
-            bytes_freed: context.stats.bytesFreed as usize,
-            allocations_moved: context.stats.allocationsMoved,
-            device_memory_blocks_freed: context.stats.deviceMemoryBlocksFreed,
+        let mut moves: Vec<ffi::VmaDefragmentationPassMoveInfo> =
+            (0..entries.len()).map(|_| mem::zeroed()).collect();
+        let mut pass = ffi::VmaDefragmentationPassInfo {
+            moveCount: moves.len() as u32,
+            pMoves: moves.as_mut_ptr(),
         };
+        let result = incremental_defragmentation_result(ffi::vmaBeginDefragmentationPass(
+            self.handle(),
+            context.internal,
+            &mut pass,
+        ))
+        .and_then(|()| {
+            self.record_image_moves(entries, &moves[..pass.moveCount as usize], command_buffer)
+        });
+
+        match result {
+            Ok(moved) => Ok((context, moved)),
+            Err(err) => {
+                let _ = ffi::vmaEndDefragmentationPass(self.handle(), context.internal);
+                let _ = ffi::vmaDefragmentationEnd(self.handle(), context.internal);
+                Err(err)
+            }
+        }
+    }
 
-        Ok((stats, changed))
+    /// Creates and binds the new images of `moves` and records their copies.
+    unsafe fn record_image_moves(
+        &self,
+        entries: &[(Allocation, vk::Image, vk::ImageCreateInfo)],
+        moves: &[ffi::VmaDefragmentationPassMoveInfo],
+        command_buffer: vk::CommandBuffer,
+    ) -> VkResult<Vec<(usize, vk::Image)>> {
+        let indices: std::collections::HashMap<Allocation, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (entry.0, index))
+            .collect();
+
+        let mut moved = Vec::with_capacity(moves.len());
+        for pass_move in moves {
+            let index = indices[&Allocation(pass_move.allocation)];
+            let image = match self
+                .device
+                .create_image(&entries[index].2, self.allocation_callbacks.as_deref())
+            {
+                Ok(image) => image,
+                Err(err) => {
+                    for &(_, image) in &moved {
+                        self.device
+                            .destroy_image(image, self.allocation_callbacks.as_deref());
+                    }
+                    return Err(err);
+                }
+            };
+            moved.push((index, image));
+            if let Err(err) =
+                self.device
+                    .bind_image_memory(image, pass_move.memory, pass_move.offset)
+            {
+                for &(_, image) in &moved {
+                    self.device
+                        .destroy_image(image, self.allocation_callbacks.as_deref());
+                }
+                return Err(err);
+            }
+        }
+
+        let barriers: Vec<vk::ImageMemoryBarrier> = moved
+            .iter()
+            .map(|&(index, image)| {
+                let image_info = &entries[index].2;
+                vk::ImageMemoryBarrier::builder()
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(image)
+                    .subresource_range(vk::ImageSubresourceRange {
+                        aspect_mask: format_aspect_mask(image_info.format),
+                        base_mip_level: 0,
+                        level_count: image_info.mip_levels,
+                        base_array_layer: 0,
+                        layer_count: image_info.array_layers,
+                    })
+                    .build()
+            })
+            .collect();
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &barriers,
+        );
+
+        for &(index, image) in &moved {
+            let (_, old_image, ref image_info) = entries[index];
+            self.device.cmd_copy_image(
+                command_buffer,
+                old_image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &image_copy_regions(image_info),
+            );
+        }
+        Ok(moved)
+    }
+
+    /// Finishes a defragmentation started by `Allocator::defragment_images`.
+    ///
+    /// The command buffer passed to it must have finished executing. Afterwards, the allocations
+    /// of moved entries point to their new place, and the old images can be destroyed.
+    pub unsafe fn end_image_defragmentation(
+        &self,
+        context: &mut DefragmentationContext,
+    ) -> VkResult<DefragmentationStats> {
+        incremental_defragmentation_result(ffi::vmaEndDefragmentationPass(
+            self.handle(),
+            context.internal,
+        ))?;
+        let (stats, _) = self.defragmentation_end(context)?;
+        Ok(stats)
+    }
+
+    /// Performs a bounded amount of CPU defragmentation of a single custom `pool`.
+    ///
+    /// At most `max_bytes_to_move` bytes and `max_allocations_to_move` allocations are moved per
+    /// call, so the time spent is bounded as well. Call it repeatedly, e.g. once per frame, until
+    /// `DefragmentationStats::allocations_moved` is 0.
+    ///
+    /// The defragmentation only locks the internal mutex of `pool`, so allocations, frees and
+    /// mapping in other pools or the default pools don't stall on it. This makes it suitable
+    /// for running on a worker thread while other threads keep allocating: `Allocator` is `Sync`,
+    /// so a reference to it can be shared with the worker. Calls touching `pool` or its
+    /// allocations from other threads block until the step finishes, and must not be made from
+    /// the thread running the step.
+    ///
+    /// Any allocation of `pool` may be moved. Afterwards, query their new `ash::vk::DeviceMemory`
+    /// and offset with `Allocator::get_allocation_info`, and recreate the resources bound to them.
+    /// Only `ash::vk::MemoryPropertyFlags::HOST_VISIBLE` memory can be defragmented on the CPU.
+    pub unsafe fn try_defragment_step(
+        &self,
+        pool: AllocatorPool,
+        max_bytes_to_move: vk::DeviceSize,
+        max_allocations_to_move: u32,
+    ) -> VkResult<DefragmentationStats> {
+        let pools = [pool];
+        let info = DefragmentationInfo2 {
+            allocations: &[],
+            pools: Some(&pools),
+            max_cpu_bytes_to_move: max_bytes_to_move,
+            max_cpu_allocations_to_move: max_allocations_to_move,
+            max_gpu_bytes_to_move: 0,
+            max_gpu_allocations_to_move: 0,
+            command_buffer: None,
+        };
+        let mut context = self.defragmentation_begin(&info)?;
+        let (stats, _) = self.defragmentation_end(&mut context)?;
+        Ok(stats)
     }
 
     /// Compacts memory by moving allocations.
@@ -1558,18 +4430,18 @@ impl Allocator {
         let mut ffi_change_list: Vec<vk::Bool32> = vec![0; allocations.len()];
         let ffi_info = match defrag_info {
             Some(info) => ffi::VmaDefragmentationInfo {
-                maxBytesToMove: info.max_bytes_to_move as vk::DeviceSize,
+                maxBytesToMove: host_size_to_device(info.max_bytes_to_move),
                 maxAllocationsToMove: info.max_allocations_to_move,
             },
             None => ffi::VmaDefragmentationInfo {
                 maxBytesToMove: ash::vk::WHOLE_SIZE,
-                maxAllocationsToMove: std::u32::MAX,
+                maxAllocationsToMove: u32::MAX,
             },
         };
 
         let mut ffi_stats: ffi::VmaDefragmentationStats = mem::zeroed();
         ffi_to_result(ffi::vmaDefragment(
-            self.0,
+            self.handle(),
             allocations.as_ptr() as *mut _,
             allocations.len(),
             ffi_change_list.as_mut_ptr(),
@@ -1581,13 +4453,8 @@ impl Allocator {
             .iter()
             .map(|change| *change == ash::vk::TRUE)
             .collect();
-
-        let stats = DefragmentationStats {
-            bytes_moved: ffi_stats.bytesMoved as usize,
-            bytes_freed: ffi_stats.bytesFreed as usize,
-            allocations_moved: ffi_stats.allocationsMoved,
-            device_memory_blocks_freed: ffi_stats.deviceMemoryBlocksFreed,
-        };
+        let stats = DefragmentationStats::from(&ffi_stats);
+        trace::defragmented(&stats);
 
         Ok((stats, change_list))
     }
@@ -1610,7 +4477,65 @@ impl Allocator {
         buffer: ash::vk::Buffer,
         allocation: Allocation,
     ) -> VkResult<()> {
-        ffi_to_result(ffi::vmaBindBufferMemory(self.0, allocation.0, buffer))
+        ffi_to_result(ffi::vmaBindBufferMemory(
+            self.handle(),
+            allocation.0,
+            buffer,
+        ))?;
+        self.allocation_states
+            .update_existing(allocation, |state| state.unbound = false);
+        Ok(())
+    }
+
+    /// Same as `Allocator::bind_buffer_memory`, but first checks the queue families of
+    /// `buffer_info`, the create info `buffer` was created with, for mistakes that commonly
+    /// surface later as validation errors in multi-queue engines.
+    ///
+    /// If the sharing mode is `ash::vk::SharingMode::CONCURRENT`, returns
+    /// `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` without binding if:
+    /// - fewer than two distinct queue families are listed, or a queue family is listed twice,
+    /// - a listed queue family doesn't exist on the physical device.
+    ///
+    /// Vulkan ignores the queue families of `ash::vk::SharingMode::EXCLUSIVE` buffers, so they
+    /// are not checked, nor read.
+    pub unsafe fn bind_buffer_memory_checked(
+        &self,
+        buffer: vk::Buffer,
+        allocation: Allocation,
+        buffer_info: &vk::BufferCreateInfo,
+    ) -> VkResult<()> {
+        if buffer_info.sharing_mode == vk::SharingMode::CONCURRENT {
+            let queue_family_indices = if buffer_info.queue_family_index_count == 0 {
+                &[]
+            } else {
+                std::slice::from_raw_parts(
+                    buffer_info.p_queue_family_indices,
+                    buffer_info.queue_family_index_count as usize,
+                )
+            };
+            self.validate_concurrent_sharing(queue_family_indices)?;
+        }
+        self.bind_buffer_memory(buffer, allocation)
+    }
+
+    /// Checks the `queue_family_indices` of a resource with concurrent sharing, see
+    /// `Allocator::bind_buffer_memory_checked`.
+    unsafe fn validate_concurrent_sharing(&self, queue_family_indices: &[u32]) -> VkResult<()> {
+        let queue_family_count = self
+            .instance
+            .get_physical_device_queue_family_properties(self.physical_device)
+            .len() as u32;
+        let mut unique = queue_family_indices.to_vec();
+        unique.sort_unstable();
+        unique.dedup();
+
+        if unique.len() < 2
+            || unique.len() != queue_family_indices.len()
+            || unique.iter().any(|&index| index >= queue_family_count)
+        {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+        }
+        Ok(())
     }
 
     /// Binds image to allocation.
@@ -1631,7 +4556,36 @@ impl Allocator {
         image: ash::vk::Image,
         allocation: Allocation,
     ) -> VkResult<()> {
-        ffi_to_result(ffi::vmaBindImageMemory(self.0, allocation.0, image))
+        ffi_to_result(ffi::vmaBindImageMemory(self.handle(), allocation.0, image))?;
+        self.allocation_states
+            .update_existing(allocation, |state| state.unbound = false);
+        Ok(())
+    }
+
+    /// Returns `false` if `allocation` was made for a buffer or image that has not been bound to
+    /// it through this wrapper yet.
+    ///
+    /// Only allocations made for a resource are tracked, i.e. with
+    /// `AllocationCreateFlags::CREATE_DONT_BIND` or by `Allocator::allocate_memory_for_buffer`,
+    /// `Allocator::allocate_memory_for_buffer_info` or `Allocator::allocate_memory_for_image`.
+    /// They count as bound once passed to `Allocator::bind_buffer_memory` or
+    /// `Allocator::bind_image_memory`, and freeing them before is asserted against in debug
+    /// builds, after the memory has been freed. Any other allocation, including one bound with
+    /// `ash::Device` directly, is reported as bound.
+    pub fn is_bound(&self, allocation: Allocation) -> bool {
+        !self.allocation_states.get(allocation).unbound
+    }
+
+    /// Tracks an allocation made by `Allocator::create_buffer` or `Allocator::create_image` if
+    /// `AllocationCreateFlags::CREATE_DONT_BIND` left it unbound.
+    fn track_unbound(&self, allocation_info: &AllocationCreateInfo, allocation: Allocation) {
+        if allocation_info
+            .flags
+            .contains(AllocationCreateFlags::CREATE_DONT_BIND)
+        {
+            self.allocation_states
+                .update(allocation, |state| state.unbound = true);
+        }
     }
 
     /// This function automatically creates a buffer, allocates appropriate memory
@@ -1651,21 +4605,292 @@ impl Allocator {
         &self,
         buffer_info: &ash::vk::BufferCreateInfo,
         allocation_info: &AllocationCreateInfo,
-    ) -> VkResult<(ash::vk::Buffer, Allocation, AllocationInfo)> {
-        let allocation_create_info = allocation_create_info_to_ffi(&allocation_info);
+    ) -> Result<(ash::vk::Buffer, Allocation, AllocationInfo), Error> {
         let mut buffer = vk::Buffer::null();
         let mut allocation: Allocation = mem::zeroed();
-        let mut allocation_info: AllocationInfo = mem::zeroed();
-        ffi_to_result(ffi::vmaCreateBuffer(
-            self.0,
-            &*buffer_info,
-            &allocation_create_info,
-            &mut buffer,
-            &mut allocation.0,
-            &mut allocation_info.0,
-        ))?;
+        let mut info: AllocationInfo = mem::zeroed();
+        let result = self.allocate_with_dedicated_preference(
+            allocation_info,
+            || self.buffer_info_dedicated_requirements(buffer_info),
+            |create_info| {
+                ffi_to_result(ffi::vmaCreateBuffer(
+                    self.handle(),
+                    &*buffer_info,
+                    create_info,
+                    &mut buffer,
+                    &mut allocation.0,
+                    &mut info.0,
+                ))
+            },
+        );
+        self.check_allocation(result, allocation_info, || {
+            self.buffer_info_memory_requirements(buffer_info)
+                .unwrap_or_default()
+        })?;
+        if let Err(err) = self.finish_allocation(allocation_info, allocation, &info) {
+            self.destroy_buffer(buffer, allocation);
+            return Err(err.into());
+        }
+        self.track_unbound(allocation_info, allocation);
+
+        Ok((buffer, allocation, info))
+    }
+
+    /// Same as `Allocator::create_buffer`, but chains `allocate_next` to the
+    /// `ash::vk::MemoryAllocateInfo` of the allocation, e.g. an
+    /// `ash::vk::DedicatedAllocationMemoryAllocateInfoNV` or an `ash::vk::MemoryAllocateFlagsInfo`.
+    ///
+    /// VMA only takes such a chain per custom pool, so the buffer gets a pool of its own, with a
+    /// single block just large enough for it, in the memory type chosen for `buffer_info` and
+    /// `allocation_info`. The pool is destroyed when the allocation is freed, with
+    /// `Allocator::destroy_buffer` or `Allocator::free_memory`. `allocation_info.pool` is
+    /// replaced. The chain must stay valid until this returns.
+    pub unsafe fn create_buffer_with_allocate_next(
+        &self,
+        buffer_info: &ash::vk::BufferCreateInfo,
+        allocation_info: &AllocationCreateInfo,
+        allocate_next: *mut ::std::os::raw::c_void,
+    ) -> Result<(ash::vk::Buffer, Allocation, AllocationInfo), Error> {
+        let memory_requirements = self.buffer_info_memory_requirements(buffer_info)?;
+        let memory_type_index =
+            self.find_memory_type_index_for_buffer_info(buffer_info, allocation_info)?;
+        self.allocate_chained(
+            memory_type_index,
+            &memory_requirements,
+            allocation_info,
+            allocate_next,
+            |allocation_info| self.create_buffer(buffer_info, allocation_info),
+        )
+    }
 
-        Ok((buffer, allocation, allocation_info))
+    /// Same as `Allocator::create_buffer`, but retries while it fails with
+    /// `ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY`, for up to `retry`.
+    ///
+    /// Between attempts, the calling thread sleeps for a millisecond, giving other threads the
+    /// chance to free memory, e.g. as frames in flight complete. Once `retry` has elapsed, the
+    /// last error is returned. Other errors are returned right away. As the thread is blocked,
+    /// don't call this from a thread that is responsible for freeing memory.
+    pub unsafe fn allocate_buffer_blocking(
+        &self,
+        buffer_info: &ash::vk::BufferCreateInfo,
+        allocation_info: &AllocationCreateInfo,
+        retry: std::time::Duration,
+    ) -> Result<(ash::vk::Buffer, Allocation, AllocationInfo), Error> {
+        const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1);
+        let deadline = std::time::Instant::now() + retry;
+        loop {
+            match self.create_buffer(buffer_info, allocation_info) {
+                Err(Error::Vulkan(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY))
+                    if std::time::Instant::now() < deadline =>
+                {
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Same as `Allocator::create_buffer`, for an exclusive buffer of `size` bytes with `usage`,
+    /// allocated with `AllocationCreateInfo::for_usage(memory_usage)`.
+    ///
+    /// This covers the common case; use `Allocator::create_buffer` for sharing between queue
+    /// families, buffer create flags or other allocation parameters.
+    pub unsafe fn create_buffer_simple(
+        &self,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        memory_usage: MemoryUsage,
+    ) -> Result<(ash::vk::Buffer, Allocation, AllocationInfo), Error> {
+        let buffer_info = vk::BufferCreateInfo::builder()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        self.create_buffer(&buffer_info, &AllocationCreateInfo::for_usage(memory_usage))
+    }
+
+    /// Creates a `MemoryUsage::GpuOnly` buffer with `usage` holding a copy of `data`, uploaded
+    /// through a staging buffer.
+    ///
+    /// The data is written to a new `MemoryUsage::CpuOnly` staging buffer, and a copy from it
+    /// to the new buffer, which gets `ash::vk::BufferUsageFlags::TRANSFER_DST` added to `usage`,
+    /// is recorded into `command_buffer`. Nothing is submitted. The caller must:
+    ///
+    /// - submit `command_buffer`, which must be in the recording state and outside of a render
+    ///   pass instance, before using the buffer on the device;
+    /// - make uses of the buffer wait for the copy, e.g. with a pipeline barrier from
+    ///   `ash::vk::PipelineStageFlags::TRANSFER` with `ash::vk::AccessFlags::TRANSFER_WRITE`;
+    /// - keep the returned `StagingResources` alive until `command_buffer` has finished
+    ///   executing, then destroy them with `Allocator::destroy_buffer`.
+    ///
+    /// Returns `Error::InvalidSize` if `data` is empty.
+    pub unsafe fn create_buffer_with_data(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        usage: vk::BufferUsageFlags,
+        data: &[u8],
+    ) -> Result<(vk::Buffer, Allocation, StagingResources), Error> {
+        if data.is_empty() {
+            return Err(Error::InvalidSize);
+        }
+        let size = data.len() as vk::DeviceSize;
+
+        let (staging_buffer, staging_allocation, staging_info) = self.create_buffer_simple(
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            MemoryUsage::CpuOnly,
+        )?;
+        let staging = StagingResources {
+            buffer: staging_buffer,
+            allocation: staging_allocation,
+        };
+        std::ptr::copy_nonoverlapping(data.as_ptr(), staging_info.mapped_data(), data.len());
+        let flushed = if staging_info.is_host_coherent(self) {
+            Ok(())
+        } else {
+            self.flush_allocation(staging.allocation, 0, data.len())
+        };
+        let uploaded = flushed.and_then(|()| {
+            self.create_buffer_simple(
+                size,
+                usage | vk::BufferUsageFlags::TRANSFER_DST,
+                MemoryUsage::GpuOnly,
+            )
+        });
+        let (buffer, allocation, _) = match uploaded {
+            Ok(created) => created,
+            Err(err) => {
+                self.destroy_buffer(staging.buffer, staging.allocation);
+                return Err(err);
+            }
+        };
+
+        let region = vk::BufferCopy {
+            src_offset: 0,
+            dst_offset: 0,
+            size,
+        };
+        self.device
+            .cmd_copy_buffer(command_buffer, staging.buffer, buffer, &[region]);
+
+        Ok((buffer, allocation, staging))
+    }
+
+    /// Same as `Allocator::create_buffer` with `AllocationCreateInfo::zero_initialize`, but also
+    /// clears buffers placed in memory that is not `ash::vk::MemoryPropertyFlags::HOST_VISIBLE`.
+    ///
+    /// For such buffers, a `vkCmdFillBuffer` writing zeros over the whole buffer is recorded into
+    /// `command_buffer`, which must be in the recording state and outside of a render pass instance.
+    /// `buffer_info.usage` must then contain `ash::vk::BufferUsageFlags::TRANSFER_DST`, and uses
+    /// of the buffer must wait for the transfer stage. Host-visible buffers are cleared on the
+    /// host before returning, and nothing is recorded.
+    pub unsafe fn create_buffer_zeroed(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        buffer_info: &ash::vk::BufferCreateInfo,
+        allocation_info: &AllocationCreateInfo,
+    ) -> Result<(ash::vk::Buffer, Allocation, AllocationInfo), Error> {
+        let allocation_info = AllocationCreateInfo {
+            zero_initialize: true,
+            ..allocation_info.clone()
+        };
+        let (buffer, allocation, info) = self.create_buffer(buffer_info, &allocation_info)?;
+        if !info.is_host_visible(self) {
+            self.device
+                .cmd_fill_buffer(command_buffer, buffer, 0, vk::WHOLE_SIZE, 0);
+        }
+
+        Ok((buffer, allocation, info))
+    }
+
+    /// Creates a buffer for each of `buffer_infos`, all bound to a single allocation.
+    ///
+    /// Each buffer is placed at the next offset satisfying its alignment, and bound there with
+    /// `vmaBindBufferMemory2`. This saves an allocation per buffer for many small buffers,
+    /// e.g. uniform buffers. Returns the allocation and, for each entry of `buffer_infos`, the
+    /// buffer and its offset within the allocation.
+    ///
+    /// Fails with `Error::NoCompatibleMemoryType` if no memory type is supported by all the
+    /// buffers.
+    /// Destroy the buffers with `ash::Device::destroy_buffer` and free the allocation with
+    /// `Allocator::free_memory` once all of them are no longer used.
+    pub unsafe fn create_packed_buffers(
+        &self,
+        buffer_infos: &[vk::BufferCreateInfo],
+        allocation_info: &AllocationCreateInfo,
+    ) -> Result<(Allocation, Vec<(vk::Buffer, vk::DeviceSize)>), Error> {
+        let mut buffers = Vec::with_capacity(buffer_infos.len());
+        let destroy_buffers = |buffers: &[(vk::Buffer, vk::DeviceSize)]| {
+            for &(buffer, _) in buffers {
+                self.device
+                    .destroy_buffer(buffer, self.allocation_callbacks.as_deref());
+            }
+        };
+
+        let mut memory_requirements = vk::MemoryRequirements {
+            size: 0,
+            alignment: 1,
+            memory_type_bits: !0,
+        };
+        for buffer_info in buffer_infos {
+            let buffer = match self
+                .device
+                .create_buffer(buffer_info, self.allocation_callbacks.as_deref())
+            {
+                Ok(buffer) => buffer,
+                Err(err) => {
+                    destroy_buffers(&buffers);
+                    return Err(err.into());
+                }
+            };
+            let requirements = self.device.get_buffer_memory_requirements(buffer);
+            let offset = memory_requirements
+                .size
+                .next_multiple_of(requirements.alignment);
+            memory_requirements.size = offset + requirements.size;
+            memory_requirements.alignment =
+                memory_requirements.alignment.max(requirements.alignment);
+            memory_requirements.memory_type_bits &= requirements.memory_type_bits;
+            buffers.push((buffer, offset));
+        }
+        if memory_requirements.memory_type_bits == 0 {
+            destroy_buffers(&buffers);
+            return Err(Error::NoCompatibleMemoryType);
+        }
+
+        let (allocation, _) = match self.allocate_memory(&memory_requirements, allocation_info) {
+            Ok(allocation) => allocation,
+            Err(err) => {
+                destroy_buffers(&buffers);
+                return Err(err);
+            }
+        };
+        for &(buffer, offset) in &buffers {
+            let result = ffi_to_result(ffi::vmaBindBufferMemory2(
+                self.handle(),
+                allocation.0,
+                offset,
+                buffer,
+                std::ptr::null(),
+            ));
+            if let Err(err) = result {
+                destroy_buffers(&buffers);
+                self.free_memory(allocation);
+                return Err(err.into());
+            }
+        }
+        Ok((allocation, buffers))
+    }
+
+    /// Same as `Allocator::create_buffer`, but returns a `Buffer` that owns the created buffer
+    /// and its allocation, destroying both when dropped.
+    pub unsafe fn create_owned_buffer(
+        &self,
+        buffer_info: &ash::vk::BufferCreateInfo,
+        allocation_info: &AllocationCreateInfo,
+    ) -> Result<Buffer<'_>, Error> {
+        let (buffer, allocation, allocation_info) =
+            self.create_buffer(buffer_info, allocation_info)?;
+        Ok(Buffer::new(self, buffer, allocation, allocation_info))
     }
 
     /// Destroys Vulkan buffer and frees allocated memory.
@@ -1679,7 +4904,178 @@ impl Allocator {
     ///
     /// It it safe to pass null as `buffer` and/or `allocation`.
     pub unsafe fn destroy_buffer(&self, buffer: ash::vk::Buffer, allocation: Allocation) {
-        ffi::vmaDestroyBuffer(self.0, buffer, allocation.0);
+        let state = self.release_allocation(allocation);
+        ffi::vmaDestroyBuffer(self.handle(), buffer, allocation.0);
+        self.finish_free(allocation, state);
+    }
+
+    /// Moves `buffer` and its `allocation` into a new buffer allocated from `target_pool`.
+    ///
+    /// Vulkan does not allow binding a resource to different memory once it has been bound,
+    /// so the migration creates a new buffer from `buffer_info`, which must be the create info
+    /// `buffer` was created with, allocates it from `target_pool`, and copies `buffer_info.size`
+    /// bytes from `buffer` into the new buffer on the device. The copy is recorded into
+    /// `command_buffer`, submitted to `queue`, and waited for, after which `buffer` and
+    /// `allocation` are destroyed and the new buffer, allocation and allocation info are returned.
+    ///
+    /// Requirements:
+    ///
+    /// - `command_buffer` must be in the initial state and `queue` must support transfer
+    ///   operations. The command buffer is left in the executable state, or the invalid state if
+    ///   it was allocated from a pool with `ash::vk::CommandPoolCreateFlags::TRANSIENT`.
+    /// - `buffer_info.usage` must contain `ash::vk::BufferUsageFlags::TRANSFER_SRC` and
+    ///   `ash::vk::BufferUsageFlags::TRANSFER_DST`.
+    /// - All device accesses to `buffer` must have completed before this call.
+    ///
+    /// On error, the new buffer is destroyed and `buffer` and `allocation` are left untouched.
+    ///
+    /// Images are not supported, as copying them requires knowledge of their layouts and subresources.
+    pub unsafe fn migrate_allocation(
+        &self,
+        queue: vk::Queue,
+        command_buffer: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        allocation: Allocation,
+        buffer_info: &vk::BufferCreateInfo,
+        target_pool: AllocatorPool,
+    ) -> Result<(vk::Buffer, Allocation, AllocationInfo), Error> {
+        let allocation_info = AllocationCreateInfo {
+            pool: Some(target_pool),
+            ..Default::default()
+        };
+        let (new_buffer, new_allocation, info) =
+            self.create_buffer(buffer_info, &allocation_info)?;
+
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        let copied = self
+            .device
+            .begin_command_buffer(command_buffer, &begin_info)
+            .and_then(|()| {
+                let region = vk::BufferCopy {
+                    src_offset: 0,
+                    dst_offset: 0,
+                    size: buffer_info.size,
+                };
+                self.device
+                    .cmd_copy_buffer(command_buffer, buffer, new_buffer, &[region]);
+                self.device.end_command_buffer(command_buffer)
+            })
+            .and_then(|()| self.submit_and_wait(queue, command_buffer));
+        if let Err(err) = copied {
+            self.destroy_buffer(new_buffer, new_allocation);
+            return Err(err.into());
+        }
+
+        self.destroy_buffer(buffer, allocation);
+        Ok((new_buffer, new_allocation, info))
+    }
+
+    /// Submits `command_buffer` to `queue` and waits for it to finish executing.
+    unsafe fn submit_and_wait(
+        &self,
+        queue: vk::Queue,
+        command_buffer: vk::CommandBuffer,
+    ) -> VkResult<()> {
+        let fence = self.device.create_fence(
+            &vk::FenceCreateInfo::default(),
+            self.allocation_callbacks.as_deref(),
+        )?;
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::builder().command_buffers(&command_buffers);
+        let result = self
+            .device
+            .queue_submit(queue, &[submit_info.build()], fence)
+            .and_then(|()| self.device.wait_for_fences(&[fence], true, u64::MAX));
+        self.device
+            .destroy_fence(fence, self.allocation_callbacks.as_deref());
+        result
+    }
+
+    /// Moves the contents of `buffer` into a new buffer allocated with `priority`.
+    ///
+    /// VMA cannot change the priority of an existing allocation, so a new buffer is created from
+    /// `buffer_info`, which must be the create info `buffer` was created with, and
+    /// `allocation_info` with `AllocationCreateInfo::priority` replaced by `priority`, and a copy
+    /// of `buffer_info.size` bytes from `buffer` is recorded in `command_buffer`.
+    ///
+    /// Requirements:
+    ///
+    /// - `command_buffer` must be in the recording state and outside of a render pass instance,
+    ///   on a queue supporting transfer operations.
+    /// - `buffer_info.usage` must contain `ash::vk::BufferUsageFlags::TRANSFER_SRC` and
+    ///   `ash::vk::BufferUsageFlags::TRANSFER_DST`.
+    /// - Prior device writes to `buffer` must be made available to the transfer stage, and uses
+    ///   of the returned buffer must wait for the transfer stage, e.g. with pipeline barriers
+    ///   recorded around this call.
+    ///
+    /// `buffer` and its allocation are left untouched. Destroy them with `Allocator::destroy_buffer`
+    /// once `command_buffer` has finished executing.
+    ///
+    /// The priority only has an effect if the allocator was created with
+    /// `AllocatorCreateFlags::EXT_MEMORY_PRIORITY` and the new allocation ends up dedicated, e.g.
+    /// with `AllocationCreateFlags::DEDICATED_MEMORY` or `AllocationCreateInfo::prefer_dedicated`.
+    /// Allocations from a custom pool use the priority of the pool instead.
+    pub unsafe fn reallocate_with_priority(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        buffer_info: &vk::BufferCreateInfo,
+        allocation_info: &AllocationCreateInfo,
+        priority: f32,
+    ) -> Result<(vk::Buffer, Allocation, AllocationInfo), Error> {
+        let allocation_info = AllocationCreateInfo {
+            priority,
+            ..allocation_info.clone()
+        };
+        let (new_buffer, allocation, info) = self.create_buffer(buffer_info, &allocation_info)?;
+
+        let region = vk::BufferCopy {
+            src_offset: 0,
+            dst_offset: 0,
+            size: buffer_info.size,
+        };
+        self.device
+            .cmd_copy_buffer(command_buffer, buffer, new_buffer, &[region]);
+
+        Ok((new_buffer, allocation, info))
+    }
+
+    /// Records a copy of the first `size` bytes of `src` to the start of `dst` into
+    /// `command_buffer`.
+    ///
+    /// `src_buffer` and `dst_buffer` must be bound at offset 0 of the `ash::vk::DeviceMemory`
+    /// blocks of `src` and `dst` respectively, e.g. buffers spanning whole blocks as used for
+    /// moving allocations during defragmentation. The copy region is placed at
+    /// `AllocationInfo::device_offset` of each allocation, so that callers don't have to track
+    /// those offsets themselves. The buffers need `ash::vk::BufferUsageFlags::TRANSFER_SRC` and
+    /// `ash::vk::BufferUsageFlags::TRANSFER_DST` respectively.
+    ///
+    /// Returns `Error::InvalidSize` without recording anything if `size` exceeds either
+    /// allocation.
+    pub unsafe fn cmd_copy_allocation(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        src: Allocation,
+        src_buffer: vk::Buffer,
+        dst: Allocation,
+        dst_buffer: vk::Buffer,
+        size: vk::DeviceSize,
+    ) -> Result<(), Error> {
+        let src_info = self.get_allocation_info(src)?;
+        let dst_info = self.get_allocation_info(dst)?;
+        if size > src_info.device_size() || size > dst_info.device_size() {
+            return Err(Error::InvalidSize);
+        }
+
+        let region = vk::BufferCopy {
+            src_offset: src_info.device_offset(),
+            dst_offset: dst_info.device_offset(),
+            size,
+        };
+        self.device
+            .cmd_copy_buffer(command_buffer, src_buffer, dst_buffer, &[region]);
+        Ok(())
     }
 
     /// This function automatically creates an image, allocates appropriate memory
@@ -1696,28 +5092,132 @@ impl Allocator {
     /// and `AllocationCreateFlags::NEVER_ALLOCATE` is not used), it creates dedicated
     /// allocation for this image, just like when using `AllocationCreateFlags::DEDICATED_MEMORY`.
     ///
-    /// If `VK_ERROR_VALIDAITON_FAILED_EXT` is returned, VMA may have encountered a problem
-    /// that is not caught by the validation layers. One example is if you try to create a 0x0
-    /// image, a panic will occur and `VK_ERROR_VALIDAITON_FAILED_EXT` is thrown.
+    /// Images with a zero width, height, depth, mip level count or array layer count are rejected
+    /// with `Error::InvalidImageExtent` before calling into VMA, which would otherwise hit an
+    /// assertion. If `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` is returned, VMA may have
+    /// encountered a problem that is not caught by the validation layers.
     pub unsafe fn create_image(
         &self,
         image_info: &ash::vk::ImageCreateInfo,
         allocation_info: &AllocationCreateInfo,
-    ) -> VkResult<(ash::vk::Image, Allocation, AllocationInfo)> {
-        let allocation_create_info = allocation_create_info_to_ffi(&allocation_info);
+    ) -> Result<(ash::vk::Image, Allocation, AllocationInfo), Error> {
+        validate_image_info(image_info)?;
         let mut image = vk::Image::null();
         let mut allocation: Allocation = mem::zeroed();
-        let mut allocation_info: AllocationInfo = mem::zeroed();
-        ffi_to_result(ffi::vmaCreateImage(
-            self.0,
-            &*image_info,
-            &allocation_create_info,
-            &mut image,
-            &mut allocation.0,
-            &mut allocation_info.0,
-        ))?;
+        let mut info: AllocationInfo = mem::zeroed();
+        let result = self.allocate_with_dedicated_preference(
+            allocation_info,
+            || self.image_info_dedicated_requirements(image_info),
+            |create_info| {
+                ffi_to_result(ffi::vmaCreateImage(
+                    self.handle(),
+                    &*image_info,
+                    create_info,
+                    &mut image,
+                    &mut allocation.0,
+                    &mut info.0,
+                ))
+            },
+        );
+        self.check_allocation(result, allocation_info, || {
+            self.image_info_memory_requirements(image_info)
+                .unwrap_or_default()
+        })?;
+        if let Err(err) = self.finish_allocation(allocation_info, allocation, &info) {
+            self.destroy_image(image, allocation);
+            return Err(err.into());
+        }
+        self.track_unbound(allocation_info, allocation);
+
+        Ok((image, allocation, info))
+    }
+
+    /// Same as `Allocator::create_image`, but chains `allocate_next` to the
+    /// `ash::vk::MemoryAllocateInfo` of the allocation, see
+    /// `Allocator::create_buffer_with_allocate_next`.
+    pub unsafe fn create_image_with_allocate_next(
+        &self,
+        image_info: &ash::vk::ImageCreateInfo,
+        allocation_info: &AllocationCreateInfo,
+        allocate_next: *mut ::std::os::raw::c_void,
+    ) -> Result<(ash::vk::Image, Allocation, AllocationInfo), Error> {
+        validate_image_info(image_info)?;
+        let memory_requirements = self.image_info_memory_requirements(image_info)?;
+        let memory_type_index =
+            self.find_memory_type_index_for_image_info(*image_info, allocation_info)?;
+        self.allocate_chained(
+            memory_type_index,
+            &memory_requirements,
+            allocation_info,
+            allocate_next,
+            |allocation_info| self.create_image(image_info, allocation_info),
+        )
+    }
+
+    /// Creates an image and honors the driver's dedicated allocation hints for it alone, without
+    /// `AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION` being used allocator-wide.
+    ///
+    /// The hints are queried with `vkGetImageMemoryRequirements2` and
+    /// `ash::vk::MemoryDedicatedRequirements`, which requires `vulkan_api_version` 1.1 or
+    /// `AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION`; otherwise
+    /// `ash::vk::Result::ERROR_EXTENSION_NOT_PRESENT` is returned. If the driver requires
+    /// a dedicated allocation, `AllocationCreateFlags::DEDICATED_MEMORY` is added to
+    /// `allocation_info`; if it only prefers one, `AllocationCreateInfo::prefer_dedicated` is set.
+    /// The image is then bound to the new allocation.
+    ///
+    /// Without the allocator-wide flag, VMA gives the image its own `ash::vk::DeviceMemory` block
+    /// but does not chain `VkMemoryDedicatedAllocateInfo` to it. Resources that must be allocated
+    /// with that structure, e.g. some external memory images, still need the allocator flag.
+    pub unsafe fn create_image_dedicated(
+        &self,
+        image_info: &ash::vk::ImageCreateInfo,
+        allocation_info: &AllocationCreateInfo,
+    ) -> Result<(ash::vk::Image, Allocation, AllocationInfo), Error> {
+        validate_image_info(image_info)?;
+        if self.memory_requirements2.is_none() {
+            return Err(Error::Vulkan(vk::Result::ERROR_EXTENSION_NOT_PRESENT));
+        }
+        let image = self
+            .device
+            .create_image(image_info, self.allocation_callbacks.as_deref())?;
+
+        let mut allocation_info = allocation_info.clone();
+        if let Some(dedicated_requirements) = self.image_dedicated_requirements(image) {
+            if dedicated_requirements.requires_dedicated_allocation == vk::TRUE {
+                allocation_info.flags |= AllocationCreateFlags::DEDICATED_MEMORY;
+            } else if dedicated_requirements.prefers_dedicated_allocation == vk::TRUE {
+                allocation_info.prefer_dedicated = true;
+            }
+        }
+
+        let (allocation, info) = match self.allocate_memory_for_image(image, &allocation_info) {
+            Ok(allocation) => allocation,
+            Err(err) => {
+                self.device
+                    .destroy_image(image, self.allocation_callbacks.as_deref());
+                return Err(err);
+            }
+        };
+        if let Err(err) = self.bind_image_memory(image, allocation) {
+            self.allocation_states
+                .update_existing(allocation, |state| state.unbound = false);
+            self.destroy_image(image, allocation);
+            return Err(err.into());
+        }
+
+        Ok((image, allocation, info))
+    }
 
-        Ok((image, allocation, allocation_info))
+    /// Same as `Allocator::create_image`, but returns an `Image` that owns the created image
+    /// and its allocation, destroying both when dropped.
+    pub unsafe fn create_owned_image(
+        &self,
+        image_info: &ash::vk::ImageCreateInfo,
+        allocation_info: &AllocationCreateInfo,
+    ) -> Result<Image<'_>, Error> {
+        let (image, allocation, allocation_info) =
+            self.create_image(image_info, allocation_info)?;
+        Ok(Image::new(self, image, allocation, allocation_info))
     }
 
     /// Destroys Vulkan image and frees allocated memory.
@@ -1731,14 +5231,57 @@ impl Allocator {
     ///
     /// It it safe to pass null as `image` and/or `allocation`.
     pub unsafe fn destroy_image(&self, image: ash::vk::Image, allocation: Allocation) {
-        ffi::vmaDestroyImage(self.0, image, allocation.0);
+        let state = self.release_allocation(allocation);
+        ffi::vmaDestroyImage(self.handle(), image, allocation.0);
+        self.finish_free(allocation, state);
     }
 
     /// Destroys the internal allocator instance. After this has been called,
     /// no other functions may be called. Useful for ensuring a specific destruction
     /// order (for example, if an Allocator is a member of something that owns the Vulkan
     /// instance and destroys it in its own Drop).
+    ///
+    /// In debug builds, calling any other function that reaches VMA afterwards panics instead of
+    /// using the destroyed instance. Destroying the allocator twice panics in debug builds and
+    /// does nothing in release builds.
     pub unsafe fn destroy_allocator(&self) {
-        ffi::vmaDestroyAllocator(self.0);
+        let destroyed = self
+            .destroyed
+            .swap(true, std::sync::atomic::Ordering::AcqRel);
+        debug_assert!(!destroyed, "vk_mem::Allocator destroyed twice");
+        if !destroyed {
+            ffi::vmaDestroyAllocator(self.internal);
+        }
+    }
+
+    /// Raw VMA handle, checking in debug builds that `Allocator::destroy_allocator` was not
+    /// called yet.
+    #[inline]
+    fn handle(&self) -> ffi::VmaAllocator {
+        debug_assert!(
+            !self.destroyed.load(std::sync::atomic::Ordering::Acquire),
+            "vk_mem::Allocator used after destroy_allocator"
+        );
+        self.internal
+    }
+
+    /// Replaces the internal allocator instance with a new one created from `create_info`,
+    /// e.g. against a fresh device after `ash::vk::Result::ERROR_DEVICE_LOST`.
+    ///
+    /// The new instance is created first; if that fails, this allocator is left unchanged.
+    /// Otherwise the old instance is destroyed, so all pools and allocations made from it must
+    /// have been freed before, and the old device must still be alive at this point.
+    /// The callbacks set with `Allocator::set_allocation_failure_callback` and
+    /// `Allocator::set_allocate_callback` are kept.
+    ///
+    /// All `Allocation`, `AllocatorPool` and `DefragmentationContext` handles obtained before
+    /// the call are invalid afterwards.
+    pub unsafe fn recreate(&mut self, create_info: &AllocatorCreateInfo) -> Result<(), Error> {
+        let mut allocator = Allocator::new(create_info)?;
+        allocator.allocation_failure_callback = self.allocation_failure_callback.take();
+        allocator.allocate_callback = self.allocate_callback.take();
+        self.destroy_allocator();
+        *self = allocator;
+        Ok(())
     }
 }
@@ -0,0 +1,381 @@
+use crate::{Allocation, Allocator};
+use ash::prelude::VkResult;
+use ash::vk;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+/// Allocation handle that can be freely sent between threads and mapped on any of them.
+///
+/// The mapping guards returned by `MappableAllocation::map_for_read` and
+/// `MappableAllocation::map_for_write` are neither `Send` nor `Sync` and borrow the
+/// `MappableAllocation`, so the mapped pointer stays on the thread that mapped the memory,
+/// while the handle itself can move to another thread once the guard is dropped.
+///
+/// The wrapper is deliberately not `Clone`, so that a single owner maps the allocation at a
+/// time; `MappableAllocation::map_for_write` borrows it mutably for the same reason. Wrapping
+/// the same `Allocation` more than once bypasses this.
+#[derive(Debug)]
+pub struct MappableAllocation<'a> {
+    allocator: &'a Allocator,
+    allocation: Allocation,
+}
+
+impl<'a> MappableAllocation<'a> {
+    /// Wraps `allocation`, which must have been allocated from `allocator` in host-visible memory.
+    pub fn new(allocator: &'a Allocator, allocation: Allocation) -> Self {
+        MappableAllocation {
+            allocator,
+            allocation,
+        }
+    }
+
+    /// Wrapped allocation.
+    #[inline(always)]
+    pub fn allocation(&self) -> Allocation {
+        self.allocation
+    }
+
+    /// Maps the allocation for reading on the current thread, see `Allocator::map_memory_for_read`.
+    pub unsafe fn map_for_read(&self) -> VkResult<ReadMapping<'_>> {
+        self.allocator.map_memory_for_read(self.allocation)
+    }
+
+    /// Maps the allocation for writing on the current thread, see `Allocator::map_memory_for_write`.
+    pub unsafe fn map_for_write(&mut self) -> VkResult<WriteMapping<'_>> {
+        self.allocator.map_memory_for_write(self.allocation)
+    }
+}
+
+/// Read-only view of a mapped allocation, returned by `Allocator::map_memory_for_read`.
+///
+/// The allocation has been invalidated before the mapping was handed out, so the contents
+/// reflect device writes made visible to the host. The memory is unmapped when the guard is dropped.
+#[derive(Debug)]
+pub struct ReadMapping<'a> {
+    allocator: &'a Allocator,
+    allocation: Allocation,
+    data: *const u8,
+    size: usize,
+}
+
+impl<'a> ReadMapping<'a> {
+    pub(crate) fn new(
+        allocator: &'a Allocator,
+        allocation: Allocation,
+        data: *const u8,
+        size: usize,
+    ) -> Self {
+        ReadMapping {
+            allocator,
+            allocation,
+            data,
+            size,
+        }
+    }
+
+    /// Allocation this mapping belongs to.
+    #[inline(always)]
+    pub fn allocation(&self) -> Allocation {
+        self.allocation
+    }
+}
+
+impl Deref for ReadMapping<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.data, self.size) }
+    }
+}
+
+impl Drop for ReadMapping<'_> {
+    fn drop(&mut self) {
+        unsafe { self.allocator.unmap_memory(self.allocation) };
+    }
+}
+
+/// Writable view of a mapped allocation, returned by `Allocator::map_memory_for_write`.
+///
+/// The whole allocation is flushed and unmapped when the guard is dropped. Errors from the
+/// flush are ignored on drop; call `WriteMapping::flush` to observe them.
+#[derive(Debug)]
+pub struct WriteMapping<'a> {
+    allocator: &'a Allocator,
+    allocation: Allocation,
+    data: *mut u8,
+    size: usize,
+    coherent: bool,
+}
+
+impl<'a> WriteMapping<'a> {
+    pub(crate) fn new(
+        allocator: &'a Allocator,
+        allocation: Allocation,
+        data: *mut u8,
+        size: usize,
+        coherent: bool,
+    ) -> Self {
+        WriteMapping {
+            allocator,
+            allocation,
+            data,
+            size,
+            coherent,
+        }
+    }
+
+    /// Allocation this mapping belongs to.
+    #[inline(always)]
+    pub fn allocation(&self) -> Allocation {
+        self.allocation
+    }
+
+    /// Flushes host writes made so far, making them visible to the device.
+    ///
+    /// Does nothing if the memory is `ash::vk::MemoryPropertyFlags::HOST_COHERENT`.
+    pub fn flush(&self) -> VkResult<()> {
+        if self.coherent {
+            return Ok(());
+        }
+        unsafe {
+            self.allocator
+                .flush_allocation(self.allocation, 0, vk::WHOLE_SIZE as usize)
+                .map_err(vk::Result::from)
+        }
+    }
+}
+
+impl Deref for WriteMapping<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.data, self.size) }
+    }
+}
+
+impl DerefMut for WriteMapping<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.data, self.size) }
+    }
+}
+
+impl Drop for WriteMapping<'_> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+        unsafe { self.allocator.unmap_memory(self.allocation) };
+    }
+}
+
+/// Typed view of a mapped allocation, returned by `Allocator::mapped_as`.
+///
+/// The first `size_of::<T>()` bytes of the allocation are flushed if the memory is not
+/// `ash::vk::MemoryPropertyFlags::HOST_COHERENT`, and the memory is unmapped when the guard is dropped.
+/// Errors from the flush are ignored on drop; call `MappedRef::flush` to observe them.
+#[derive(Debug)]
+pub struct MappedRef<'a, T: Copy> {
+    allocator: &'a Allocator,
+    allocation: Allocation,
+    data: *mut T,
+    coherent: bool,
+    marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T: Copy> MappedRef<'a, T> {
+    pub(crate) fn new(
+        allocator: &'a Allocator,
+        allocation: Allocation,
+        data: *mut T,
+        coherent: bool,
+    ) -> Self {
+        MappedRef {
+            allocator,
+            allocation,
+            data,
+            coherent,
+            marker: PhantomData,
+        }
+    }
+
+    /// Allocation this mapping belongs to.
+    #[inline(always)]
+    pub fn allocation(&self) -> Allocation {
+        self.allocation
+    }
+
+    /// Flushes host writes made so far, making them visible to the device.
+    pub fn flush(&self) -> VkResult<()> {
+        if self.coherent {
+            return Ok(());
+        }
+        unsafe {
+            self.allocator
+                .flush_allocation(self.allocation, 0, std::mem::size_of::<T>())
+                .map_err(vk::Result::from)
+        }
+    }
+}
+
+impl<T: Copy> Deref for MappedRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.data }
+    }
+}
+
+impl<T: Copy> DerefMut for MappedRef<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<T: Copy> Drop for MappedRef<'_, T> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+        unsafe { self.allocator.unmap_memory(self.allocation) };
+    }
+}
+
+/// Long-lived mapping of an allocation, returned by `Allocator::map_persistent`.
+///
+/// Holds one reference of the allocation's map reference count until dropped, so the pointer
+/// stays valid across frames and `PersistentMap::as_slice_mut` costs no FFI call. Dropping it
+/// calls `Allocator::unmap_memory` exactly once. Mappings of the same allocation can overlap,
+/// and dropping one never unmaps memory of an allocation created with
+/// `AllocationCreateFlags::MAPPED`, which keeps its own reference. Unlike
+/// `WriteMapping`, nothing is flushed implicitly, not even on drop: call `PersistentMap::flush`
+/// after writing, e.g. once per frame.
+#[derive(Debug)]
+pub struct PersistentMap<'a> {
+    allocator: &'a Allocator,
+    allocation: Allocation,
+    data: *mut u8,
+    size: usize,
+    coherent: bool,
+}
+
+impl<'a> PersistentMap<'a> {
+    pub(crate) fn new(
+        allocator: &'a Allocator,
+        allocation: Allocation,
+        data: *mut u8,
+        size: usize,
+        coherent: bool,
+    ) -> Self {
+        PersistentMap {
+            allocator,
+            allocation,
+            data,
+            size,
+            coherent,
+        }
+    }
+
+    /// Allocation this mapping belongs to.
+    #[inline(always)]
+    pub fn allocation(&self) -> Allocation {
+        self.allocation
+    }
+
+    /// Mapped memory viewed as a slice of as many `T` as fit in the allocation.
+    ///
+    /// Panics if the mapped pointer is not suitably aligned for `T`.
+    ///
+    /// # Safety
+    ///
+    /// Every bit pattern of the mapped bytes must be a valid `T`, e.g. `T` has no padding,
+    /// references, `bool` or `enum` fields. The memory must not be written while the slice is
+    /// alive, neither through another mapping of the same allocation, such as an overlapping
+    /// `PersistentMap` or `Allocator::map_memory`, nor by the device.
+    pub unsafe fn as_slice<T: Copy>(&self) -> &[T] {
+        std::slice::from_raw_parts(self.typed_data(), self.size / mem::size_of::<T>())
+    }
+
+    /// Mapped memory viewed as a mutable slice of as many `T` as fit in the allocation.
+    ///
+    /// Panics if the mapped pointer is not suitably aligned for `T`.
+    ///
+    /// # Safety
+    ///
+    /// Every bit pattern of the mapped bytes must be a valid `T`, as for
+    /// `PersistentMap::as_slice`. The memory must not be read or written while the slice is
+    /// alive through any other mapping of the same allocation, such as an overlapping
+    /// `PersistentMap` or `Allocator::map_memory`, nor accessed by the device.
+    pub unsafe fn as_slice_mut<T: Copy>(&mut self) -> &mut [T] {
+        std::slice::from_raw_parts_mut(self.typed_data(), self.size / mem::size_of::<T>())
+    }
+
+    /// Flushes host writes made so far, making them visible to the device.
+    ///
+    /// Does nothing if the memory is `ash::vk::MemoryPropertyFlags::HOST_COHERENT`.
+    pub fn flush(&self) -> VkResult<()> {
+        if self.coherent {
+            return Ok(());
+        }
+        unsafe {
+            self.allocator
+                .flush_allocation(self.allocation, 0, vk::WHOLE_SIZE as usize)
+                .map_err(vk::Result::from)
+        }
+    }
+
+    fn typed_data<T>(&self) -> *mut T {
+        assert_ne!(mem::size_of::<T>(), 0, "zero-sized types cannot be mapped");
+        assert_eq!(
+            self.data as usize % mem::align_of::<T>(),
+            0,
+            "mapped pointer is not aligned for the requested type"
+        );
+        self.data as *mut T
+    }
+}
+
+impl Drop for PersistentMap<'_> {
+    fn drop(&mut self) {
+        unsafe { self.allocator.unmap_memory(self.allocation) };
+    }
+}
+
+/// Guard of one mapping of an allocation, returned by `Allocator::map_guarded`.
+///
+/// Dereferences to the mapped pointer, and calls `Allocator::unmap_memory` exactly once when
+/// dropped. Each guard holds its own reference of the allocation's map reference count, so
+/// guards can overlap, and dropping one never unmaps memory of an allocation created with
+/// `AllocationCreateFlags::MAPPED`, which keeps its own reference.
+#[derive(Debug)]
+pub struct MapGuard<'a> {
+    allocator: &'a Allocator,
+    allocation: Allocation,
+    data: *mut u8,
+}
+
+impl<'a> MapGuard<'a> {
+    pub(crate) fn new(allocator: &'a Allocator, allocation: Allocation, data: *mut u8) -> Self {
+        MapGuard {
+            allocator,
+            allocation,
+            data,
+        }
+    }
+
+    /// Allocation this mapping belongs to.
+    #[inline(always)]
+    pub fn allocation(&self) -> Allocation {
+        self.allocation
+    }
+}
+
+impl Deref for MapGuard<'_> {
+    type Target = *mut u8;
+
+    fn deref(&self) -> &*mut u8 {
+        &self.data
+    }
+}
+
+impl Drop for MapGuard<'_> {
+    fn drop(&mut self) {
+        unsafe { self.allocator.unmap_memory(self.allocation) };
+    }
+}
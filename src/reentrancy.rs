@@ -0,0 +1,38 @@
+use std::cell::Cell;
+
+thread_local! {
+    static IN_CALLBACK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the current thread as running a user callback, until dropped.
+///
+/// Entered around the callbacks the wrapper invokes in the middle of an allocating call, such
+/// as `Allocator::set_allocate_callback`. `assert_outside_callback` turns allocating, freeing
+/// or mapping from there into a panic.
+pub(crate) struct CallbackScope {
+    previous: bool,
+}
+
+impl CallbackScope {
+    pub(crate) fn enter() -> Self {
+        CallbackScope {
+            previous: IN_CALLBACK.with(|flag| flag.replace(true)),
+        }
+    }
+}
+
+impl Drop for CallbackScope {
+    fn drop(&mut self) {
+        IN_CALLBACK.with(|flag| flag.set(self.previous));
+    }
+}
+
+/// Panics if the current thread is inside a `CallbackScope`.
+pub(crate) fn assert_outside_callback() {
+    if IN_CALLBACK.with(Cell::get) {
+        panic!(
+            "vk_mem::Allocator used to allocate, free or map memory from inside a callback; \
+             the allocator is in the middle of another call there"
+        );
+    }
+}
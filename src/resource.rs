@@ -0,0 +1,125 @@
+use crate::{Allocation, AllocationInfo, Allocator};
+use ash::vk;
+use std::mem;
+
+/// Buffer created by `Allocator::create_owned_buffer`, owning both the buffer and its memory.
+///
+/// The buffer and its allocation are destroyed together with `Allocator::destroy_buffer`
+/// when this object is dropped.
+#[derive(Debug)]
+pub struct Buffer<'a> {
+    allocator: &'a Allocator,
+    buffer: vk::Buffer,
+    allocation: Allocation,
+    info: AllocationInfo,
+}
+
+impl<'a> Buffer<'a> {
+    pub(crate) fn new(
+        allocator: &'a Allocator,
+        buffer: vk::Buffer,
+        allocation: Allocation,
+        info: AllocationInfo,
+    ) -> Self {
+        Buffer {
+            allocator,
+            buffer,
+            allocation,
+            info,
+        }
+    }
+
+    /// Vulkan buffer handle.
+    #[inline(always)]
+    pub fn handle(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    /// Allocation backing the buffer.
+    #[inline(always)]
+    pub fn allocation(&self) -> Allocation {
+        self.allocation
+    }
+
+    /// Allocation parameters as returned when the buffer was created.
+    ///
+    /// Use `Allocator::get_allocation_info` to get up to date values, e.g. after defragmentation.
+    #[inline(always)]
+    pub fn info(&self) -> &AllocationInfo {
+        &self.info
+    }
+
+    /// Releases ownership, returning the raw parts. They must then be destroyed manually.
+    pub fn into_raw(self) -> (vk::Buffer, Allocation, AllocationInfo) {
+        let parts = (self.buffer, self.allocation, self.info);
+        mem::forget(self);
+        parts
+    }
+}
+
+impl Drop for Buffer<'_> {
+    fn drop(&mut self) {
+        unsafe { self.allocator.destroy_buffer(self.buffer, self.allocation) };
+    }
+}
+
+/// Image created by `Allocator::create_owned_image`, owning both the image and its memory.
+///
+/// The image and its allocation are destroyed together with `Allocator::destroy_image`
+/// when this object is dropped.
+#[derive(Debug)]
+pub struct Image<'a> {
+    allocator: &'a Allocator,
+    image: vk::Image,
+    allocation: Allocation,
+    info: AllocationInfo,
+}
+
+impl<'a> Image<'a> {
+    pub(crate) fn new(
+        allocator: &'a Allocator,
+        image: vk::Image,
+        allocation: Allocation,
+        info: AllocationInfo,
+    ) -> Self {
+        Image {
+            allocator,
+            image,
+            allocation,
+            info,
+        }
+    }
+
+    /// Vulkan image handle.
+    #[inline(always)]
+    pub fn handle(&self) -> vk::Image {
+        self.image
+    }
+
+    /// Allocation backing the image.
+    #[inline(always)]
+    pub fn allocation(&self) -> Allocation {
+        self.allocation
+    }
+
+    /// Allocation parameters as returned when the image was created.
+    ///
+    /// Use `Allocator::get_allocation_info` to get up to date values, e.g. after defragmentation.
+    #[inline(always)]
+    pub fn info(&self) -> &AllocationInfo {
+        &self.info
+    }
+
+    /// Releases ownership, returning the raw parts. They must then be destroyed manually.
+    pub fn into_raw(self) -> (vk::Image, Allocation, AllocationInfo) {
+        let parts = (self.image, self.allocation, self.info);
+        mem::forget(self);
+        parts
+    }
+}
+
+impl Drop for Image<'_> {
+    fn drop(&mut self) {
+        unsafe { self.allocator.destroy_image(self.image, self.allocation) };
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{
+    Allocation, AllocationCreateFlags, AllocationCreateInfo, Allocator, AllocatorPool,
+    AllocatorPoolCreateFlags, AllocatorPoolCreateInfo, Error,
+};
+use ash::prelude::VkResult;
+use ash::vk;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+/// Transient, frame-based ring allocator built on a custom pool with
+/// `AllocatorPoolCreateFlags::LINEAR_ALGORITHM`.
+///
+/// The pool consists of a single block. Every allocation is made with
+/// `AllocationCreateFlags::CAN_BECOME_LOST` and `AllocationCreateFlags::CAN_MAKE_OTHER_LOST`,
+/// so once the end of the block is reached, allocations made more than `frames_in_flight`
+/// frames ago are reclaimed and new allocations wrap around to the beginning of the block.
+///
+/// `RingPool::reset_frame` advances the frame index with `Allocator::set_current_frame_index`.
+/// Because the frame index is global to the allocator, it should not be combined with other
+/// code that drives the frame index of the same allocator.
+///
+/// Allocations from a lost-capable pool cannot be mapped, so this is meant for memory accessed
+/// by the device, e.g. per-frame scratch buffers bound at the returned offsets.
+#[derive(Debug)]
+pub struct RingPool<'a> {
+    allocator: &'a Allocator,
+    pool: AllocatorPool,
+    frames_in_flight: u32,
+    frame_index: u32,
+    device_memory: vk::DeviceMemory,
+    allocations: VecDeque<(u32, Allocation)>,
+}
+
+impl<'a> RingPool<'a> {
+    /// Creates a ring of `size` bytes in memory type `memory_type_index`.
+    ///
+    /// `frames_in_flight` is the number of previous frames whose allocations may still be in use
+    /// by the device; it is passed to the pool as `AllocatorPoolCreateInfo::frame_in_use_count`.
+    pub unsafe fn new(
+        allocator: &'a Allocator,
+        memory_type_index: u32,
+        size: vk::DeviceSize,
+        frames_in_flight: u32,
+    ) -> VkResult<Self> {
+        let pool = allocator.create_pool(&AllocatorPoolCreateInfo {
+            memory_type_index,
+            flags: AllocatorPoolCreateFlags::LINEAR_ALGORITHM,
+            block_size: usize::try_from(size).map_err(|_| Error::SizeOverflow)?,
+            min_block_count: 1,
+            max_block_count: 1,
+            frame_in_use_count: frames_in_flight,
+            ..Default::default()
+        })?;
+
+        Ok(RingPool {
+            allocator,
+            pool,
+            frames_in_flight,
+            frame_index: 0,
+            device_memory: vk::DeviceMemory::null(),
+            allocations: VecDeque::new(),
+        })
+    }
+
+    /// Allocates `size` bytes aligned to `alignment` and returns the offset into `RingPool::device_memory`.
+    ///
+    /// Returns `ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY` if the ring is full of allocations
+    /// that may still be in use.
+    pub unsafe fn alloc(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> VkResult<vk::DeviceSize> {
+        let memory_requirements = vk::MemoryRequirements {
+            size,
+            alignment,
+            memory_type_bits: u32::MAX,
+        };
+        let allocation_info = AllocationCreateInfo {
+            flags: AllocationCreateFlags::CAN_BECOME_LOST
+                | AllocationCreateFlags::CAN_MAKE_OTHER_LOST,
+            pool: Some(self.pool),
+            ..Default::default()
+        };
+
+        let (allocation, info) = self
+            .allocator
+            .allocate_memory(&memory_requirements, &allocation_info)?;
+        self.allocations.push_back((self.frame_index, allocation));
+        self.device_memory = info.device_memory();
+        Ok(info.device_offset())
+    }
+
+    /// Advances to the next frame and releases allocations that are no longer in flight.
+    pub unsafe fn reset_frame(&mut self) -> VkResult<()> {
+        self.frame_index += 1;
+        self.allocator.set_current_frame_index(self.frame_index);
+        self.allocator.make_pool_allocations_lost(self.pool)?;
+
+        // Allocations are never touched, so they become lost exactly `frames_in_flight` frames after creation.
+        while let Some(&(frame_index, allocation)) = self.allocations.front() {
+            if frame_index.saturating_add(self.frames_in_flight) >= self.frame_index {
+                break;
+            }
+            self.allocator.free_memory(allocation);
+            self.allocations.pop_front();
+        }
+
+        Ok(())
+    }
+
+    /// Memory block backing the ring. Null until the first successful `RingPool::alloc`.
+    #[inline(always)]
+    pub fn device_memory(&self) -> vk::DeviceMemory {
+        self.device_memory
+    }
+
+    /// Underlying custom pool.
+    #[inline(always)]
+    pub fn pool(&self) -> AllocatorPool {
+        self.pool
+    }
+}
+
+impl Drop for RingPool<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            for (_, allocation) in self.allocations.drain(..) {
+                self.allocator.free_memory(allocation);
+            }
+            self.allocator.destroy_pool(self.pool);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{Allocation, Allocator};
+use std::sync::Arc;
+
+/// Reference-counted owner of an `Allocation`.
+///
+/// `Allocation` is a plain `Copy` handle, which makes it easy to free the same allocation twice.
+/// `SharedAllocation` can be cloned freely and frees the underlying memory with
+/// `Allocator::free_memory` exactly once, when the last clone is dropped.
+///
+/// Only the memory is freed. Buffers or images bound to the allocation must be destroyed
+/// by the user before the last clone goes away.
+#[derive(Debug, Clone)]
+pub struct SharedAllocation<'a> {
+    inner: Arc<SharedAllocationInner<'a>>,
+}
+
+#[derive(Debug)]
+struct SharedAllocationInner<'a> {
+    allocator: &'a Allocator,
+    allocation: Allocation,
+}
+
+impl Drop for SharedAllocationInner<'_> {
+    fn drop(&mut self) {
+        unsafe { self.allocator.free_memory(self.allocation) };
+    }
+}
+
+impl<'a> SharedAllocation<'a> {
+    /// Takes ownership of `allocation`, which must have been allocated from `allocator`.
+    ///
+    /// The allocation must not be freed by any other means afterwards.
+    pub unsafe fn new(allocator: &'a Allocator, allocation: Allocation) -> Self {
+        SharedAllocation {
+            inner: Arc::new(SharedAllocationInner {
+                allocator,
+                allocation,
+            }),
+        }
+    }
+
+    /// Raw allocation handle. It stays valid for as long as any clone of this object is alive.
+    #[inline(always)]
+    pub fn allocation(&self) -> Allocation {
+        self.inner.allocation
+    }
+
+    /// Allocator the allocation belongs to.
+    #[inline(always)]
+    pub fn allocator(&self) -> &'a Allocator {
+        self.inner.allocator
+    }
+
+    /// Number of live clones referring to this allocation.
+    #[inline(always)]
+    pub fn strong_count(&self) -> usize {
+        Arc::strong_count(&self.inner)
+    }
+}
@@ -0,0 +1,175 @@
+use crate::Allocation;
+use ash::prelude::VkResult;
+use ash::vk;
+
+/// Staging buffer returned by `Allocator::create_buffer_with_data`, holding a copy of the data
+/// until the recorded copy has executed.
+///
+/// Destroy it with `Allocator::destroy_buffer(staging.buffer, staging.allocation)` once the
+/// command buffer the copy was recorded into has finished executing.
+#[derive(Debug, Clone, Copy)]
+pub struct StagingResources {
+    /// Host-visible buffer the data was written to.
+    pub buffer: vk::Buffer,
+
+    /// Allocation backing `buffer`.
+    pub allocation: Allocation,
+}
+
+/// Size of a texel block of an aspect of a format, i.e. the unit `vkCmdCopyBufferToImage` reads.
+#[derive(Debug, Clone, Copy)]
+struct TexelBlock {
+    aspect_mask: vk::ImageAspectFlags,
+    width: u32,
+    height: u32,
+    bytes: vk::DeviceSize,
+}
+
+impl TexelBlock {
+    const fn color(width: u32, height: u32, bytes: vk::DeviceSize) -> Self {
+        TexelBlock {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            width,
+            height,
+            bytes,
+        }
+    }
+
+    const fn depth(bytes: vk::DeviceSize) -> Self {
+        TexelBlock {
+            aspect_mask: vk::ImageAspectFlags::DEPTH,
+            width: 1,
+            height: 1,
+            bytes,
+        }
+    }
+
+    const STENCIL: TexelBlock = TexelBlock {
+        aspect_mask: vk::ImageAspectFlags::STENCIL,
+        width: 1,
+        height: 1,
+        bytes: 1,
+    };
+}
+
+/// Texel blocks of each aspect of `format` as laid out in a buffer, or
+/// `ash::vk::Result::ERROR_FORMAT_NOT_SUPPORTED` for formats not in the core Vulkan 1.0 table,
+/// e.g. multi-planar ones.
+fn texel_blocks(format: vk::Format) -> VkResult<Vec<TexelBlock>> {
+    let color = |width, height, bytes| Ok(vec![TexelBlock::color(width, height, bytes)]);
+    match format.as_raw() {
+        // R4G4_UNORM_PACK8, R8_*
+        1 | 9..=15 => color(1, 1, 1),
+        // 16-bit packed formats, R8G8_*, R16_*
+        2..=8 | 16..=22 | 70..=76 => color(1, 1, 2),
+        // R8G8B8_*, B8G8R8_*
+        23..=36 => color(1, 1, 3),
+        // 8-bit RGBA, 32-bit packed formats, R16G16_*, R32_*, B10G11R11, E5B9G9R9
+        37..=69 | 77..=83 | 98..=100 | 122 | 123 => color(1, 1, 4),
+        // R16G16B16_*
+        84..=90 => color(1, 1, 6),
+        // R16G16B16A16_*, R32G32_*, R64_*
+        91..=97 | 101..=103 | 110..=112 => color(1, 1, 8),
+        // R32G32B32_*
+        104..=106 => color(1, 1, 12),
+        // R32G32B32A32_*, R64G64_*
+        107..=109 | 113..=115 => color(1, 1, 16),
+        // R64G64B64_*
+        116..=118 => color(1, 1, 24),
+        // R64G64B64A64_*
+        119..=121 => color(1, 1, 32),
+        // D16_UNORM
+        124 => Ok(vec![TexelBlock::depth(2)]),
+        // X8_D24_UNORM_PACK32, D32_SFLOAT
+        125 | 126 => Ok(vec![TexelBlock::depth(4)]),
+        // S8_UINT
+        127 => Ok(vec![TexelBlock::STENCIL]),
+        // D16_UNORM_S8_UINT
+        128 => Ok(vec![TexelBlock::depth(2), TexelBlock::STENCIL]),
+        // D24_UNORM_S8_UINT, D32_SFLOAT_S8_UINT
+        129 | 130 => Ok(vec![TexelBlock::depth(4), TexelBlock::STENCIL]),
+        // BC1, BC4, ETC2 RGB and RGBA1, EAC R11
+        131..=134 | 139 | 140 | 147..=150 | 153 | 154 => color(4, 4, 8),
+        // BC2, BC3, BC5, BC6H, BC7, ETC2 RGBA8, EAC R11G11
+        135..=138 | 141..=146 | 151 | 152 | 155 | 156 => color(4, 4, 16),
+        // ASTC, two formats (UNORM and SRGB) per block size
+        157..=184 => {
+            const ASTC_BLOCKS: [(u32, u32); 14] = [
+                (4, 4),
+                (5, 4),
+                (5, 5),
+                (6, 5),
+                (6, 6),
+                (8, 5),
+                (8, 6),
+                (8, 8),
+                (10, 5),
+                (10, 6),
+                (10, 8),
+                (10, 10),
+                (12, 10),
+                (12, 12),
+            ];
+            let (width, height) = ASTC_BLOCKS[(format.as_raw() - 157) as usize / 2];
+            color(width, height, 16)
+        }
+        _ => Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED),
+    }
+}
+
+fn gcd(a: vk::DeviceSize, b: vk::DeviceSize) -> vk::DeviceSize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Lays out all mip levels and array layers of `image_info` in a staging buffer, see
+/// `Allocator::image_staging_size`.
+///
+/// `offset_alignment` is the preferred alignment of region offsets, e.g.
+/// `ash::vk::PhysicalDeviceLimits::optimal_buffer_copy_offset_alignment`.
+pub(crate) fn image_staging_layout(
+    image_info: &vk::ImageCreateInfo,
+    offset_alignment: vk::DeviceSize,
+) -> VkResult<(vk::DeviceSize, Vec<vk::BufferImageCopy>)> {
+    let blocks = texel_blocks(image_info.format)?;
+
+    let mut size: vk::DeviceSize = 0;
+    let mut regions = Vec::with_capacity(image_info.mip_levels as usize * blocks.len());
+    for mip_level in 0..image_info.mip_levels {
+        let extent = vk::Extent3D {
+            width: (image_info.extent.width >> mip_level).max(1),
+            height: (image_info.extent.height >> mip_level).max(1),
+            depth: (image_info.extent.depth >> mip_level).max(1),
+        };
+        for block in &blocks {
+            // Region offsets must be a multiple of 4 and of the texel block size.
+            let alignment = [4, block.bytes, offset_alignment.max(1)]
+                .iter()
+                .fold(1, |lcm, &n| lcm / gcd(lcm, n) * n);
+            let offset = size.next_multiple_of(alignment);
+            let blocks_per_layer = vk::DeviceSize::from(extent.width.div_ceil(block.width))
+                * vk::DeviceSize::from(extent.height.div_ceil(block.height))
+                * vk::DeviceSize::from(extent.depth);
+            size = offset
+                + blocks_per_layer * vk::DeviceSize::from(image_info.array_layers) * block.bytes;
+
+            regions.push(vk::BufferImageCopy {
+                buffer_offset: offset,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: block.aspect_mask,
+                    mip_level,
+                    base_array_layer: 0,
+                    layer_count: image_info.array_layers,
+                },
+                image_offset: vk::Offset3D::default(),
+                image_extent: extent,
+            });
+        }
+    }
+    Ok((size, regions))
+}
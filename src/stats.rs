@@ -0,0 +1,374 @@
+use crate::ffi;
+use ash::vk;
+
+/// Statistics of a memory type, a memory heap or the whole allocator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatInfo {
+    /// Number of `ash::vk::DeviceMemory` blocks allocated.
+    pub block_count: u32,
+
+    /// Number of `Allocation` objects allocated.
+    pub allocation_count: u32,
+
+    /// Number of free ranges of memory between allocations.
+    pub unused_range_count: u32,
+
+    /// Total number of bytes occupied by all allocations.
+    pub used_bytes: vk::DeviceSize,
+
+    /// Total number of bytes occupied by unused ranges.
+    pub unused_bytes: vk::DeviceSize,
+
+    /// Size of the smallest allocation, in bytes.
+    pub allocation_size_min: vk::DeviceSize,
+
+    /// Average size of an allocation, in bytes.
+    pub allocation_size_avg: vk::DeviceSize,
+
+    /// Size of the largest allocation, in bytes.
+    pub allocation_size_max: vk::DeviceSize,
+
+    /// Size of the smallest unused range, in bytes.
+    pub unused_range_size_min: vk::DeviceSize,
+
+    /// Average size of an unused range, in bytes.
+    pub unused_range_size_avg: vk::DeviceSize,
+
+    /// Size of the largest unused range, in bytes.
+    pub unused_range_size_max: vk::DeviceSize,
+}
+
+impl From<&ffi::VmaStatInfo> for StatInfo {
+    fn from(info: &ffi::VmaStatInfo) -> Self {
+        StatInfo {
+            block_count: info.blockCount,
+            allocation_count: info.allocationCount,
+            unused_range_count: info.unusedRangeCount,
+            used_bytes: info.usedBytes,
+            unused_bytes: info.unusedBytes,
+            allocation_size_min: info.allocationSizeMin,
+            allocation_size_avg: info.allocationSizeAvg,
+            allocation_size_max: info.allocationSizeMax,
+            unused_range_size_min: info.unusedRangeSizeMin,
+            unused_range_size_avg: info.unusedRangeSizeAvg,
+            unused_range_size_max: info.unusedRangeSizeMax,
+        }
+    }
+}
+
+impl StatInfo {
+    /// Checks the invariants every `StatInfo` produced by VMA satisfies.
+    fn is_consistent(&self) -> bool {
+        let allocations = if self.allocation_count == 0 {
+            self.used_bytes == 0
+        } else {
+            self.allocation_size_min <= self.allocation_size_avg
+                && self.allocation_size_avg <= self.allocation_size_max
+                && self.allocation_size_max <= self.used_bytes
+        };
+        let unused_ranges = if self.unused_range_count == 0 {
+            self.unused_bytes == 0
+        } else {
+            self.unused_range_size_min <= self.unused_range_size_avg
+                && self.unused_range_size_avg <= self.unused_range_size_max
+                && self.unused_range_size_max <= self.unused_bytes
+        };
+        allocations && unused_ranges
+    }
+}
+
+/// General statistics from the current state of the allocator, see `Allocator::calculate_stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    /// Statistics of each memory type of the physical device, indexed by memory type index.
+    pub memory_type: Vec<StatInfo>,
+
+    /// Statistics of each memory heap of the physical device, indexed by memory heap index.
+    pub memory_heap: Vec<StatInfo>,
+
+    /// Statistics of all memory types and heaps together.
+    pub total: StatInfo,
+}
+
+impl Stats {
+    /// Converts `stats`, keeping only the first `memory_type_count` types and
+    /// `memory_heap_count` heaps, which are the ones VMA fills in.
+    pub(crate) fn new(
+        stats: &ffi::VmaStats,
+        memory_type_count: u32,
+        memory_heap_count: u32,
+    ) -> Self {
+        Stats {
+            memory_type: stats.memoryType[..memory_type_count as usize]
+                .iter()
+                .map(StatInfo::from)
+                .collect(),
+            memory_heap: stats.memoryHeap[..memory_heap_count as usize]
+                .iter()
+                .map(StatInfo::from)
+                .collect(),
+            total: StatInfo::from(&stats.total),
+        }
+    }
+
+    /// Returns `true` if every `StatInfo` is consistent on its own and both the memory types
+    /// and the memory heaps add up to `total`.
+    pub(crate) fn is_consistent(&self) -> bool {
+        let adds_up = |infos: &[StatInfo]| {
+            let sum = |field: fn(&StatInfo) -> u64| infos.iter().map(field).sum::<u64>();
+            sum(|i| i.block_count.into()) == self.total.block_count.into()
+                && sum(|i| i.allocation_count.into()) == self.total.allocation_count.into()
+                && sum(|i| i.used_bytes) == self.total.used_bytes
+                && sum(|i| i.unused_bytes) == self.total.unused_bytes
+        };
+        self.total.is_consistent()
+            && self.memory_type.iter().all(StatInfo::is_consistent)
+            && self.memory_heap.iter().all(StatInfo::is_consistent)
+            && adds_up(&self.memory_type)
+            && adds_up(&self.memory_heap)
+    }
+}
+
+/// Basic statistics of a memory type, a memory heap or the whole allocator, in the layout of
+/// VMA 3's `VmaStatistics`, see `Allocator::calculate_statistics`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Statistics {
+    /// Number of `ash::vk::DeviceMemory` blocks allocated.
+    pub block_count: u32,
+
+    /// Number of `Allocation` objects allocated.
+    pub allocation_count: u32,
+
+    /// Number of bytes allocated in `ash::vk::DeviceMemory` blocks.
+    pub block_bytes: vk::DeviceSize,
+
+    /// Total number of bytes occupied by all allocations, at most `block_bytes`.
+    pub allocation_bytes: vk::DeviceSize,
+}
+
+/// `Statistics` extended with the sizes of allocations and unused ranges, in the layout of
+/// VMA 3's `VmaDetailedStatistics`.
+///
+/// The minimums are `u64::MAX` and the maximums 0 if there are no allocations, or no unused
+/// ranges, respectively.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DetailedStatistics {
+    /// Basic statistics.
+    pub statistics: Statistics,
+
+    /// Number of free ranges of memory between allocations.
+    pub unused_range_count: u32,
+
+    /// Size of the smallest allocation, in bytes.
+    pub allocation_size_min: vk::DeviceSize,
+
+    /// Size of the largest allocation, in bytes.
+    pub allocation_size_max: vk::DeviceSize,
+
+    /// Size of the smallest unused range, in bytes.
+    pub unused_range_size_min: vk::DeviceSize,
+
+    /// Size of the largest unused range, in bytes.
+    pub unused_range_size_max: vk::DeviceSize,
+}
+
+impl From<&StatInfo> for DetailedStatistics {
+    fn from(info: &StatInfo) -> Self {
+        DetailedStatistics {
+            statistics: Statistics {
+                block_count: info.block_count,
+                allocation_count: info.allocation_count,
+                block_bytes: info.used_bytes + info.unused_bytes,
+                allocation_bytes: info.used_bytes,
+            },
+            unused_range_count: info.unused_range_count,
+            allocation_size_min: info.allocation_size_min,
+            allocation_size_max: info.allocation_size_max,
+            unused_range_size_min: info.unused_range_size_min,
+            unused_range_size_max: info.unused_range_size_max,
+        }
+    }
+}
+
+/// Statistics of the whole allocator, in the layout of VMA 3's `VmaTotalStatistics`, see
+/// `Allocator::calculate_statistics`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TotalStatistics {
+    /// Statistics of each memory type of the physical device, indexed by memory type index.
+    pub memory_type: Vec<DetailedStatistics>,
+
+    /// Statistics of each memory heap of the physical device, indexed by memory heap index.
+    pub memory_heap: Vec<DetailedStatistics>,
+
+    /// Statistics of all memory types and heaps together.
+    pub total: DetailedStatistics,
+}
+
+impl From<&Stats> for TotalStatistics {
+    fn from(stats: &Stats) -> Self {
+        TotalStatistics {
+            memory_type: stats.memory_type.iter().map(Into::into).collect(),
+            memory_heap: stats.memory_heap.iter().map(Into::into).collect(),
+            total: (&stats.total).into(),
+        }
+    }
+}
+
+/// Statistics of one or more custom pools, e.g. the pools of a `ThreadLocalArena`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Total bytes of the `ash::vk::DeviceMemory` blocks of the pools.
+    pub size: vk::DeviceSize,
+
+    /// Total bytes not occupied by any allocation.
+    pub unused_size: vk::DeviceSize,
+
+    /// Number of `Allocation` objects allocated.
+    pub allocation_count: usize,
+
+    /// Number of free ranges of memory between allocations.
+    pub unused_range_count: usize,
+
+    /// Size of the largest free range of any block, in bytes.
+    pub unused_range_size_max: vk::DeviceSize,
+
+    /// Number of `ash::vk::DeviceMemory` blocks allocated.
+    pub block_count: usize,
+}
+
+impl From<&ffi::VmaPoolStats> for PoolStats {
+    fn from(stats: &ffi::VmaPoolStats) -> Self {
+        PoolStats {
+            size: stats.size,
+            unused_size: stats.unusedSize,
+            allocation_count: stats.allocationCount,
+            unused_range_count: stats.unusedRangeCount,
+            unused_range_size_max: stats.unusedRangeSizeMax,
+            block_count: stats.blockCount,
+        }
+    }
+}
+
+/// Adds up the statistics of several pools, e.g. the pools of a `ThreadLocalArena`.
+///
+/// `unusedRangeSizeMax` is the largest free range of any pool.
+pub(crate) fn sum_pool_stats(stats: &[ffi::VmaPoolStats]) -> ffi::VmaPoolStats {
+    let mut total = ffi::VmaPoolStats {
+        size: 0,
+        unusedSize: 0,
+        allocationCount: 0,
+        unusedRangeCount: 0,
+        unusedRangeSizeMax: 0,
+        blockCount: 0,
+    };
+    for stats in stats {
+        total.size += stats.size;
+        total.unusedSize += stats.unusedSize;
+        total.allocationCount += stats.allocationCount;
+        total.unusedRangeCount += stats.unusedRangeCount;
+        total.unusedRangeSizeMax = total.unusedRangeSizeMax.max(stats.unusedRangeSizeMax);
+        total.blockCount += stats.blockCount;
+    }
+    total
+}
+
+/// Suballocation of a memory block, as listed in the detailed map of
+/// `Allocator::build_stats_string`.
+struct Suballocation {
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+    free: bool,
+}
+
+/// Share of `total_free` bytes that is not in the largest free range(s), see
+/// `Allocator::fragmentation`.
+pub(crate) fn fragmentation(largest_free: vk::DeviceSize, total_free: vk::DeviceSize) -> f32 {
+    if total_free == 0 {
+        0.0
+    } else {
+        1.0 - (largest_free as f64 / total_free as f64) as f32
+    }
+}
+
+const SIZE_KEY: &str = "\"Size\":";
+
+fn leading_number(text: &str) -> Option<vk::DeviceSize> {
+    let text = text.trim_start();
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    text[..end].parse().ok()
+}
+
+/// Parses the `{"Offset": .., "Type": .., "Size": ..}` objects of the detailed map, in order.
+///
+/// Dedicated allocations are listed without an offset and are skipped.
+fn suballocations(detailed_json: &str) -> Vec<Suballocation> {
+    detailed_json
+        .split("\"Offset\":")
+        .skip(1)
+        .filter_map(|object| {
+            let object = &object[..object.find('}').unwrap_or(object.len())];
+            let size = &object[object.find(SIZE_KEY)? + SIZE_KEY.len()..];
+            Some(Suballocation {
+                offset: leading_number(object)?,
+                size: leading_number(size)?,
+                free: object.contains("\"Type\": \"FREE\""),
+            })
+        })
+        .collect()
+}
+
+/// Estimates the bytes of free ranges that are alignment padding, from the detailed map of
+/// `Allocator::build_stats_string`.
+///
+/// VMA records the padding in front of an aligned allocation as a free range. A free range that
+/// ends right where an allocation starts is counted as padding if it is smaller than the largest
+/// power of two dividing that allocation's offset, since the alignment cannot be larger. This
+/// is an upper bound: small holes left by freed allocations are counted as well.
+pub(crate) fn alignment_padding(detailed_json: &str) -> vk::DeviceSize {
+    suballocations(detailed_json)
+        .windows(2)
+        .filter(|pair| {
+            let (range, next) = (&pair[0], &pair[1]);
+            range.free
+                && !next.free
+                && next.offset > 0
+                && next.offset == range.offset + range.size
+                && range.size < 1 << next.offset.trailing_zeros()
+        })
+        .map(|pair| pair[0].size)
+        .sum()
+}
+
+/// Sizes of all allocations listed in the detailed map of `Allocator::build_stats_string`,
+/// dedicated allocations included.
+///
+/// Allocations are the only flat objects of the map with a `"Type"` other than `"FREE"`; memory
+/// types are keyed `"Type N"` instead.
+fn allocation_sizes(detailed_json: &str) -> impl Iterator<Item = vk::DeviceSize> + '_ {
+    detailed_json.split('{').skip(1).filter_map(|object| {
+        let object = &object[..object.find('}').unwrap_or(object.len())];
+        if !object.contains("\"Type\":") || object.contains("\"Type\": \"FREE\"") {
+            return None;
+        }
+        leading_number(&object[object.find(SIZE_KEY)? + SIZE_KEY.len()..])
+    })
+}
+
+/// Counts the allocations of the detailed map per size bucket, see
+/// `Allocator::allocation_size_histogram`.
+pub(crate) fn allocation_size_histogram(
+    detailed_json: &str,
+    bucket_bounds: &[vk::DeviceSize],
+) -> Vec<u32> {
+    let mut counts = vec![0; bucket_bounds.len() + 1];
+    for size in allocation_sizes(detailed_json) {
+        let bucket = bucket_bounds
+            .iter()
+            .position(|&bound| size <= bound)
+            .unwrap_or(bucket_bounds.len());
+        counts[bucket] += 1;
+    }
+    counts
+}
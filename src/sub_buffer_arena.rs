@@ -0,0 +1,87 @@
+use crate::{Allocation, AllocationCreateInfo, Allocator, Error, VirtualBlock};
+use ash::prelude::VkResult;
+use ash::vk;
+use std::convert::TryFrom;
+
+/// Single persistently mapped buffer handing out sub-ranges, e.g. for uniform data.
+///
+/// The buffer is created with `Allocator::create_buffer` and mapped once. Free space is tracked
+/// by a `VirtualBlock`. If the memory is not `ash::vk::MemoryPropertyFlags::HOST_COHERENT`, writes
+/// through the returned pointers must be flushed with `Allocator::flush_allocation` on
+/// `SubBufferArena::allocation`.
+///
+/// The buffer is unmapped and destroyed when the arena is dropped.
+#[derive(Debug)]
+pub struct SubBufferArena<'a> {
+    allocator: &'a Allocator,
+    buffer: vk::Buffer,
+    allocation: Allocation,
+    mapped_data: *mut u8,
+    block: VirtualBlock,
+}
+
+impl<'a> SubBufferArena<'a> {
+    /// Creates and maps the backing buffer. `allocation_info` must select host-visible memory.
+    pub unsafe fn new(
+        allocator: &'a Allocator,
+        buffer_info: &vk::BufferCreateInfo,
+        allocation_info: &AllocationCreateInfo,
+    ) -> VkResult<Self> {
+        let (buffer, allocation, _) = allocator.create_buffer(buffer_info, allocation_info)?;
+        let mapped_data = match allocator.map_memory(allocation) {
+            Ok(mapped_data) => mapped_data,
+            Err(err) => {
+                allocator.destroy_buffer(buffer, allocation);
+                return Err(err);
+            }
+        };
+
+        Ok(SubBufferArena {
+            allocator,
+            buffer,
+            allocation,
+            mapped_data,
+            block: VirtualBlock::new(buffer_info.size),
+        })
+    }
+
+    /// Reserves `size` bytes aligned to `alignment` and returns their offset in
+    /// `SubBufferArena::buffer` together with the mapped pointer to them.
+    ///
+    /// Returns `ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY` if the arena is full.
+    pub fn suballoc(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> VkResult<(vk::DeviceSize, *mut u8)> {
+        let offset = self.block.allocate(size, alignment)?;
+        let mapped_offset = usize::try_from(offset).map_err(|_| Error::SizeOverflow)?;
+        Ok((offset, unsafe { self.mapped_data.add(mapped_offset) }))
+    }
+
+    /// Releases the range at `offset`, previously returned by `SubBufferArena::suballoc`.
+    pub fn free(&mut self, offset: vk::DeviceSize) -> VkResult<()> {
+        self.block.free(offset)
+    }
+
+    /// Backing buffer.
+    #[inline(always)]
+    pub fn buffer(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    /// Allocation of the backing buffer.
+    #[inline(always)]
+    pub fn allocation(&self) -> Allocation {
+        self.allocation
+    }
+}
+
+impl Drop for SubBufferArena<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            self.allocator.unmap_memory(self.allocation);
+            self.allocator.destroy_buffer(self.buffer, self.allocation);
+        }
+    }
+}
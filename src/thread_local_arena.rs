@@ -0,0 +1,131 @@
+use crate::{stats, Allocator, AllocatorPool, AllocatorPoolCreateInfo, PoolStats};
+use ash::prelude::VkResult;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, Weak};
+use std::thread::{self, ThreadId};
+
+thread_local! {
+    // Dropped when the thread exits, which tells arenas that its pool is no longer in use.
+    static THREAD_ALIVE: Arc<()> = Arc::new(());
+}
+
+/// A custom pool per thread, so that threads allocating at the same time don't contend.
+///
+/// VMA protects each pool (and each memory type of the default pool) with its own mutex. When
+/// many threads allocate from the same pool, e.g. asset loader workers, they serialize on it.
+/// `ThreadLocalArena::for_current_thread` returns a pool only used by the calling thread, which
+/// is created from the same `AllocatorPoolCreateInfo` on first use. Looking the pool up takes
+/// a shared lock of the arena, so threads only contend on the arena itself when they call
+/// `for_current_thread` for the first time.
+///
+/// The trade-off is memory: every thread keeps its own blocks, so partially filled blocks are
+/// not shared between threads, and an arena used by `n` threads can hold up to `n` times as
+/// many blocks as a single pool. Keep `AllocatorPoolCreateInfo::block_size` moderate, and prefer
+/// a shared pool for threads that allocate rarely.
+///
+/// When a thread exits, its pool is handed to the next thread calling `for_current_thread` for
+/// the first time, so short-lived threads don't add pools. `ThreadLocalArena::reclaim` destroys
+/// the pools of exited threads that no allocation is left in.
+///
+/// Allocations may be freed from any thread. All of them must be freed before the arena is
+/// dropped, which destroys the pools.
+#[derive(Debug)]
+pub struct ThreadLocalArena<'a> {
+    allocator: &'a Allocator,
+    pool_info: AllocatorPoolCreateInfo,
+    pools: RwLock<HashMap<ThreadId, (AllocatorPool, Weak<()>)>>,
+}
+
+// `pool_info` is only read, and `AllocatorPoolCreateInfo::memory_allocate_next` is required to
+// stay valid while pools are created from it, see `ThreadLocalArena::new`.
+unsafe impl Send for ThreadLocalArena<'_> {}
+unsafe impl Sync for ThreadLocalArena<'_> {}
+
+impl<'a> ThreadLocalArena<'a> {
+    /// Creates an arena whose pools are created from `pool_info`.
+    ///
+    /// No pool is created until a thread calls `ThreadLocalArena::for_current_thread`. If
+    /// `pool_info.memory_allocate_next` is set, the chain must stay valid and unmodified for
+    /// the lifetime of the arena.
+    pub fn new(allocator: &'a Allocator, pool_info: &AllocatorPoolCreateInfo) -> Self {
+        ThreadLocalArena {
+            allocator,
+            pool_info: pool_info.clone(),
+            pools: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the pool of the calling thread, on first use taking over the pool of an exited
+    /// thread or creating one.
+    ///
+    /// After the first call from a thread, this only takes a shared lock to look the pool up.
+    pub unsafe fn for_current_thread(&self) -> VkResult<AllocatorPool> {
+        let id = thread::current().id();
+        if let Some(&(pool, _)) = self.pools.read().unwrap().get(&id) {
+            return Ok(pool);
+        }
+
+        let alive = THREAD_ALIVE.with(Arc::downgrade);
+        let mut pools = self.pools.write().unwrap();
+        let exited = pools
+            .iter()
+            .find(|(_, (_, alive))| alive.strong_count() == 0)
+            .map(|(&exited, _)| exited);
+        let pool = match exited {
+            Some(exited) => pools.remove(&exited).unwrap().0,
+            None => self.allocator.create_pool(&self.pool_info)?,
+        };
+        pools.insert(id, (pool, alive));
+        Ok(pool)
+    }
+
+    /// Number of threads that have a pool in this arena, including exited threads whose pool
+    /// was not reclaimed yet.
+    pub fn thread_count(&self) -> usize {
+        self.pools.read().unwrap().len()
+    }
+
+    /// Destroys the pools of exited threads that have no allocations left, and returns how many
+    /// were destroyed.
+    ///
+    /// Pools that still hold allocations, e.g. ones passed to another thread, are kept until
+    /// a later call finds them empty or a new thread takes them over.
+    pub unsafe fn reclaim(&self) -> VkResult<usize> {
+        let mut pools = self.pools.write().unwrap();
+        let mut empty = Vec::new();
+        for (&id, &(pool, ref alive)) in pools.iter() {
+            if alive.strong_count() == 0
+                && self.allocator.get_pool_stats(pool)?.allocationCount == 0
+            {
+                empty.push(id);
+            }
+        }
+        for id in &empty {
+            let (pool, _) = pools.remove(id).unwrap();
+            self.allocator.destroy_pool(pool);
+        }
+        Ok(empty.len())
+    }
+
+    /// Returns the statistics of all pools of the arena, added together.
+    ///
+    /// `PoolStats::unused_range_size_max` is the largest free range of any pool.
+    pub unsafe fn stats(&self) -> VkResult<PoolStats> {
+        let stats = self
+            .pools
+            .read()
+            .unwrap()
+            .values()
+            .map(|&(pool, _)| self.allocator.get_pool_stats(pool))
+            .collect::<VkResult<Vec<_>>>()?;
+        Ok(PoolStats::from(&stats::sum_pool_stats(&stats)))
+    }
+}
+
+impl Drop for ThreadLocalArena<'_> {
+    fn drop(&mut self) {
+        for (_, (pool, _)) in self.pools.get_mut().unwrap().drain() {
+            unsafe { self.allocator.destroy_pool(pool) };
+        }
+    }
+}
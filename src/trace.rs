@@ -0,0 +1,76 @@
+//! `tracing` events of the `tracing` feature.
+//!
+//! Every function is a no-op unless the feature is enabled, so the allocator can call them
+//! unconditionally. All events use the `vk_mem` target.
+#![cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+
+use crate::{Allocation, AllocationInfo, AllocatorPool, DefragmentationStats};
+
+pub(crate) fn allocated(
+    allocation: Allocation,
+    info: &AllocationInfo,
+    pool: Option<AllocatorPool>,
+) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        target: "vk_mem",
+        ?allocation,
+        size = info.device_size(),
+        memory_type = info.memory_type(),
+        ?pool,
+        "allocated"
+    );
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn freed(allocation: Allocation, info: &AllocationInfo) {
+    tracing::debug!(
+        target: "vk_mem",
+        ?allocation,
+        size = info.device_size(),
+        memory_type = info.memory_type(),
+        "freed"
+    );
+}
+
+pub(crate) fn mapped(allocation: Allocation) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(target: "vk_mem", ?allocation, "mapped");
+}
+
+pub(crate) fn unmapped(allocation: Allocation) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(target: "vk_mem", ?allocation, "unmapped");
+}
+
+pub(crate) fn defragmentation_begun(allocation_count: usize, pool_count: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        target: "vk_mem",
+        allocation_count,
+        pool_count,
+        "defragmentation begun"
+    );
+}
+
+pub(crate) fn defragmented(stats: &DefragmentationStats) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        target: "vk_mem",
+        bytes_moved = stats.bytes_moved as u64,
+        bytes_freed = stats.bytes_freed as u64,
+        allocations_moved = stats.allocations_moved,
+        device_memory_blocks_freed = stats.device_memory_blocks_freed,
+        "defragmented"
+    );
+}
+
+pub(crate) fn ignored_buffer_image_granularity(memory_type_index: u32, granularity: u64) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        target: "vk_mem",
+        memory_type_index,
+        granularity,
+        "pool ignores a buffer-image granularity larger than 1; buffers and optimal images in it may alias"
+    );
+}
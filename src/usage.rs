@@ -0,0 +1,38 @@
+use ash::vk;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Bytes currently allocated and their high-water mark, see `Allocator::peak_usage`.
+///
+/// The size of each counted allocation is remembered in its `AllocationState`, so that freeing
+/// it doesn't have to query VMA, and allocations that were never counted are not subtracted.
+#[derive(Debug, Default)]
+pub(crate) struct UsageCounter {
+    current: AtomicU64,
+    peak: AtomicU64,
+}
+
+impl UsageCounter {
+    pub(crate) fn add(&self, size: vk::DeviceSize) {
+        let current = self.current.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(current, Ordering::Relaxed);
+    }
+
+    /// Only called with sizes passed to `UsageCounter::add`.
+    pub(crate) fn sub(&self, size: vk::DeviceSize) {
+        let previous = self.current.fetch_sub(size, Ordering::Relaxed);
+        debug_assert!(previous >= size, "device-local usage underflow");
+    }
+
+    pub(crate) fn current(&self) -> vk::DeviceSize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn peak(&self) -> vk::DeviceSize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    /// Restarts the high-water mark from the current usage.
+    pub(crate) fn reset_peak(&self) {
+        self.peak.store(self.current(), Ordering::Relaxed);
+    }
+}
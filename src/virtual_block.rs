@@ -0,0 +1,132 @@
+use ash::prelude::VkResult;
+use ash::vk;
+use std::collections::{BTreeMap, HashMap};
+
+/// Allocator of ranges inside a block of abstract, user-managed memory.
+///
+/// `VirtualBlock` does not allocate any Vulkan memory. It only tracks which ranges of a block of
+/// `size` bytes are in use, so it can be used to sub-allocate from a single buffer or from memory
+/// obtained by other means. The vendored VMA predates the virtual allocator, so this is
+/// implemented by the wrapper as a first-fit free list that merges adjacent free ranges.
+///
+/// A block can be associated with an `ash::vk::DeviceMemory` it describes, see
+/// `Allocator::wrap_external_memory`.
+#[derive(Debug, Clone)]
+pub struct VirtualBlock {
+    size: vk::DeviceSize,
+    device_memory: vk::DeviceMemory,
+    /// Free ranges, keyed by offset.
+    free: BTreeMap<vk::DeviceSize, vk::DeviceSize>,
+    /// Sizes of allocated ranges, keyed by offset.
+    allocated: HashMap<vk::DeviceSize, vk::DeviceSize>,
+}
+
+impl VirtualBlock {
+    /// Creates an empty block of `size` bytes.
+    pub fn new(size: vk::DeviceSize) -> Self {
+        let mut free = BTreeMap::new();
+        if size > 0 {
+            free.insert(0, size);
+        }
+        VirtualBlock {
+            size,
+            device_memory: vk::DeviceMemory::null(),
+            free,
+            allocated: HashMap::new(),
+        }
+    }
+
+    /// Creates an empty block of `size` bytes describing `device_memory`, whose offsets are
+    /// offsets into that memory.
+    pub fn with_device_memory(device_memory: vk::DeviceMemory, size: vk::DeviceSize) -> Self {
+        VirtualBlock {
+            device_memory,
+            ..VirtualBlock::new(size)
+        }
+    }
+
+    /// Allocates `size` bytes aligned to `alignment` and returns the offset of the range.
+    ///
+    /// Returns `ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY` if no free range is large enough,
+    /// and `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` if `size` is 0.
+    pub fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> VkResult<vk::DeviceSize> {
+        if size == 0 {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+        }
+        let alignment = alignment.max(1);
+
+        let found = self.free.iter().find_map(|(&start, &len)| {
+            let aligned = start.checked_next_multiple_of(alignment)?;
+            match aligned.checked_add(size) {
+                Some(end) if end <= start + len => Some((start, len, aligned)),
+                _ => None,
+            }
+        });
+        let (start, len, offset) = found.ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)?;
+
+        self.free.remove(&start);
+        if offset > start {
+            self.free.insert(start, offset - start);
+        }
+        let end = offset + size;
+        if end < start + len {
+            self.free.insert(end, start + len - end);
+        }
+        self.allocated.insert(offset, size);
+        Ok(offset)
+    }
+
+    /// Frees the range starting at `offset`, previously returned by `VirtualBlock::allocate`.
+    ///
+    /// Returns `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` if no range starts at `offset`.
+    pub fn free(&mut self, offset: vk::DeviceSize) -> VkResult<()> {
+        let size = self
+            .allocated
+            .remove(&offset)
+            .ok_or(vk::Result::ERROR_VALIDATION_FAILED_EXT)?;
+
+        let mut start = offset;
+        let mut len = size;
+        if let Some((&prev_start, &prev_len)) = self.free.range(..offset).next_back() {
+            if prev_start + prev_len == offset {
+                self.free.remove(&prev_start);
+                start = prev_start;
+                len += prev_len;
+            }
+        }
+        if let Some(next_len) = self.free.remove(&(offset + size)) {
+            len += next_len;
+        }
+        self.free.insert(start, len);
+        Ok(())
+    }
+
+    /// Frees all ranges at once.
+    pub fn clear(&mut self) {
+        *self = VirtualBlock::with_device_memory(self.device_memory, self.size);
+    }
+
+    /// Returns `true` if no ranges are allocated.
+    pub fn is_empty(&self) -> bool {
+        self.allocated.is_empty()
+    }
+
+    /// Total size of the block in bytes.
+    pub fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+
+    /// Memory the block describes, or null if it was created with `VirtualBlock::new`.
+    pub fn device_memory(&self) -> vk::DeviceMemory {
+        self.device_memory
+    }
+
+    /// Number of bytes in allocated ranges, excluding alignment padding.
+    pub fn allocated_bytes(&self) -> vk::DeviceSize {
+        self.allocated.values().sum()
+    }
+}
@@ -1,151 +1,6 @@
-use ash::extensions::ext::DebugReport;
-use std::os::raw::{c_char, c_void};
-
-fn extension_names() -> Vec<*const i8> {
-    vec![DebugReport::name().as_ptr()]
-}
-
-unsafe extern "system" fn vulkan_debug_callback(
-    _: ash::vk::DebugReportFlagsEXT,
-    _: ash::vk::DebugReportObjectTypeEXT,
-    _: u64,
-    _: usize,
-    _: i32,
-    _: *const c_char,
-    p_message: *const c_char,
-    _: *mut c_void,
-) -> u32 {
-    println!("{:?}", ::std::ffi::CStr::from_ptr(p_message));
-    ash::vk::FALSE
-}
-
-pub struct TestHarness {
-    pub entry: ash::Entry,
-    pub instance: ash::Instance,
-    pub device: ash::Device,
-    pub physical_device: ash::vk::PhysicalDevice,
-    pub debug_callback: ash::vk::DebugReportCallbackEXT,
-    pub debug_report_loader: ash::extensions::ext::DebugReport,
-}
-
-impl Drop for TestHarness {
-    fn drop(&mut self) {
-        unsafe {
-            self.device.device_wait_idle().unwrap();
-            self.device.destroy_device(None);
-            self.debug_report_loader
-                .destroy_debug_report_callback(self.debug_callback, None);
-            self.instance.destroy_instance(None);
-        }
-    }
-}
-impl TestHarness {
-    pub fn new() -> Self {
-        let app_name = ::std::ffi::CString::new("vk-mem testing").unwrap();
-        let app_info = ash::vk::ApplicationInfo::builder()
-            .application_name(&app_name)
-            .application_version(0)
-            .engine_name(&app_name)
-            .engine_version(0)
-            .api_version(ash::vk::API_VERSION_1_0);
-
-        let layer_names = [::std::ffi::CString::new("VK_LAYER_KHRONOS_validation").unwrap()];
-        let layers_names_raw: Vec<*const i8> = layer_names
-            .iter()
-            .map(|raw_name| raw_name.as_ptr())
-            .collect();
-
-        let extension_names_raw = extension_names();
-        let create_info = ash::vk::InstanceCreateInfo::builder()
-            .application_info(&app_info)
-            .enabled_layer_names(&layers_names_raw)
-            .enabled_extension_names(&extension_names_raw);
-
-        let entry = unsafe { ash::Entry::new().unwrap() };
-        let instance: ash::Instance = unsafe {
-            entry
-                .create_instance(&create_info, None)
-                .expect("Instance creation error")
-        };
-
-        let debug_info = ash::vk::DebugReportCallbackCreateInfoEXT::builder()
-            .flags(
-                ash::vk::DebugReportFlagsEXT::ERROR
-                    | ash::vk::DebugReportFlagsEXT::WARNING
-                    | ash::vk::DebugReportFlagsEXT::PERFORMANCE_WARNING,
-            )
-            .pfn_callback(Some(vulkan_debug_callback));
-
-        let debug_report_loader = DebugReport::new(&entry, &instance);
-        let debug_callback = unsafe {
-            debug_report_loader
-                .create_debug_report_callback(&debug_info, None)
-                .unwrap()
-        };
-
-        let physical_devices = unsafe {
-            instance
-                .enumerate_physical_devices()
-                .expect("Physical device error")
-        };
-
-        let (physical_device, queue_family_index) = unsafe {
-            physical_devices
-                .iter()
-                .map(|physical_device| {
-                    instance
-                        .get_physical_device_queue_family_properties(*physical_device)
-                        .iter()
-                        .enumerate()
-                        .filter_map(|(index, _)| Some((*physical_device, index)))
-                        .nth(0)
-                })
-                .filter_map(|v| v)
-                .nth(0)
-                .expect("Couldn't find suitable device.")
-        };
+mod test_support;
 
-        let priorities = [1.0];
-
-        let queue_info = [ash::vk::DeviceQueueCreateInfo::builder()
-            .queue_family_index(queue_family_index as u32)
-            .queue_priorities(&priorities)
-            .build()];
-
-        let device_create_info =
-            ash::vk::DeviceCreateInfo::builder().queue_create_infos(&queue_info);
-
-        let device: ash::Device = unsafe {
-            instance
-                .create_device(physical_device, &device_create_info, None)
-                .unwrap()
-        };
-
-        TestHarness {
-            entry,
-            instance,
-            device,
-            physical_device,
-            debug_report_loader,
-            debug_callback,
-        }
-    }
-
-    pub fn create_allocator(&self) -> vk_mem::Allocator {
-        let create_info = vk_mem::AllocatorCreateInfo {
-            flags: Default::default(),
-            physical_device: self.physical_device,
-            device: self.device.clone(),
-            instance: self.instance.clone(),
-            preferred_large_heap_block_size: 0,
-            allocation_callbacks: None,
-            frame_in_use_count: 0,
-            heap_size_limits: None,
-            vulkan_api_version: 0,
-        };
-        unsafe { vk_mem::Allocator::new(&create_info).unwrap() }
-    }
-}
+use test_support::TestHarness;
 
 #[test]
 fn create_harness() {
@@ -275,10 +130,10 @@ fn test_gpu_stats() {
         ..Default::default()
     };
 
-    let stats_1 = unsafe { allocator.calculate_stats().unwrap() };
-    assert_eq!(stats_1.total.blockCount, 0);
-    assert_eq!(stats_1.total.allocationCount, 0);
-    assert_eq!(stats_1.total.usedBytes, 0);
+    let stats_1 = allocator.calculate_stats().unwrap();
+    assert_eq!(stats_1.total.block_count, 0);
+    assert_eq!(stats_1.total.allocation_count, 0);
+    assert_eq!(stats_1.total.used_bytes, 0);
 
     let (buffer, allocation, _allocation_info) = unsafe {
         allocator
@@ -295,17 +150,17 @@ fn test_gpu_stats() {
             .unwrap()
     };
 
-    let stats_2 = unsafe { allocator.calculate_stats().unwrap() };
-    assert_eq!(stats_2.total.blockCount, 1);
-    assert_eq!(stats_2.total.allocationCount, 1);
-    assert_eq!(stats_2.total.usedBytes, 16 * 1024);
+    let stats_2 = allocator.calculate_stats().unwrap();
+    assert_eq!(stats_2.total.block_count, 1);
+    assert_eq!(stats_2.total.allocation_count, 1);
+    assert_eq!(stats_2.total.used_bytes, 16 * 1024);
 
     unsafe { allocator.destroy_buffer(buffer, allocation) };
 
-    let stats_3 = unsafe { allocator.calculate_stats().unwrap() };
-    assert_eq!(stats_3.total.blockCount, 1);
-    assert_eq!(stats_3.total.allocationCount, 0);
-    assert_eq!(stats_3.total.usedBytes, 0);
+    let stats_3 = allocator.calculate_stats().unwrap();
+    assert_eq!(stats_3.total.block_count, 1);
+    assert_eq!(stats_3.total.allocation_count, 0);
+    assert_eq!(stats_3.total.used_bytes, 0);
 
     unsafe { allocator.destroy_allocator() };
 }
@@ -319,7 +174,7 @@ fn test_stats_string() {
         ..Default::default()
     };
 
-    let stats_1 = unsafe { allocator.build_stats_string(true).unwrap() };
+    let stats_1 = allocator.build_stats_string(true).unwrap();
     assert!(stats_1.len() > 0);
 
     let (buffer, allocation, _allocation_info) = unsafe {
@@ -337,16 +192,4394 @@ fn test_stats_string() {
             .unwrap()
     };
 
-    let stats_2 = unsafe { allocator.build_stats_string(true).unwrap() };
+    let stats_2 = allocator.build_stats_string(true).unwrap();
     assert!(stats_2.len() > 0);
     assert_ne!(stats_1, stats_2);
 
     unsafe { allocator.destroy_buffer(buffer, allocation) };
 
-    let stats_3 = unsafe { allocator.build_stats_string(true).unwrap() };
+    let stats_3 = allocator.build_stats_string(true).unwrap();
     assert!(stats_3.len() > 0);
     assert_ne!(stats_3, stats_1);
     assert_ne!(stats_3, stats_2);
 
     unsafe { allocator.destroy_allocator() };
 }
+
+#[test]
+fn get_budget() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let heap_count = allocator.get_memory_properties().unwrap().memory_heap_count as usize;
+
+    let budgets_1 = allocator.get_budget().unwrap();
+    assert_eq!(budgets_1.len(), heap_count);
+
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        ..Default::default()
+    };
+    let (buffer, allocation, info) = unsafe {
+        allocator
+            .create_buffer(
+                &ash::vk::BufferCreateInfo::builder()
+                    .size(16 * 1024)
+                    .usage(ash::vk::BufferUsageFlags::VERTEX_BUFFER)
+                    .build(),
+                &allocation_info,
+            )
+            .unwrap()
+    };
+    let memory_types = allocator.get_memory_properties().unwrap().memory_types;
+    let heap = memory_types[info.memory_type() as usize].heap_index as usize;
+
+    let budgets_2 = allocator.get_budget().unwrap();
+    assert!(budgets_2[heap].allocationBytes >= budgets_1[heap].allocationBytes + 16 * 1024);
+    assert!(budgets_2[heap].blockBytes >= budgets_2[heap].allocationBytes);
+
+    // Nothing is over a budget that can never be reached.
+    assert!(allocator.heaps_over_budget(f32::MAX).unwrap().is_empty());
+
+    unsafe {
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn test_shared_allocation() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        ..Default::default()
+    };
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 16 * 1024,
+        alignment: 256,
+        memory_type_bits: u32::MAX,
+    };
+
+    let (allocation, _allocation_info) = unsafe {
+        allocator
+            .allocate_memory(&memory_requirements, &allocation_info)
+            .unwrap()
+    };
+    let shared = unsafe { vk_mem::SharedAllocation::new(&allocator, allocation) };
+    let clone = shared.clone();
+    assert_eq!(shared.strong_count(), 2);
+    assert_eq!(clone.allocation(), allocation);
+
+    drop(shared);
+    let stats_1 = allocator.calculate_stats().unwrap();
+    assert_eq!(stats_1.total.allocation_count, 1);
+
+    drop(clone);
+    let stats_2 = allocator.calculate_stats().unwrap();
+    assert_eq!(stats_2.total.allocation_count, 0);
+
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn read_write_mapping() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuToCpu,
+        ..Default::default()
+    };
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: u32::MAX,
+    };
+    let (allocation, _allocation_info) = unsafe {
+        allocator
+            .allocate_memory(&memory_requirements, &allocation_info)
+            .unwrap()
+    };
+
+    unsafe {
+        let mut write = allocator.map_memory_for_write(allocation).unwrap();
+        assert_eq!(write.len(), 1024);
+        for (i, byte) in write.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        write.flush().unwrap();
+    }
+
+    unsafe {
+        let read = allocator.map_memory_for_read(allocation).unwrap();
+        assert_eq!(read.allocation(), allocation);
+        assert!(read.iter().enumerate().all(|(i, &byte)| byte == i as u8));
+    }
+
+    // Both guards unmapped the memory again.
+    let info = unsafe { allocator.get_allocation_info(allocation).unwrap() };
+    assert!(info.mapped_data().is_null());
+
+    unsafe {
+        allocator.free_memory(allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn create_owned_buffer() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        ..Default::default()
+    };
+    let buffer = unsafe {
+        allocator
+            .create_owned_buffer(
+                &ash::vk::BufferCreateInfo::builder()
+                    .size(16 * 1024)
+                    .usage(ash::vk::BufferUsageFlags::VERTEX_BUFFER)
+                    .build(),
+                &allocation_info,
+            )
+            .unwrap()
+    };
+    assert_ne!(buffer.handle(), ash::vk::Buffer::null());
+    assert_eq!(buffer.info().device_size(), 16 * 1024);
+
+    drop(buffer);
+    let stats = allocator.calculate_stats().unwrap();
+    assert_eq!(stats.total.allocation_count, 0);
+    assert!(allocator.assert_empty().unwrap().is_ok());
+
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn reject_exclusive_flags() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::CpuOnly,
+        flags: vk_mem::AllocationCreateFlags::MAPPED
+            | vk_mem::AllocationCreateFlags::CAN_BECOME_LOST,
+        ..Default::default()
+    };
+    let result = unsafe {
+        allocator.create_buffer(
+            &ash::vk::BufferCreateInfo::builder()
+                .size(16 * 1024)
+                .usage(ash::vk::BufferUsageFlags::TRANSFER_SRC)
+                .build(),
+            &allocation_info,
+        )
+    };
+    assert_eq!(result.err(), Some(vk_mem::Error::InvalidFlags));
+
+    let built = vk_mem::AllocationCreateInfo::builder()
+        .flags(
+            vk_mem::AllocationCreateFlags::DEDICATED_MEMORY
+                | vk_mem::AllocationCreateFlags::NEVER_ALLOCATE,
+        )
+        .build();
+    assert_eq!(built.unwrap_err(), vk_mem::Error::InvalidFlags);
+
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn double_stack_pool() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        ..Default::default()
+    };
+    let memory_type_index = allocator
+        .find_memory_type_index(u32::MAX, &allocation_info)
+        .unwrap();
+
+    let general = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        ..Default::default()
+    };
+    assert_eq!(
+        unsafe { vk_mem::DoubleStackPool::new(&allocator, &general) }.err(),
+        Some(vk_mem::Error::NotLinearPool)
+    );
+
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        flags: vk_mem::AllocatorPoolCreateFlags::LINEAR_ALGORITHM,
+        block_size: 64 * 1024,
+        max_block_count: 1,
+        ..Default::default()
+    };
+    let pool = unsafe { vk_mem::DoubleStackPool::new(&allocator, &pool_info).unwrap() };
+    let mut lower = Vec::new();
+    let mut upper = Vec::new();
+    for _ in 0..4 {
+        lower.push(unsafe { pool.alloc_lower(1024, 256).unwrap() });
+        upper.push(unsafe { pool.alloc_upper(1024, 256).unwrap() });
+    }
+
+    // The lower stack grows up from the start of the block, the upper one down from its end.
+    for (i, (_, info)) in lower.iter().enumerate() {
+        assert_eq!(info.device_offset(), i as u64 * 1024);
+    }
+    for (i, (_, info)) in upper.iter().enumerate() {
+        assert_eq!(info.device_offset(), (63 - i as u64) * 1024);
+    }
+    let block = lower[0].1.device_memory();
+    let ranges = |allocations: &[(vk_mem::Allocation, vk_mem::AllocationInfo)]| {
+        allocations
+            .iter()
+            .map(|(_, info)| {
+                assert_eq!(info.device_memory(), block);
+                (
+                    info.device_offset(),
+                    info.device_offset() + info.device_size(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let assert_disjoint = |ranges: &[(u64, u64)]| {
+        for (i, a) in ranges.iter().enumerate() {
+            for b in &ranges[i + 1..] {
+                assert!(a.1 <= b.0 || b.1 <= a.0, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    };
+    assert_disjoint(&[ranges(&lower), ranges(&upper)].concat());
+
+    // Freeing from the middle of either stack doesn't move the top of the stacks.
+    unsafe {
+        pool.free(lower.remove(1).0);
+        pool.free(upper.remove(2).0);
+        pool.free(lower.remove(0).0);
+    }
+    lower.push(unsafe { pool.alloc_lower(1024, 256).unwrap() });
+    upper.push(unsafe { pool.alloc_upper(1024, 256).unwrap() });
+    assert!(lower[2].1.device_offset() >= 4 * 1024);
+    assert!(upper[3].1.device_offset() + upper[3].1.device_size() <= 60 * 1024);
+    assert_disjoint(&[ranges(&lower), ranges(&upper)].concat());
+
+    unsafe {
+        for (allocation, _) in upper.into_iter().chain(lower).rev() {
+            pool.free(allocation);
+        }
+    }
+    drop(pool);
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn allocation_tags() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 4096,
+        alignment: 256,
+        memory_type_bits: u32::MAX,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        ..Default::default()
+    };
+    let (textures, _) = unsafe {
+        allocator
+            .allocate_tagged(&memory_requirements, &allocation_info, 1)
+            .unwrap()
+    };
+    let (geometry, _) = unsafe {
+        allocator
+            .allocate_tagged(&memory_requirements, &allocation_info, 2)
+            .unwrap()
+    };
+
+    let bytes = allocator.bytes_by_tag();
+    assert_eq!(bytes.get(&1), Some(&4096));
+    assert_eq!(bytes.get(&2), Some(&4096));
+
+    unsafe { allocator.free_memory(textures) };
+    assert_eq!(allocator.bytes_by_tag().get(&1), None);
+
+    // Retagging moves the allocation to the new tag instead of counting it twice.
+    unsafe { allocator.set_allocation_tag(geometry, 3).unwrap() };
+    let bytes = allocator.bytes_by_tag();
+    assert_eq!(bytes.len(), 1);
+    assert_eq!(bytes.get(&3), Some(&4096));
+
+    unsafe {
+        allocator.free_memory(geometry);
+        assert!(allocator.bytes_by_tag().is_empty());
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn flush_whole_size() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 4096,
+        alignment: 256,
+        memory_type_bits: u32::MAX,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::CpuToGpu,
+        ..Default::default()
+    };
+    let (allocation, info) = unsafe {
+        allocator
+            .allocate_memory(&memory_requirements, &allocation_info)
+            .unwrap()
+    };
+    assert_eq!(info.device_size(), 4096);
+
+    unsafe {
+        allocator
+            .flush_allocation(allocation, 0, ash::vk::WHOLE_SIZE as usize)
+            .unwrap();
+        allocator
+            .invalidate_allocation(allocation, 0, ash::vk::WHOLE_SIZE as usize)
+            .unwrap();
+        allocator.free_memory(allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn virtual_block() {
+    let mut block = vk_mem::VirtualBlock::new(1024);
+    let a = block.allocate(100, 1).unwrap();
+    let b = block.allocate(100, 256).unwrap();
+    assert_eq!(a, 0);
+    assert_eq!(b, 256);
+    assert!(block.allocate(1024, 1).is_err());
+
+    block.free(a).unwrap();
+    block.free(b).unwrap();
+    assert!(block.is_empty());
+    assert_eq!(block.allocate(1024, 1).unwrap(), 0);
+    assert!(block.free(512).is_err());
+}
+
+#[test]
+fn free_memory_pages_sorted() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 256,
+        alignment: 256,
+        memory_type_bits: u32::MAX,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        ..Default::default()
+    };
+    let mut allocations = unsafe {
+        allocator
+            .allocate_memory_pages(&memory_requirements, &allocation_info, 8)
+            .unwrap()
+    }
+    .into_iter()
+    .rev()
+    .flat_map(|(allocation, _)| vec![allocation, vk_mem::Allocation::null()])
+    .collect::<Vec<_>>();
+    assert_eq!(allocations.len(), 16);
+
+    unsafe { allocator.free_memory_pages_sorted(&mut allocations) };
+    assert!(allocations[..8]
+        .iter()
+        .all(|allocation| allocation.is_null()));
+    assert!(allocator.assert_empty().unwrap().is_ok());
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn reject_zero_sized_images() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        ..Default::default()
+    };
+    let image_info = ash::vk::ImageCreateInfo::builder()
+        .image_type(ash::vk::ImageType::TYPE_2D)
+        .format(ash::vk::Format::R8G8B8A8_UNORM)
+        .extent(ash::vk::Extent3D {
+            width: 16,
+            height: 16,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(ash::vk::SampleCountFlags::TYPE_1)
+        .tiling(ash::vk::ImageTiling::OPTIMAL)
+        .usage(ash::vk::ImageUsageFlags::SAMPLED)
+        .build();
+
+    let zero_width = ash::vk::ImageCreateInfo {
+        extent: ash::vk::Extent3D {
+            width: 0,
+            ..image_info.extent
+        },
+        ..image_info
+    };
+    let zero_height = ash::vk::ImageCreateInfo {
+        extent: ash::vk::Extent3D {
+            height: 0,
+            ..image_info.extent
+        },
+        ..image_info
+    };
+    let zero_depth = ash::vk::ImageCreateInfo {
+        extent: ash::vk::Extent3D {
+            depth: 0,
+            ..image_info.extent
+        },
+        ..image_info
+    };
+    let zero_mip_levels = ash::vk::ImageCreateInfo {
+        mip_levels: 0,
+        ..image_info
+    };
+    let zero_array_layers = ash::vk::ImageCreateInfo {
+        array_layers: 0,
+        ..image_info
+    };
+
+    for invalid in &[
+        zero_width,
+        zero_height,
+        zero_depth,
+        zero_mip_levels,
+        zero_array_layers,
+    ] {
+        let result = unsafe { allocator.create_image(invalid, &allocation_info) };
+        assert_eq!(
+            result.err(),
+            Some(vk_mem::Error::Vulkan(
+                ash::vk::Result::ERROR_VALIDATION_FAILED_EXT
+            ))
+        );
+    }
+
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn thread_local_arena() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: u32::MAX,
+    };
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            memory_requirements.memory_type_bits,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+    let arena = vk_mem::ThreadLocalArena::new(
+        &allocator,
+        &vk_mem::AllocatorPoolCreateInfo {
+            memory_type_index,
+            ..Default::default()
+        },
+    );
+
+    // All threads are alive at the same time, so none of them takes over the pool of another.
+    let barrier = std::sync::Barrier::new(8);
+    let per_thread = std::thread::scope(|scope| {
+        let threads = (0..8)
+            .map(|_| {
+                scope.spawn(|| unsafe {
+                    let pool = arena.for_current_thread().unwrap();
+                    assert_eq!(arena.for_current_thread().unwrap(), pool);
+                    let allocation_info = vk_mem::AllocationCreateInfo {
+                        pool: Some(pool),
+                        ..Default::default()
+                    };
+                    let allocations = (0..100)
+                        .map(|_| {
+                            allocator
+                                .allocate_memory(&memory_requirements, &allocation_info)
+                                .unwrap()
+                                .0
+                        })
+                        .collect::<Vec<_>>();
+                    barrier.wait();
+                    assert_eq!(allocator.get_pool_stats(pool).unwrap().allocationCount, 100);
+                    (pool, allocations)
+                })
+            })
+            .collect::<Vec<_>>();
+        threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let mut pools = per_thread.iter().map(|&(pool, _)| pool).collect::<Vec<_>>();
+    pools.sort();
+    pools.dedup();
+    assert_eq!(pools.len(), 8);
+    assert_eq!(arena.thread_count(), 8);
+    assert_eq!(unsafe { arena.stats() }.unwrap().allocation_count, 800);
+
+    // Allocations may be freed from another thread.
+    for (_, allocations) in per_thread {
+        for allocation in allocations {
+            unsafe { allocator.free_memory(allocation) };
+        }
+    }
+    assert_eq!(unsafe { arena.stats() }.unwrap().allocation_count, 0);
+
+    drop(arena);
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn preallocate() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_count = allocator.get_memory_properties().unwrap().memory_type_count;
+
+    let reserved = unsafe { allocator.preallocate(0, 1024 * 1024) }.unwrap();
+    let stats = allocator.calculate_stats().unwrap();
+    assert_eq!(stats.total.allocation_count, 0);
+    assert_eq!(stats.memory_type[0].unused_bytes, reserved);
+
+    // The empty block is kept, so warming up again doesn't reserve more.
+    assert_eq!(unsafe { allocator.preallocate(0, 1024 * 1024) }.unwrap(), 0);
+    assert_eq!(unsafe { allocator.preallocate(0, 0) }.unwrap(), 0);
+    assert_eq!(
+        unsafe { allocator.preallocate(memory_type_count, 1024) },
+        Err(vk_mem::Error::InvalidMemoryType)
+    );
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn for_usage_maps_upload_memory() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let allocation_info = vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuToGpu);
+    assert_eq!(allocation_info.flags, vk_mem::AllocationCreateFlags::MAPPED);
+    let (buffer, allocation, info) = unsafe {
+        allocator
+            .create_buffer(
+                &ash::vk::BufferCreateInfo::builder()
+                    .size(16 * 1024)
+                    .usage(ash::vk::BufferUsageFlags::UNIFORM_BUFFER)
+                    .build(),
+                &allocation_info,
+            )
+            .unwrap()
+    };
+    assert!(!info.mapped_data().is_null());
+    unsafe {
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn track_pools() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index: 0,
+        block_size: 1024 * 1024,
+        ..Default::default()
+    };
+    let pools: Vec<_> = (0..3)
+        .map(|_| unsafe { allocator.create_pool(&pool_info).unwrap() })
+        .collect();
+    assert_eq!(allocator.pools(), pools);
+
+    unsafe { allocator.destroy_pool(pools[1]) };
+    assert_eq!(allocator.pools(), [pools[0], pools[2]]);
+
+    unsafe { allocator.destroy_all_pools() };
+    assert!(allocator.pools().is_empty());
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn map_persistent() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let (buffer, allocation, _) = unsafe {
+        allocator
+            .create_buffer(
+                &ash::vk::BufferCreateInfo::builder()
+                    .size(256)
+                    .usage(ash::vk::BufferUsageFlags::UNIFORM_BUFFER)
+                    .build(),
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuToGpu),
+            )
+            .unwrap()
+    };
+
+    let mut map = unsafe { allocator.map_persistent(allocation) }.unwrap();
+    for frame in 0..3u32 {
+        let data = unsafe { map.as_slice_mut::<u32>() };
+        assert_eq!(data.len(), 64);
+        data.iter_mut().for_each(|value| *value = frame);
+        map.flush().unwrap();
+        assert!(unsafe { map.as_slice::<u32>() }
+            .iter()
+            .all(|&value| value == frame));
+    }
+    drop(map);
+
+    unsafe {
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn alignment_overhead() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    assert_eq!(allocator.alignment_overhead().unwrap(), 0);
+
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 100,
+        alignment: 256,
+        memory_type_bits: 1,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo::default();
+    let allocations: Vec<_> = (0..2)
+        .map(|_| unsafe {
+            allocator
+                .allocate_memory(&memory_requirements, &allocation_info)
+                .unwrap()
+                .0
+        })
+        .collect();
+    // The second allocation starts at 256, after 156 bytes of padding.
+    assert!(allocator.alignment_overhead().unwrap() >= 156);
+
+    unsafe { allocator.free_memory_pages(&allocations) };
+    assert_eq!(allocator.alignment_overhead().unwrap(), 0);
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn image_staging_size() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let image_info = ash::vk::ImageCreateInfo::builder()
+        .image_type(ash::vk::ImageType::TYPE_2D)
+        .format(ash::vk::Format::BC1_RGB_UNORM_BLOCK)
+        .extent(ash::vk::Extent3D {
+            width: 16,
+            height: 8,
+            depth: 1,
+        })
+        .mip_levels(5)
+        .array_layers(2)
+        .build();
+
+    let (size, regions) = allocator.image_staging_size(&image_info).unwrap();
+    assert_eq!(regions.len(), 5);
+    // 4x4 blocks of 8 bytes: 8 blocks at mip 0, 2 at mip 1, then a single block.
+    let block_counts = [8, 2, 1, 1, 1];
+    for (region, blocks) in regions.iter().zip(block_counts.iter()) {
+        assert_eq!(region.buffer_offset % 8, 0);
+        assert_eq!(region.image_subresource.layer_count, 2);
+        assert!(region.buffer_offset + blocks * 8 * 2 <= size);
+    }
+    assert_eq!(regions[4].image_extent.width, 1);
+    assert_eq!(regions[4].image_extent.height, 1);
+
+    let undefined = ash::vk::ImageCreateInfo {
+        format: ash::vk::Format::UNDEFINED,
+        ..image_info
+    };
+    assert_eq!(
+        allocator.image_staging_size(&undefined).unwrap_err(),
+        ash::vk::Result::ERROR_FORMAT_NOT_SUPPORTED
+    );
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn excluded_memory_types() {
+    let harness = TestHarness::new();
+    let create_info = vk_mem::AllocatorCreateInfo::builder(
+        &harness.instance,
+        &harness.device,
+        harness.physical_device,
+    )
+    .flags(harness.allocator_flags)
+    .excluded_memory_types(1)
+    .build();
+    let allocator = unsafe { vk_mem::Allocator::new(&create_info).unwrap() };
+
+    let allocation_info = vk_mem::AllocationCreateInfo::default();
+    // Fails if memory type 0 is the only one, which is fine as well.
+    if let Ok(memory_type_index) = allocator.find_memory_type_index(!0, &allocation_info) {
+        assert_ne!(memory_type_index, 0);
+    }
+
+    let only_excluded = vk_mem::AllocationCreateInfo {
+        memory_type_bits: 1,
+        ..Default::default()
+    };
+    assert_eq!(
+        allocator.find_memory_type_index(!0, &only_excluded),
+        Err(ash::vk::Result::ERROR_FEATURE_NOT_PRESENT)
+    );
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn effective_block_size() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_properties = allocator.get_memory_properties().unwrap();
+    for memory_type in 0..memory_properties.memory_type_count {
+        let block_size = allocator.effective_block_size(memory_type).unwrap();
+        assert!(block_size > 0 && block_size <= 256 * 1024 * 1024);
+        assert_eq!(block_size % 32, 0);
+    }
+    assert_eq!(
+        allocator.effective_block_size(memory_properties.memory_type_count),
+        Err(ash::vk::Result::ERROR_VALIDATION_FAILED_EXT)
+    );
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn overlapping_persistent_maps() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let (buffer, allocation, info) = unsafe {
+        allocator
+            .create_buffer(
+                &ash::vk::BufferCreateInfo::builder()
+                    .size(256)
+                    .usage(ash::vk::BufferUsageFlags::UNIFORM_BUFFER)
+                    .build(),
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuToGpu),
+            )
+            .unwrap()
+    };
+
+    {
+        let first = unsafe { allocator.map_persistent(allocation) }.unwrap();
+        let second = unsafe { allocator.map_persistent(allocation) }.unwrap();
+        let (first, second) = unsafe { (first.as_slice::<u8>(), second.as_slice::<u8>()) };
+        assert_eq!(first.as_ptr(), second.as_ptr());
+        assert_eq!(first.as_ptr(), info.mapped_data() as *const u8);
+    }
+    // The persistent mapping of MAPPED allocations survives the guards.
+    let info = unsafe { allocator.get_allocation_info(allocation) }.unwrap();
+    assert!(!info.mapped_data().is_null());
+
+    unsafe {
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn map_guarded() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let (buffer, allocation, info) = unsafe {
+        allocator
+            .create_buffer(
+                &ash::vk::BufferCreateInfo::builder()
+                    .size(256)
+                    .usage(ash::vk::BufferUsageFlags::UNIFORM_BUFFER)
+                    .build(),
+                &vk_mem::AllocationCreateInfo {
+                    flags: vk_mem::AllocationCreateFlags::MAPPED,
+                    ..vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuToGpu)
+                },
+            )
+            .unwrap()
+    };
+
+    {
+        let first = unsafe { allocator.map_guarded(allocation) }.unwrap();
+        let second = unsafe { allocator.map_guarded(allocation) }.unwrap();
+        assert_eq!(*first, *second);
+        assert_eq!(*first, info.mapped_data());
+        assert_eq!(first.allocation(), allocation);
+    }
+    // The implicit mapping of MAPPED allocations survives the guards.
+    let info = unsafe { allocator.get_allocation_info(allocation) }.unwrap();
+    assert!(!info.mapped_data().is_null());
+
+    unsafe {
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn bind_buffer_memory_checked() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let queue_families = [0, 0];
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(1024)
+        .usage(ash::vk::BufferUsageFlags::VERTEX_BUFFER)
+        .sharing_mode(ash::vk::SharingMode::CONCURRENT)
+        .queue_family_indices(&queue_families)
+        .build();
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        ..Default::default()
+    };
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+    let (allocation, _) = unsafe {
+        allocator
+            .allocate_memory(&memory_requirements, &allocation_info)
+            .unwrap()
+    };
+
+    // A queue family listed twice is invalid for concurrent sharing.
+    let result = unsafe {
+        allocator.bind_buffer_memory_checked(ash::vk::Buffer::null(), allocation, &buffer_info)
+    };
+    assert_eq!(result, Err(ash::vk::Result::ERROR_VALIDATION_FAILED_EXT));
+
+    // Vulkan ignores the queue families of exclusive buffers, so they are not checked.
+    let invalid_queue_families = [u32::MAX; 2];
+    let exclusive = ash::vk::BufferCreateInfo {
+        sharing_mode: ash::vk::SharingMode::EXCLUSIVE,
+        queue_family_index_count: invalid_queue_families.len() as u32,
+        p_queue_family_indices: invalid_queue_families.as_ptr(),
+        ..buffer_info
+    };
+    unsafe {
+        allocator.free_memory(allocation);
+        let buffer = harness.device.create_buffer(&exclusive, None).unwrap();
+        let (allocation, _) = allocator
+            .allocate_memory_for_buffer(buffer, &allocation_info)
+            .unwrap();
+        allocator
+            .bind_buffer_memory_checked(buffer, allocation, &exclusive)
+            .unwrap();
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn replace_allocation_user_data() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+    let (allocation, _) = unsafe {
+        allocator
+            .allocate_memory(&memory_requirements, &Default::default())
+            .unwrap()
+    };
+
+    let (mut first_data, mut second_data) = (1u32, 2u32);
+    let first = &mut first_data as *mut u32 as *mut std::os::raw::c_void;
+    let second = &mut second_data as *mut u32 as *mut std::os::raw::c_void;
+    unsafe {
+        assert!(allocator
+            .replace_allocation_user_data(allocation, first)
+            .unwrap()
+            .is_null());
+        assert_eq!(
+            allocator
+                .replace_allocation_user_data(allocation, second)
+                .unwrap(),
+            first
+        );
+        allocator.free_memory(allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn pool_allocation_constraints() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index: 0,
+        flags: vk_mem::AllocatorPoolCreateFlags::LINEAR_ALGORITHM,
+        block_size: 1024 * 1024,
+        max_block_count: 2,
+        ..Default::default()
+    };
+    let pool = unsafe { allocator.create_pool(&pool_info).unwrap() };
+
+    let constraints = pool.allocation_constraints().unwrap();
+    assert!(!constraints.allows(vk_mem::AllocationCreateFlags::UPPER_ADDRESS));
+    assert!(constraints.allows(vk_mem::AllocationCreateFlags::MAPPED));
+    assert!(constraints.allows(vk_mem::AllocationCreateFlags::STRATEGY_MIN_MEMORY));
+
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: 1,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        flags: vk_mem::AllocationCreateFlags::UPPER_ADDRESS,
+        pool: Some(pool),
+        ..Default::default()
+    };
+    let result = unsafe { allocator.allocate_memory(&memory_requirements, &allocation_info) };
+    assert_eq!(result.unwrap_err(), vk_mem::Error::IncompatibleFlags);
+    assert_eq!(
+        constraints.check(vk_mem::AllocationCreateFlags::UPPER_ADDRESS),
+        Err(vk_mem::Error::IncompatibleFlags)
+    );
+
+    unsafe {
+        assert_eq!(
+            vk_mem::AllocatorPool::from_raw(pool.as_raw()).allocation_constraints(),
+            None
+        );
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn wrap_external_memory() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(64 * 1024)
+        .usage(ash::vk::BufferUsageFlags::STORAGE_BUFFER)
+        .build();
+
+    unsafe {
+        let buffer = harness.device.create_buffer(&buffer_info, None).unwrap();
+        let requirements = harness.device.get_buffer_memory_requirements(buffer);
+        let memory_info = ash::vk::MemoryAllocateInfo::builder()
+            .allocation_size(1024 * 1024)
+            .memory_type_index(requirements.memory_type_bits.trailing_zeros());
+        let memory = harness.device.allocate_memory(&memory_info, None).unwrap();
+
+        let mut block = allocator.wrap_external_memory(memory, 1024 * 1024);
+        assert_eq!(block.device_memory(), memory);
+        block.allocate(100, 1).unwrap();
+        let offset = block
+            .allocate(requirements.size, requirements.alignment)
+            .unwrap();
+        assert_eq!(offset % requirements.alignment, 0);
+        harness
+            .device
+            .bind_buffer_memory(buffer, block.device_memory(), offset)
+            .unwrap();
+
+        harness.device.destroy_buffer(buffer, None);
+        harness.device.free_memory(memory, None);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn peak_usage() {
+    let harness = TestHarness::new();
+    let create_info = vk_mem::AllocatorCreateInfo::builder(
+        &harness.instance,
+        &harness.device,
+        harness.physical_device,
+    )
+    .flags(harness.allocator_flags)
+    .vulkan_api_version(harness.api_version)
+    .track_peak_usage(true)
+    .build();
+    let allocator = unsafe { vk_mem::Allocator::new(&create_info).unwrap() };
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        required_flags: ash::vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        ..Default::default()
+    };
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(64 * 1024)
+        .usage(ash::vk::BufferUsageFlags::STORAGE_BUFFER)
+        .build();
+    assert_eq!(allocator.peak_usage(), 0);
+
+    unsafe {
+        let (first, first_allocation, first_info) = allocator
+            .create_buffer(&buffer_info, &allocation_info)
+            .unwrap();
+        let (second, second_allocation, second_info) = allocator
+            .create_buffer(&buffer_info, &allocation_info)
+            .unwrap();
+        let both = first_info.device_size() + second_info.device_size();
+        assert_eq!(allocator.peak_usage(), both);
+
+        allocator.destroy_buffer(second, second_allocation);
+        assert_eq!(allocator.peak_usage(), both);
+        allocator.reset_peak();
+        assert_eq!(allocator.peak_usage(), first_info.device_size());
+
+        allocator.destroy_buffer(first, first_allocation);
+        allocator.reset_peak();
+        assert_eq!(allocator.peak_usage(), 0);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn clone_pool_config() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index: 0,
+        flags: vk_mem::AllocatorPoolCreateFlags::LINEAR_ALGORITHM,
+        block_size: 1024 * 1024,
+        max_block_count: 2,
+        ..Default::default()
+    };
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        let config = allocator.clone_pool_config(pool).unwrap();
+        assert_eq!(config.memory_type_index, pool_info.memory_type_index);
+        assert_eq!(config.flags, pool_info.flags);
+        assert_eq!(config.block_size, pool_info.block_size);
+        assert_eq!(config.max_block_count, pool_info.max_block_count);
+
+        let mirror = allocator.create_pool(&config).unwrap();
+        assert_eq!(
+            allocator.get_pool_stats(mirror).unwrap().size,
+            allocator.get_pool_stats(pool).unwrap().size
+        );
+
+        allocator.destroy_pool(pool);
+        assert_eq!(
+            allocator.clone_pool_config(pool).unwrap_err(),
+            ash::vk::Result::ERROR_VALIDATION_FAILED_EXT
+        );
+        allocator.destroy_pool(mirror);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn fragmentation() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index: 0,
+        block_size: 1024 * 1024,
+        max_block_count: 1,
+        ..Default::default()
+    };
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 64 * 1024,
+        alignment: 256,
+        memory_type_bits: 1,
+    };
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        assert_eq!(allocator.pool_fragmentation(pool).unwrap(), 0.0);
+
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            ..Default::default()
+        };
+        let allocations: Vec<_> = (0..4)
+            .map(|_| {
+                allocator
+                    .allocate_memory(&memory_requirements, &allocation_info)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        assert_eq!(allocator.pool_fragmentation(pool).unwrap(), 0.0);
+
+        // A hole of 64 KiB next to the 768 KiB left at the end of the block.
+        allocator.free_memory(allocations[1]);
+        let fragmentation = allocator.pool_fragmentation(pool).unwrap();
+        assert!((fragmentation - 1.0 / 13.0).abs() < 1e-6);
+        assert!(allocator.fragmentation().unwrap() > 0.0);
+
+        allocator.free_memory(allocations[0]);
+        allocator.free_memory_pages(&allocations[2..]);
+        assert_eq!(allocator.pool_fragmentation(pool).unwrap(), 0.0);
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn align_flush_range() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let atom_size = allocator
+        .get_physical_device_properties()
+        .unwrap()
+        .limits
+        .non_coherent_atom_size;
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 4096,
+        alignment: 256,
+        memory_type_bits: u32::MAX,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::CpuToGpu,
+        ..Default::default()
+    };
+
+    unsafe {
+        let (allocation, _) = allocator
+            .allocate_memory(&memory_requirements, &allocation_info)
+            .unwrap();
+        assert_eq!(
+            allocator
+                .align_flush_range(allocation, atom_size + atom_size / 2, 1)
+                .unwrap(),
+            (atom_size, atom_size)
+        );
+        assert_eq!(
+            allocator
+                .align_flush_range(allocation, 0, ash::vk::WHOLE_SIZE)
+                .unwrap(),
+            (0, 4096)
+        );
+        let offset = 4000 / atom_size * atom_size;
+        assert_eq!(
+            allocator.align_flush_range(allocation, 4000, 1000).unwrap(),
+            (offset, 4096 - offset)
+        );
+        allocator.free_memory(allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "used after destroy_allocator")]
+fn use_after_destroy_allocator() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    unsafe { allocator.destroy_allocator() };
+    let _ = allocator.calculate_stats();
+}
+
+#[test]
+fn create_buffer_simple() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    unsafe {
+        let (buffer, allocation, allocation_info) = allocator
+            .create_buffer_simple(
+                16 * 1024,
+                ash::vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk_mem::MemoryUsage::CpuToGpu,
+            )
+            .unwrap();
+        assert!(allocation_info.device_size() >= 16 * 1024);
+        assert!(!allocation_info.mapped_data().is_null());
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+#[cfg(feature = "allocation_index")]
+fn allocations_in_block() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index: 0,
+        block_size: 1024 * 1024,
+        max_block_count: 1,
+        ..Default::default()
+    };
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: 1,
+    };
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            ..Default::default()
+        };
+        let mut allocations: Vec<_> = (0..3)
+            .map(|_| {
+                allocator
+                    .allocate_memory(&memory_requirements, &allocation_info)
+                    .unwrap()
+            })
+            .collect();
+        let memory = allocations[0].1.device_memory();
+
+        let mut in_block = allocator.allocations_in_block(memory);
+        in_block.sort();
+        let mut expected: Vec<_> = allocations
+            .iter()
+            .map(|(allocation, _)| *allocation)
+            .collect();
+        expected.sort();
+        assert_eq!(in_block, expected);
+
+        let (freed, _) = allocations.remove(1);
+        allocator.free_memory(freed);
+        assert_eq!(allocator.allocations_in_block(memory).len(), 2);
+        assert!(!allocator.allocations_in_block(memory).contains(&freed));
+
+        for (allocation, _) in allocations {
+            allocator.free_memory(allocation);
+        }
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn raw_handles() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index: 0,
+        block_size: 1024 * 1024,
+        ..Default::default()
+    };
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: 1,
+    };
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        let raw_pool = pool.as_raw();
+        assert_eq!(vk_mem::AllocatorPool::from_raw(raw_pool), pool);
+
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(vk_mem::AllocatorPool::from_raw(raw_pool)),
+            ..Default::default()
+        };
+        let (allocation, info) = allocator
+            .allocate_memory(&memory_requirements, &allocation_info)
+            .unwrap();
+        let allocation = vk_mem::Allocation::from_raw(allocation.as_raw());
+        assert_eq!(
+            allocator
+                .get_allocation_info(allocation)
+                .unwrap()
+                .device_memory(),
+            info.device_memory()
+        );
+
+        allocator.free_memory(allocation);
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn allocate_buffer_blocking() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(48 * 1024)
+        .usage(ash::vk::BufferUsageFlags::TRANSFER_SRC)
+        .build();
+    let memory_type_index = unsafe {
+        allocator
+            .find_memory_type_index_for_buffer_info(
+                &buffer_info,
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+            )
+            .unwrap()
+    };
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        block_size: 64 * 1024,
+        max_block_count: 1,
+        ..Default::default()
+    };
+    let timeout = std::time::Duration::from_millis(20);
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            ..Default::default()
+        };
+        let (buffer, allocation, _) = allocator
+            .allocate_buffer_blocking(&buffer_info, &allocation_info, timeout)
+            .unwrap();
+
+        // The pool is full, so the second buffer only fits once the first one is destroyed.
+        let start = std::time::Instant::now();
+        let result = allocator.allocate_buffer_blocking(&buffer_info, &allocation_info, timeout);
+        assert_eq!(
+            result.unwrap_err(),
+            vk_mem::Error::Vulkan(ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+        );
+        assert!(start.elapsed() >= timeout);
+
+        allocator.destroy_buffer(buffer, allocation);
+        let (buffer, allocation, _) = allocator
+            .allocate_buffer_blocking(&buffer_info, &allocation_info, timeout)
+            .unwrap();
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+#[cfg(feature = "heavy_assert")]
+fn heavy_assert_stress() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let allocation_info = vk_mem::AllocationCreateInfo::default();
+
+    // Allocate and free in a pseudo-random pattern, so that VMA's heavy assertions validate
+    // blocks with many free ranges being split and merged.
+    let mut seed: u32 = 0x1234_5678;
+    let mut next = move || {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        seed >> 8
+    };
+    let mut allocations = Vec::new();
+    unsafe {
+        for _ in 0..2000 {
+            if allocations.len() < 64 && next() % 3 != 0 {
+                let memory_requirements = ash::vk::MemoryRequirements {
+                    size: 256 * (1 + u64::from(next() % 64)),
+                    alignment: 256,
+                    memory_type_bits: 1,
+                };
+                let (allocation, _) = allocator
+                    .allocate_memory(&memory_requirements, &allocation_info)
+                    .unwrap();
+                allocations.push(allocation);
+            } else if !allocations.is_empty() {
+                let index = next() as usize % allocations.len();
+                allocator.free_memory(allocations.swap_remove(index));
+            }
+        }
+        allocator.free_memory_pages(&allocations);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn allocated_bytes_per_heap() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_properties = allocator.get_memory_properties().unwrap();
+    let heaps = allocator.allocated_bytes_per_heap().unwrap();
+    assert_eq!(heaps.len(), memory_properties.memory_heap_count as usize);
+    assert!(heaps.iter().all(|&bytes| bytes == 0));
+
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: 1,
+    };
+    let (allocation, info) = unsafe {
+        allocator
+            .allocate_memory(&memory_requirements, &Default::default())
+            .unwrap()
+    };
+    let heap = memory_properties.memory_types[info.memory_type() as usize].heap_index;
+    let heaps = allocator.allocated_bytes_per_heap().unwrap();
+    let stats = allocator.calculate_stats().unwrap();
+    assert!(heaps[heap as usize] >= 1024);
+    assert_eq!(
+        heaps.iter().sum::<u64>(),
+        stats.total.used_bytes + stats.total.unused_bytes
+    );
+
+    unsafe {
+        allocator.free_memory(allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn allocate_callback() {
+    let harness = TestHarness::new();
+    let mut allocator = harness.create_allocator();
+    let allocated = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = allocated.clone();
+    allocator.set_allocate_callback(Some(Box::new(move |allocation, info, name| {
+        recorded
+            .lock()
+            .unwrap()
+            .push((allocation, info.device_size(), name.map(str::to_owned)));
+    })));
+
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: 1,
+    };
+    let name = std::ffi::CString::new("vertex buffer").unwrap();
+    let named_info = vk_mem::AllocationCreateInfo {
+        flags: vk_mem::AllocationCreateFlags::USER_DATA_COPY_STRING,
+        user_data: Some(name.as_ptr() as *mut _),
+        ..Default::default()
+    };
+
+    unsafe {
+        let (named, _) = allocator
+            .allocate_memory(&memory_requirements, &named_info)
+            .unwrap();
+        let (unnamed, _) = allocator
+            .allocate_memory(&memory_requirements, &Default::default())
+            .unwrap();
+        assert_eq!(
+            *allocated.lock().unwrap(),
+            [
+                (named, 1024, Some("vertex buffer".to_owned())),
+                (unnamed, 1024, None)
+            ]
+        );
+
+        allocator.free_memory(named);
+        allocator.free_memory(unnamed);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn create_pool_validates_memory_type() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_properties = allocator.get_memory_properties().unwrap();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index: memory_properties.memory_type_count,
+        ..Default::default()
+    };
+    unsafe {
+        assert_eq!(
+            allocator.create_pool(&pool_info).unwrap_err(),
+            vk_mem::Error::InvalidMemoryType
+        );
+        let device_coherent = memory_properties.memory_types
+            [..memory_properties.memory_type_count as usize]
+            .iter()
+            .position(|memory_type| {
+                memory_type
+                    .property_flags
+                    .contains(ash::vk::MemoryPropertyFlags::DEVICE_COHERENT_AMD)
+            });
+        if let Some(index) = device_coherent {
+            let pool_info = vk_mem::AllocatorPoolCreateInfo {
+                memory_type_index: index as u32,
+                ..Default::default()
+            };
+            assert_eq!(
+                allocator.create_pool(&pool_info).unwrap_err(),
+                vk_mem::Error::Vulkan(ash::vk::Result::ERROR_FEATURE_NOT_PRESENT)
+            );
+        }
+        assert!(allocator.pools().is_empty());
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn buffer_image_granularity() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let properties = unsafe {
+        harness
+            .instance
+            .get_physical_device_properties(harness.physical_device)
+    };
+    assert_eq!(
+        allocator.buffer_image_granularity().unwrap(),
+        properties.limits.buffer_image_granularity
+    );
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn cmd_copy_allocation_rejects_oversized_copies() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: 1,
+    };
+    unsafe {
+        let (src, _) = allocator
+            .allocate_memory(&memory_requirements, &Default::default())
+            .unwrap();
+        let (dst, _) = allocator
+            .allocate_memory(&memory_requirements, &Default::default())
+            .unwrap();
+        let result = allocator.cmd_copy_allocation(
+            ash::vk::CommandBuffer::null(),
+            src,
+            ash::vk::Buffer::null(),
+            dst,
+            ash::vk::Buffer::null(),
+            2048,
+        );
+        assert_eq!(result.unwrap_err(), vk_mem::Error::InvalidSize);
+        allocator.free_memory(src);
+        allocator.free_memory(dst);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn cmd_copy_allocation() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let data: Vec<u8> = (0..1024u32).map(|i| (i * 3) as u8).collect();
+    // A buffer spanning the whole block, through which both allocations are copied.
+    let block_buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(64 * 1024)
+        .usage(ash::vk::BufferUsageFlags::TRANSFER_SRC | ash::vk::BufferUsageFlags::TRANSFER_DST)
+        .build();
+    let memory_type_index = unsafe {
+        allocator
+            .find_memory_type_index_for_buffer_info(
+                &block_buffer_info,
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuOnly),
+            )
+            .unwrap()
+    };
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        block_size: 64 * 1024,
+        max_block_count: 1,
+        ..Default::default()
+    };
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: data.len() as ash::vk::DeviceSize,
+        alignment: 256,
+        memory_type_bits: 1 << memory_type_index,
+    };
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            ..Default::default()
+        };
+        let (src, src_info) = allocator
+            .allocate_memory(&memory_requirements, &allocation_info)
+            .unwrap();
+        let (dst, dst_info) = allocator
+            .allocate_memory(&memory_requirements, &allocation_info)
+            .unwrap();
+        assert_eq!(src_info.device_memory(), dst_info.device_memory());
+        assert_ne!(src_info.device_offset(), dst_info.device_offset());
+        allocator.map_memory_for_write(src).unwrap()[..data.len()].copy_from_slice(&data);
+
+        let block_buffer = harness
+            .device
+            .create_buffer(&block_buffer_info, None)
+            .unwrap();
+        harness
+            .device
+            .bind_buffer_memory(block_buffer, src_info.device_memory(), 0)
+            .unwrap();
+        harness.submit_and_wait(|command_buffer| {
+            allocator
+                .cmd_copy_allocation(
+                    command_buffer,
+                    src,
+                    block_buffer,
+                    dst,
+                    block_buffer,
+                    data.len() as ash::vk::DeviceSize,
+                )
+                .unwrap();
+        });
+        assert_eq!(
+            &allocator.map_memory_for_read(dst).unwrap()[..data.len()],
+            &data[..]
+        );
+
+        harness.device.destroy_buffer(block_buffer, None);
+        allocator.free_memory(src);
+        allocator.free_memory(dst);
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn allocation_size_histogram() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let buckets = [256, 4096];
+    assert_eq!(
+        allocator.allocation_size_histogram(&buckets).unwrap(),
+        [0, 0, 0]
+    );
+    assert_eq!(
+        allocator
+            .allocation_size_histogram(&[4096, 256])
+            .unwrap_err(),
+        ash::vk::Result::ERROR_VALIDATION_FAILED_EXT
+    );
+
+    let allocations: Vec<_> = [256, 256, 1024, 64 * 1024]
+        .iter()
+        .map(|&size| unsafe {
+            let memory_requirements = ash::vk::MemoryRequirements {
+                size,
+                alignment: 256,
+                memory_type_bits: 1,
+            };
+            allocator
+                .allocate_memory(&memory_requirements, &Default::default())
+                .unwrap()
+                .0
+        })
+        .collect();
+    let dedicated_info = vk_mem::AllocationCreateInfo {
+        flags: vk_mem::AllocationCreateFlags::DEDICATED_MEMORY,
+        ..Default::default()
+    };
+    let (dedicated, _) = unsafe {
+        allocator
+            .allocate_memory(
+                &ash::vk::MemoryRequirements {
+                    size: 2048,
+                    alignment: 256,
+                    memory_type_bits: 1,
+                },
+                &dedicated_info,
+            )
+            .unwrap()
+    };
+    assert_eq!(
+        allocator.allocation_size_histogram(&buckets).unwrap(),
+        [2, 2, 1]
+    );
+
+    unsafe {
+        allocator.free_memory(dedicated);
+        allocator.free_memory_pages(&allocations);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn create_buffer_with_data() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let data: Vec<u8> = (0..4096u32).map(|i| (i * 5) as u8).collect();
+    let size = data.len() as ash::vk::DeviceSize;
+
+    unsafe {
+        let (readback_buffer, readback_allocation, _) = allocator
+            .create_buffer(
+                &ash::vk::BufferCreateInfo::builder()
+                    .size(size)
+                    .usage(ash::vk::BufferUsageFlags::TRANSFER_DST),
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuToCpu),
+            )
+            .unwrap();
+
+        let mut uploaded = None;
+        harness.submit_and_wait(|command_buffer| {
+            let (buffer, allocation, staging) = allocator
+                .create_buffer_with_data(
+                    command_buffer,
+                    ash::vk::BufferUsageFlags::TRANSFER_SRC,
+                    &data,
+                )
+                .unwrap();
+            let barrier = ash::vk::MemoryBarrier::builder()
+                .src_access_mask(ash::vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(ash::vk::AccessFlags::TRANSFER_READ);
+            harness.device.cmd_pipeline_barrier(
+                command_buffer,
+                ash::vk::PipelineStageFlags::TRANSFER,
+                ash::vk::PipelineStageFlags::TRANSFER,
+                ash::vk::DependencyFlags::empty(),
+                &[barrier.build()],
+                &[],
+                &[],
+            );
+            harness.device.cmd_copy_buffer(
+                command_buffer,
+                buffer,
+                readback_buffer,
+                &[ash::vk::BufferCopy {
+                    src_offset: 0,
+                    dst_offset: 0,
+                    size,
+                }],
+            );
+            uploaded = Some((buffer, allocation, staging));
+        });
+        let (buffer, allocation, staging) = uploaded.unwrap();
+        assert_eq!(
+            &allocator.map_memory_for_read(readback_allocation).unwrap()[..data.len()],
+            &data[..]
+        );
+
+        allocator.destroy_buffer(staging.buffer, staging.allocation);
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_buffer(readback_buffer, readback_allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn create_buffer_with_data_rejects_empty_data() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    unsafe {
+        let result = allocator.create_buffer_with_data(
+            ash::vk::CommandBuffer::null(),
+            ash::vk::BufferUsageFlags::VERTEX_BUFFER,
+            &[],
+        );
+        assert_eq!(result.unwrap_err(), vk_mem::Error::InvalidSize);
+        assert_eq!(
+            allocator.calculate_stats().unwrap().total.allocation_count,
+            0
+        );
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn heap_size_limits_length() {
+    let harness = TestHarness::new();
+    let heap_count = unsafe {
+        harness
+            .instance
+            .get_physical_device_memory_properties(harness.physical_device)
+            .memory_heap_count as usize
+    };
+    let limits = vec![ash::vk::WHOLE_SIZE; heap_count + 1];
+    let create_info = vk_mem::AllocatorCreateInfo::builder(
+        &harness.instance,
+        &harness.device,
+        harness.physical_device,
+    )
+    .flags(harness.allocator_flags)
+    .heap_size_limits(&limits)
+    .build();
+    assert_eq!(
+        unsafe { vk_mem::Allocator::new(&create_info) }.err(),
+        Some(vk_mem::Error::HeapLimitsLengthMismatch)
+    );
+
+    let create_info = vk_mem::AllocatorCreateInfo::builder(
+        &harness.instance,
+        &harness.device,
+        harness.physical_device,
+    )
+    .flags(harness.allocator_flags)
+    .heap_size_limits(&limits[..heap_count])
+    .build();
+    let allocator = unsafe { vk_mem::Allocator::new(&create_info).unwrap() };
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn calculate_statistics() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let requirements = ash::vk::MemoryRequirements {
+        size: 16 * 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        let (allocation, allocation_info) = allocator
+            .allocate_memory(&requirements, &vk_mem::AllocationCreateInfo::default())
+            .unwrap();
+
+        let stats = allocator.calculate_stats().unwrap();
+        let statistics = allocator.calculate_statistics().unwrap();
+        assert_eq!(statistics.memory_type.len(), stats.memory_type.len());
+        assert_eq!(statistics.memory_heap.len(), stats.memory_heap.len());
+        assert_eq!(statistics.total.statistics.allocation_count, 1);
+        assert_eq!(
+            statistics.total.statistics.block_bytes,
+            stats.total.used_bytes + stats.total.unused_bytes
+        );
+        let memory_type = &statistics.memory_type[allocation_info.memory_type() as usize];
+        assert_eq!(memory_type.statistics.allocation_bytes, 16 * 1024);
+        assert_eq!(memory_type.allocation_size_min, 16 * 1024);
+        assert_eq!(memory_type.allocation_size_max, 16 * 1024);
+
+        allocator.free_memory(allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn create_buffer_with_allocate_next() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(16 * 1024)
+        .usage(ash::vk::BufferUsageFlags::STORAGE_BUFFER)
+        .sharing_mode(ash::vk::SharingMode::EXCLUSIVE);
+    let allocation_info = vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly);
+
+    unsafe {
+        let (buffer, allocation, info) = allocator
+            .create_buffer_with_allocate_next(&buffer_info, &allocation_info, std::ptr::null_mut())
+            .unwrap();
+        assert!(info.device_size() >= 16 * 1024);
+        // The pool backing the allocation is internal to it.
+        assert!(allocator.pools().is_empty());
+        assert_eq!(allocator.calculate_stats().unwrap().total.block_count, 1);
+
+        allocator.destroy_buffer(buffer, allocation);
+        assert_eq!(allocator.calculate_stats().unwrap().total.block_count, 0);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn supports_usage() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    assert!(allocator.supports_usage(vk_mem::MemoryUsage::GpuOnly));
+    assert!(allocator.supports_usage(vk_mem::MemoryUsage::CpuOnly));
+
+    let memory_properties = allocator.get_memory_properties().unwrap();
+    let has_lazily_allocated = memory_properties.memory_types
+        [..memory_properties.memory_type_count as usize]
+        .iter()
+        .any(|memory_type| {
+            memory_type
+                .property_flags
+                .contains(ash::vk::MemoryPropertyFlags::LAZILY_ALLOCATED)
+        });
+    assert_eq!(
+        allocator.supports_usage(vk_mem::MemoryUsage::GpuLazilyAllocated),
+        has_lazily_allocated
+    );
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn growable_pool() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        block_size: 64 * 1024,
+        ..Default::default()
+    };
+    let requirements = |size| ash::vk::MemoryRequirements {
+        size,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo::default();
+
+    unsafe {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<vk_mem::GrowablePool<'_>>();
+
+        let empty = vk_mem::AllocatorPoolCreateInfo {
+            block_size: 0,
+            ..pool_info.clone()
+        };
+        assert_eq!(
+            vk_mem::GrowablePool::new(&allocator, &empty).unwrap_err(),
+            vk_mem::Error::InvalidSize
+        );
+
+        let pool = vk_mem::GrowablePool::new(&allocator, &pool_info).unwrap();
+        let mut allocations = Vec::new();
+        for _ in 0..3 {
+            let (allocation, _) = pool
+                .alloc(&requirements(48 * 1024), &allocation_info)
+                .unwrap();
+            allocations.push(allocation);
+        }
+        assert_eq!(pool.pools().len(), 2);
+        assert_eq!(pool.current_block_size(), 128 * 1024);
+
+        let (large, _) = pool
+            .alloc(&requirements(200 * 1024), &allocation_info)
+            .unwrap();
+        allocations.push(large);
+        assert_eq!(pool.pools().len(), 3);
+        assert_eq!(pool.current_block_size(), 256 * 1024);
+
+        let stats = pool.stats().unwrap();
+        assert_eq!(stats.allocation_count, 4);
+        assert_eq!(stats.block_count, 3);
+        assert_eq!(stats.size, (64 + 128 + 256) * 1024);
+
+        // A pool too large for its heap is destroyed again when allocating from it fails.
+        let memory_properties = allocator.get_memory_properties().unwrap();
+        let heap_index = memory_properties.memory_types[memory_type_index as usize].heap_index;
+        let heap_size = memory_properties.memory_heaps[heap_index as usize].size;
+        assert!(pool
+            .alloc(&requirements(2 * heap_size), &allocation_info)
+            .is_err());
+        assert_eq!(pool.pools().len(), 3);
+        assert_eq!(allocator.pools().len(), 3);
+
+        for allocation in allocations {
+            pool.free(allocation);
+        }
+        drop(pool);
+        assert!(allocator.pools().is_empty());
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn is_bound() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(16 * 1024)
+        .usage(ash::vk::BufferUsageFlags::VERTEX_BUFFER)
+        .sharing_mode(ash::vk::SharingMode::EXCLUSIVE);
+    let allocation_info = vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly);
+
+    unsafe {
+        let (buffer, allocation, _) = allocator
+            .create_buffer(&buffer_info, &allocation_info)
+            .unwrap();
+        assert!(allocator.is_bound(allocation));
+        allocator.destroy_buffer(buffer, allocation);
+
+        let (allocation, _, _) = allocator
+            .allocate_memory_for_buffer_info(&buffer_info, &allocation_info)
+            .unwrap();
+        assert!(!allocator.is_bound(allocation));
+        let buffer = harness.device.create_buffer(&buffer_info, None).unwrap();
+        allocator.bind_buffer_memory(buffer, allocation).unwrap();
+        assert!(allocator.is_bound(allocation));
+        allocator.destroy_buffer(buffer, allocation);
+
+        // General purpose allocations are not tracked.
+        let (allocation, _) = allocator
+            .allocate_memory(
+                &ash::vk::MemoryRequirements {
+                    size: 1024,
+                    alignment: 256,
+                    memory_type_bits: !0,
+                },
+                &allocation_info,
+            )
+            .unwrap();
+        assert!(allocator.is_bound(allocation));
+        allocator.free_memory(allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "never bound")]
+fn free_unbound_allocation() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(16 * 1024)
+        .usage(ash::vk::BufferUsageFlags::VERTEX_BUFFER)
+        .sharing_mode(ash::vk::SharingMode::EXCLUSIVE);
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        flags: vk_mem::AllocationCreateFlags::CREATE_DONT_BIND,
+        ..vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly)
+    };
+    unsafe {
+        let (buffer, allocation, _) = allocator
+            .create_buffer(&buffer_info, &allocation_info)
+            .unwrap();
+        assert!(!allocator.is_bound(allocation));
+        allocator.destroy_buffer(buffer, allocation);
+    }
+}
+
+#[test]
+fn free_null_allocations() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let requirements = ash::vk::MemoryRequirements {
+        size: 4096,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        allocator.free_memory(vk_mem::Allocation::null());
+
+        let (allocation, _) = allocator
+            .allocate_memory(&requirements, &vk_mem::AllocationCreateInfo::default())
+            .unwrap();
+        assert!(!allocation.is_null());
+        allocator.free_memory_pages(&[
+            vk_mem::Allocation::null(),
+            allocation,
+            vk_mem::Allocation::null(),
+        ]);
+        assert_eq!(
+            allocator.calculate_stats().unwrap().total.allocation_count,
+            0
+        );
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn ranked_memory_types() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let ranked = allocator
+        .ranked_memory_types(vk_mem::MemoryUsage::CpuToGpu, 0)
+        .unwrap();
+    let best = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuToGpu),
+        )
+        .unwrap();
+    assert_eq!(ranked[0], best);
+    let mut unique = ranked.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), ranked.len());
+
+    let memory_properties = allocator.get_memory_properties().unwrap();
+    for &memory_type_index in &ranked {
+        assert!(memory_properties.memory_types[memory_type_index as usize]
+            .property_flags
+            .contains(ash::vk::MemoryPropertyFlags::HOST_VISIBLE));
+    }
+
+    let without_best = allocator.ranked_memory_types(vk_mem::MemoryUsage::CpuToGpu, !(1 << best));
+    match without_best {
+        Ok(rest) => assert_eq!(rest, ranked[1..]),
+        Err(err) => {
+            assert_eq!(err, ash::vk::Result::ERROR_FEATURE_NOT_PRESENT);
+            assert_eq!(ranked.len(), 1);
+        }
+    }
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn find_memory_type() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let allocation_info = vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuOnly);
+    let choice = allocator.find_memory_type(!0, &allocation_info).unwrap();
+    assert_eq!(
+        choice.index,
+        allocator
+            .find_memory_type_index(!0, &allocation_info)
+            .unwrap()
+    );
+    assert!(choice.is_host_visible());
+
+    let memory_type =
+        allocator.get_memory_properties().unwrap().memory_types[choice.index as usize];
+    assert_eq!(choice.properties, memory_type.property_flags);
+    assert_eq!(choice.heap, memory_type.heap_index);
+    assert_eq!(
+        allocator.predict_heap(&allocation_info, !0).unwrap(),
+        choice.heap
+    );
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn prefer_dedicated_follows_driver_hint() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(256)
+        .usage(ash::vk::BufferUsageFlags::UNIFORM_BUFFER)
+        .build();
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        prefer_dedicated: true,
+        ..Default::default()
+    };
+    unsafe {
+        let (buffer, allocation, _) = allocator
+            .create_buffer(&buffer_info, &allocation_info)
+            .unwrap();
+        let dedicated_memory = allocator
+            .get_allocation_info2(allocation)
+            .unwrap()
+            .dedicated_memory;
+
+        if harness
+            .allocator_flags
+            .contains(vk_mem::AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION)
+        {
+            let loader = ash::extensions::khr::GetMemoryRequirements2::new(
+                &harness.instance,
+                &harness.device,
+            );
+            let mut dedicated_requirements = ash::vk::MemoryDedicatedRequirements::default();
+            {
+                let mut memory_requirements =
+                    ash::vk::MemoryRequirements2::builder().push_next(&mut dedicated_requirements);
+                let requirements_info =
+                    ash::vk::BufferMemoryRequirementsInfo2::builder().buffer(buffer);
+                loader
+                    .get_buffer_memory_requirements2(&requirements_info, &mut memory_requirements);
+            }
+            let hinted = dedicated_requirements.prefers_dedicated_allocation == ash::vk::TRUE
+                || dedicated_requirements.requires_dedicated_allocation == ash::vk::TRUE;
+            assert_eq!(dedicated_memory, hinted);
+        } else {
+            // Without the extension, the hint can't be queried and a dedicated block is preferred.
+            assert!(dedicated_memory);
+        }
+
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn prefer_dedicated_without_resource() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 256,
+        alignment: 16,
+        memory_type_bits: !0,
+    };
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        prefer_dedicated: true,
+        ..Default::default()
+    };
+    unsafe {
+        let (allocation, _) = allocator
+            .allocate_memory(&memory_requirements, &allocation_info)
+            .unwrap();
+        assert!(
+            allocator
+                .get_allocation_info2(allocation)
+                .unwrap()
+                .dedicated_memory
+        );
+        allocator.free_memory(allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn allocation_failure_callback() {
+    let harness = TestHarness::new();
+    let mut allocator = harness.create_allocator();
+    let failures = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = failures.clone();
+    allocator.set_allocation_failure_callback(Some(Box::new(
+        move |_, allocation_info, memory_requirements, err| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((allocation_info.flags, memory_requirements.size, err));
+        },
+    )));
+
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: 0,
+    };
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(64 * 1024)
+        .usage(ash::vk::BufferUsageFlags::VERTEX_BUFFER)
+        .build();
+    let never_allocate = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::GpuOnly,
+        flags: vk_mem::AllocationCreateFlags::NEVER_ALLOCATE,
+        ..Default::default()
+    };
+    unsafe {
+        let err = allocator
+            .allocate_memory(&memory_requirements, &Default::default())
+            .unwrap_err();
+        assert_eq!(
+            *failures.lock().unwrap(),
+            [(vk_mem::AllocationCreateFlags::NONE, 1024, err)]
+        );
+
+        // There is no block to sub-allocate from yet.
+        let err = allocator
+            .create_buffer(&buffer_info, &never_allocate)
+            .unwrap_err();
+        let failures = failures.lock().unwrap();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[1].0, vk_mem::AllocationCreateFlags::NEVER_ALLOCATE);
+        assert!(failures[1].1 >= 64 * 1024);
+        assert_eq!(failures[1].2, err);
+        drop(failures);
+
+        allocator.set_allocation_failure_callback(None);
+        allocator
+            .allocate_memory(&memory_requirements, &Default::default())
+            .unwrap_err();
+        allocator.destroy_allocator();
+    }
+    assert_eq!(failures.lock().unwrap().len(), 2);
+}
+
+#[test]
+#[cfg(feature = "detect_corruption")]
+fn check_allocation_corruption() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let allocation_info = vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuOnly);
+    let memory_type_index = allocator
+        .find_memory_type_index(!0, &allocation_info)
+        .unwrap();
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: 1 << memory_type_index,
+    };
+    unsafe {
+        let pool = allocator
+            .create_pool(&vk_mem::AllocatorPoolCreateInfo {
+                memory_type_index,
+                ..Default::default()
+            })
+            .unwrap();
+        let (allocation, info) = allocator
+            .allocate_memory(
+                &memory_requirements,
+                &vk_mem::AllocationCreateInfo {
+                    pool: Some(pool),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        // Only host-coherent memory types are checked; the margins of a fresh allocation are intact.
+        let coherent = allocator.get_memory_properties().unwrap().memory_types
+            [info.memory_type() as usize]
+            .property_flags
+            .contains(ash::vk::MemoryPropertyFlags::HOST_COHERENT);
+        let expected = if coherent {
+            Ok(())
+        } else {
+            Err(ash::vk::Result::ERROR_FEATURE_NOT_PRESENT)
+        };
+        assert_eq!(allocator.check_allocation_corruption(allocation), expected);
+        assert_eq!(allocator.check_pool_corruption(pool), expected);
+
+        allocator.free_memory(allocation);
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn auto_detect_version() {
+    let harness = TestHarness::new();
+    // The harness instance is created for Vulkan 1.0, which bounds the detected version.
+    let create_info = vk_mem::AllocatorCreateInfo {
+        flags: harness.allocator_flags,
+        physical_device: harness.physical_device,
+        device: harness.device.clone(),
+        instance: harness.instance.clone(),
+        preferred_large_heap_block_size: 0,
+        allocation_callbacks: None,
+        frame_in_use_count: 0,
+        heap_size_limits: None,
+        vulkan_api_version: ash::vk::API_VERSION_1_0,
+        auto_detect_version: true,
+        excluded_memory_types: 0,
+        track_peak_usage: false,
+    };
+    let built = vk_mem::AllocatorCreateInfo::builder(
+        &harness.instance,
+        &harness.device,
+        harness.physical_device,
+    )
+    .flags(harness.allocator_flags)
+    .vulkan_api_version(ash::vk::API_VERSION_1_0)
+    .auto_detect_version(true)
+    .build();
+    assert!(built.auto_detect_version);
+    assert_eq!(built.vulkan_api_version, create_info.vulkan_api_version);
+
+    for create_info in &[create_info, built] {
+        let allocator = unsafe { vk_mem::Allocator::new(create_info).unwrap() };
+        unsafe {
+            let (buffer, allocation, _) = allocator
+                .create_buffer_simple(
+                    1024,
+                    ash::vk::BufferUsageFlags::UNIFORM_BUFFER,
+                    vk_mem::MemoryUsage::CpuToGpu,
+                )
+                .unwrap();
+            allocator.destroy_buffer(buffer, allocation);
+            allocator.destroy_allocator();
+        }
+    }
+}
+
+#[test]
+fn ring_pool_wraps() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+    unsafe {
+        let mut ring = vk_mem::RingPool::new(&allocator, memory_type_index, 4096, 1).unwrap();
+        let first = ring.alloc(1024, 256).unwrap();
+        ring.alloc(1024, 256).unwrap();
+        ring.reset_frame().unwrap();
+        let second = ring.alloc(1024, 256).unwrap();
+        assert!(second > first);
+        ring.alloc(1024, 256).unwrap();
+        ring.reset_frame().unwrap();
+
+        // The allocations of the first frame are no longer in flight, so the ring wraps around.
+        let wrapped = ring.alloc(1024, 256).unwrap();
+        assert!(wrapped < second);
+
+        // The allocations of the previous frame are still in flight and can't be reclaimed.
+        let full = (0..4)
+            .map(|_| ring.alloc(1024, 256))
+            .find(|result| result.is_err());
+        assert_eq!(full, Some(Err(ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)));
+        drop(ring);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn create_info_builders() {
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::CpuToGpu,
+        flags: vk_mem::AllocationCreateFlags::MAPPED,
+        required_flags: ash::vk::MemoryPropertyFlags::HOST_VISIBLE,
+        preferred_flags: ash::vk::MemoryPropertyFlags::HOST_COHERENT,
+        memory_type_bits: 0b101,
+        priority: 0.5,
+        prefer_dedicated: true,
+        strategy: Some(vk_mem::AllocationStrategy::MinMemory),
+        zero_initialize: true,
+        ..Default::default()
+    };
+    let built = vk_mem::AllocationCreateInfo::builder()
+        .usage(vk_mem::MemoryUsage::CpuToGpu)
+        .flags(vk_mem::AllocationCreateFlags::MAPPED)
+        .required_flags(ash::vk::MemoryPropertyFlags::HOST_VISIBLE)
+        .preferred_flags(ash::vk::MemoryPropertyFlags::HOST_COHERENT)
+        .memory_type_bits(0b101)
+        .priority(0.5)
+        .prefer_dedicated(true)
+        .strategy(vk_mem::AllocationStrategy::MinMemory)
+        .zero_initialize(true)
+        .build()
+        .unwrap();
+    assert_eq!(format!("{:?}", built), format!("{:?}", allocation_info));
+    assert_eq!(
+        vk_mem::AllocationCreateInfo::builder()
+            .flags(
+                vk_mem::AllocationCreateFlags::MAPPED
+                    | vk_mem::AllocationCreateFlags::CAN_BECOME_LOST
+            )
+            .build()
+            .unwrap_err(),
+        vk_mem::Error::InvalidFlags
+    );
+
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index: 1,
+        flags: vk_mem::AllocatorPoolCreateFlags::LINEAR_ALGORITHM,
+        block_size: 1024 * 1024,
+        min_block_count: 1,
+        max_block_count: 2,
+        frame_in_use_count: 3,
+        priority: 0.25,
+        min_allocation_alignment: 64,
+        ..Default::default()
+    };
+    let built = vk_mem::AllocatorPoolCreateInfo::builder()
+        .memory_type_index(1)
+        .flags(vk_mem::AllocatorPoolCreateFlags::LINEAR_ALGORITHM)
+        .block_size(1024 * 1024)
+        .min_block_count(1)
+        .max_block_count(2)
+        .frame_in_use_count(3)
+        .priority(0.25)
+        .min_allocation_alignment(64)
+        .build()
+        .unwrap();
+    assert_eq!(format!("{:?}", built), format!("{:?}", pool_info));
+    for invalid in &[
+        vk_mem::AllocatorPoolCreateInfo::builder()
+            .min_block_count(3)
+            .max_block_count(2),
+        vk_mem::AllocatorPoolCreateInfo::builder().min_allocation_alignment(48),
+        vk_mem::AllocatorPoolCreateInfo::builder().priority(2.0),
+    ] {
+        assert_eq!(
+            invalid.clone().build().unwrap_err(),
+            vk_mem::Error::InvalidPoolCreateInfo
+        );
+    }
+}
+
+#[test]
+fn mapped_as_rejects_misaligned_mapping() {
+    #[repr(C, align(4096))]
+    #[derive(Debug, Clone, Copy)]
+    struct Page([u8; 4096]);
+
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuOnly),
+        )
+        .unwrap();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        flags: vk_mem::AllocatorPoolCreateFlags::LINEAR_ALGORITHM,
+        block_size: 64 * 1024,
+        max_block_count: 1,
+        ..Default::default()
+    };
+    let requirements = ash::vk::MemoryRequirements {
+        size: 4096 + 256,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            ..Default::default()
+        };
+        let mut allocations = Vec::new();
+        let mut misaligned = 0;
+        for _ in 0..4 {
+            let (allocation, _) = allocator
+                .allocate_memory(&requirements, &allocation_info)
+                .unwrap();
+            allocations.push(allocation);
+
+            let mapped_data = allocator.map_memory(allocation).unwrap();
+            allocator.unmap_memory(allocation);
+            if mapped_data as usize % 4096 == 0 {
+                drop(allocator.mapped_as::<Page>(allocation).unwrap());
+            } else {
+                misaligned += 1;
+                assert_eq!(
+                    allocator.mapped_as::<Page>(allocation).unwrap_err(),
+                    ash::vk::Result::ERROR_VALIDATION_FAILED_EXT
+                );
+            }
+            // The failed `mapped_as` must not leave the allocation mapped.
+            assert!(allocator
+                .get_allocation_info(allocation)
+                .unwrap()
+                .mapped_data()
+                .is_null());
+        }
+        assert!(misaligned > 0);
+
+        for allocation in allocations {
+            allocator.free_memory(allocation);
+        }
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn migrate_allocation() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let data: Vec<u8> = (0..4096u32).map(|i| i as u8).collect();
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(data.len() as ash::vk::DeviceSize)
+        .usage(ash::vk::BufferUsageFlags::TRANSFER_SRC | ash::vk::BufferUsageFlags::TRANSFER_DST)
+        .build();
+    let memory_type_index = unsafe {
+        allocator
+            .find_memory_type_index_for_buffer_info(
+                &buffer_info,
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuOnly),
+            )
+            .unwrap()
+    };
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        block_size: 64 * 1024,
+        ..Default::default()
+    };
+
+    unsafe {
+        let source_pool = allocator.create_pool(&pool_info).unwrap();
+        let target_pool = allocator.create_pool(&pool_info).unwrap();
+        let (buffer, allocation, _) = allocator
+            .create_buffer(
+                &buffer_info,
+                &vk_mem::AllocationCreateInfo {
+                    pool: Some(source_pool),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let mapped_data = allocator.map_memory(allocation).unwrap();
+        std::ptr::copy_nonoverlapping(data.as_ptr(), mapped_data, data.len());
+        allocator
+            .flush_allocation(allocation, 0, data.len())
+            .unwrap();
+        allocator.unmap_memory(allocation);
+
+        let command_buffer = harness.allocate_command_buffer();
+        let (new_buffer, new_allocation, _) = allocator
+            .migrate_allocation(
+                harness.queue,
+                command_buffer,
+                buffer,
+                allocation,
+                &buffer_info,
+                target_pool,
+            )
+            .unwrap();
+        harness
+            .device
+            .free_command_buffers(harness.command_pool, &[command_buffer]);
+
+        // The source allocation is freed once the copy has finished.
+        assert_eq!(
+            allocator
+                .get_pool_stats(source_pool)
+                .unwrap()
+                .allocationCount,
+            0
+        );
+        assert_eq!(
+            allocator
+                .get_pool_stats(target_pool)
+                .unwrap()
+                .allocationCount,
+            1
+        );
+
+        let mapped_data = allocator.map_memory(new_allocation).unwrap();
+        allocator
+            .invalidate_allocation(new_allocation, 0, data.len())
+            .unwrap();
+        assert_eq!(
+            std::slice::from_raw_parts(mapped_data, data.len()),
+            &data[..]
+        );
+        allocator.unmap_memory(new_allocation);
+
+        allocator.destroy_buffer(new_buffer, new_allocation);
+        allocator.destroy_pool(target_pool);
+        allocator.destroy_pool(source_pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn fixed_size_pool() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+
+    unsafe {
+        for &(element_size, alignment, elements_per_block, error) in &[
+            (0, 16, 16, vk_mem::Error::InvalidSize),
+            (256, 0, 16, vk_mem::Error::InvalidSize),
+            (256, 48, 16, vk_mem::Error::InvalidSize),
+            (256, 16, 0, vk_mem::Error::InvalidSize),
+            (!0, 16, 16, vk_mem::Error::SizeOverflow),
+            (256, 1 << 32, usize::MAX, vk_mem::Error::SizeOverflow),
+        ] {
+            assert_eq!(
+                vk_mem::FixedSizePool::new(
+                    &allocator,
+                    memory_type_index,
+                    element_size,
+                    alignment,
+                    elements_per_block,
+                )
+                .unwrap_err(),
+                error
+            );
+        }
+
+        let mut pool =
+            vk_mem::FixedSizePool::new(&allocator, memory_type_index, 100, 64, 16).unwrap();
+        assert_eq!(pool.element_size(), 100);
+        let allocations: Vec<_> = (0..32).map(|_| pool.alloc().unwrap()).collect();
+        let mut offsets: Vec<_> = allocations
+            .iter()
+            .map(|&allocation| {
+                let info = allocator.get_allocation_info(allocation).unwrap();
+                (info.device_memory(), info.device_offset())
+            })
+            .collect();
+        assert!(offsets.iter().all(|&(_, offset)| offset % 64 == 0));
+        offsets.sort();
+        offsets.dedup();
+        assert_eq!(offsets.len(), allocations.len());
+
+        // Freed allocations are reused without allocating from VMA.
+        let freed = allocations[5];
+        pool.free(freed);
+        assert_eq!(pool.alloc().unwrap(), freed);
+
+        for allocation in allocations {
+            pool.free(allocation);
+        }
+        pool.trim();
+        assert_eq!(
+            allocator
+                .get_pool_stats(pool.pool())
+                .unwrap()
+                .allocationCount,
+            0
+        );
+        drop(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn flush_coherent_allocation() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_properties = allocator.get_memory_properties().unwrap();
+    let requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        for &usage in &[
+            vk_mem::MemoryUsage::CpuOnly,
+            vk_mem::MemoryUsage::CpuToGpu,
+            vk_mem::MemoryUsage::GpuToCpu,
+        ] {
+            let (allocation, allocation_info) = allocator
+                .allocate_memory(
+                    &requirements,
+                    &vk_mem::AllocationCreateInfo::for_usage(usage),
+                )
+                .unwrap();
+            let flags = memory_properties.memory_types[allocation_info.memory_type() as usize]
+                .property_flags;
+            assert_eq!(
+                allocation_info.is_host_coherent(&allocator),
+                flags.contains(ash::vk::MemoryPropertyFlags::HOST_COHERENT)
+            );
+
+            let mut mapping = allocator.map_memory_for_write(allocation).unwrap();
+            mapping[0] = 1;
+            mapping.flush().unwrap();
+            drop(mapping);
+            allocator
+                .flush_allocation(allocation, 0, ash::vk::WHOLE_SIZE as usize)
+                .unwrap();
+            allocator
+                .invalidate_allocation(allocation, 0, ash::vk::WHOLE_SIZE as usize)
+                .unwrap();
+            assert_eq!(allocator.map_memory_for_read(allocation).unwrap()[0], 1);
+
+            allocator.free_memory(allocation);
+        }
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn create_image_pool() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let optimal_info = ash::vk::ImageCreateInfo::builder()
+        .image_type(ash::vk::ImageType::TYPE_2D)
+        .format(ash::vk::Format::R8G8B8A8_UNORM)
+        .extent(ash::vk::Extent3D {
+            width: 16,
+            height: 16,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(ash::vk::SampleCountFlags::TYPE_1)
+        .tiling(ash::vk::ImageTiling::OPTIMAL)
+        .usage(ash::vk::ImageUsageFlags::SAMPLED)
+        .build();
+    let linear_info = ash::vk::ImageCreateInfo {
+        tiling: ash::vk::ImageTiling::LINEAR,
+        usage: ash::vk::ImageUsageFlags::TRANSFER_SRC,
+        initial_layout: ash::vk::ImageLayout::PREINITIALIZED,
+        ..optimal_info
+    };
+
+    unsafe {
+        for &(image_info, usage) in &[
+            (optimal_info, vk_mem::MemoryUsage::GpuOnly),
+            (linear_info, vk_mem::MemoryUsage::CpuOnly),
+        ] {
+            let pool = allocator.create_image_pool(&image_info, usage).unwrap();
+            let allocation_info = vk_mem::AllocationCreateInfo {
+                pool: Some(pool),
+                ..Default::default()
+            };
+            let images: Vec<_> = (0..2)
+                .map(|_| {
+                    allocator
+                        .create_image(&image_info, &allocation_info)
+                        .unwrap()
+                })
+                .collect();
+            let memory_type = images[0].2.memory_type();
+            assert_eq!(
+                memory_type,
+                allocator
+                    .find_memory_type_index_for_image_info(
+                        image_info,
+                        &vk_mem::AllocationCreateInfo::for_usage(usage),
+                    )
+                    .unwrap()
+            );
+            assert_eq!(images[1].2.memory_type(), memory_type);
+            if usage == vk_mem::MemoryUsage::CpuOnly {
+                assert!(images[0].2.is_host_visible(&allocator));
+            }
+            assert_eq!(allocator.get_pool_stats(pool).unwrap().allocationCount, 2);
+
+            for (image, allocation, _) in images {
+                allocator.destroy_image(image, allocation);
+            }
+            allocator.destroy_pool(pool);
+        }
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn largest_free_range() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+    let block_size = 64 * 1024;
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        block_size,
+        min_block_count: 1,
+        ..Default::default()
+    };
+    let requirements = ash::vk::MemoryRequirements {
+        size: 16 * 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        assert_eq!(
+            allocator.pool_largest_free_range(pool).unwrap(),
+            block_size as ash::vk::DeviceSize
+        );
+
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            ..Default::default()
+        };
+        let (allocation, _) = allocator
+            .allocate_memory(&requirements, &allocation_info)
+            .unwrap();
+        let largest = allocator.pool_largest_free_range(pool).unwrap();
+        assert!(largest >= 32 * 1024);
+        assert!(largest <= (block_size - 16 * 1024) as ash::vk::DeviceSize);
+        // Statistics of the memory type include the blocks of the pool.
+        assert!(
+            allocator
+                .default_largest_free_range(memory_type_index)
+                .unwrap()
+                >= largest
+        );
+
+        allocator.free_memory(allocation);
+        assert_eq!(
+            allocator.pool_largest_free_range(pool).unwrap(),
+            block_size as ash::vk::DeviceSize
+        );
+        assert_eq!(
+            allocator.default_largest_free_range(ash::vk::MAX_MEMORY_TYPES as u32),
+            Err(ash::vk::Result::ERROR_VALIDATION_FAILED_EXT)
+        );
+
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn device_group_pool() {
+    let harness = TestHarness::new();
+    // `ash::vk::MemoryAllocateFlagsInfo` needs `VK_KHR_device_group` on a Vulkan 1.0 device.
+    if !harness.device_group {
+        return;
+    }
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        block_size: 64 * 1024,
+        ..Default::default()
+    };
+    let requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        // A device that is not created from a device group is a group of one device.
+        let pool = vk_mem::DeviceGroupPool::new(
+            &allocator,
+            &pool_info,
+            ash::vk::MemoryAllocateFlags::empty(),
+            Some(1),
+        )
+        .unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool.pool()),
+            ..Default::default()
+        };
+        let (allocation, info) = allocator
+            .allocate_memory(&requirements, &allocation_info)
+            .unwrap();
+        assert_ne!(info.device_memory(), ash::vk::DeviceMemory::null());
+        assert_eq!(info.memory_type(), memory_type_index);
+        assert_eq!(allocator.get_pool_stats(pool.pool()).unwrap().blockCount, 1);
+
+        allocator.free_memory(allocation);
+        drop(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn mappable_allocation_across_threads() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        let (allocation, _) = allocator
+            .allocate_memory(
+                &requirements,
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuOnly),
+            )
+            .unwrap();
+        let mut mappable = vk_mem::MappableAllocation::new(&allocator, allocation);
+        mappable.map_for_write().unwrap()[..4].copy_from_slice(&[1, 2, 3, 4]);
+
+        let mappable = std::thread::scope(|scope| {
+            scope
+                .spawn(move || {
+                    assert_eq!(&mappable.map_for_read().unwrap()[..4], &[1, 2, 3, 4]);
+                    mappable
+                })
+                .join()
+                .unwrap()
+        });
+
+        allocator.free_memory(mappable.allocation());
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn reallocate_with_priority() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let data: Vec<u8> = (0..4096u32).map(|i| (i * 7) as u8).collect();
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(data.len() as ash::vk::DeviceSize)
+        .usage(ash::vk::BufferUsageFlags::TRANSFER_SRC | ash::vk::BufferUsageFlags::TRANSFER_DST)
+        .build();
+    let allocation_info = vk_mem::AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::CpuOnly,
+        priority: 0.25,
+        ..Default::default()
+    };
+
+    unsafe {
+        let (buffer, allocation, _) = allocator
+            .create_buffer(&buffer_info, &allocation_info)
+            .unwrap();
+        allocator.map_memory_for_write(allocation).unwrap()[..data.len()].copy_from_slice(&data);
+
+        let mut reallocated = None;
+        harness.submit_and_wait(|command_buffer| {
+            reallocated = Some(
+                allocator
+                    .reallocate_with_priority(
+                        command_buffer,
+                        buffer,
+                        &buffer_info,
+                        &allocation_info,
+                        1.0,
+                    )
+                    .unwrap(),
+            );
+        });
+        let (new_buffer, new_allocation, new_info) = reallocated.unwrap();
+        assert_ne!(new_allocation, allocation);
+        assert!(new_info.is_host_visible(&allocator));
+        assert_eq!(
+            &allocator.map_memory_for_read(new_allocation).unwrap()[..data.len()],
+            &data[..]
+        );
+
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_buffer(new_buffer, new_allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn get_memory_properties2() {
+    let harness = TestHarness::with_api_version(ash::vk::API_VERSION_1_1);
+    // `vkGetPhysicalDeviceMemoryProperties2` is core in Vulkan 1.1, and
+    // `ash::vk::PhysicalDeviceMemoryBudgetPropertiesEXT` needs `VK_EXT_memory_budget`.
+    if harness.api_version < ash::vk::API_VERSION_1_1
+        || !harness
+            .allocator_flags
+            .contains(vk_mem::AllocatorCreateFlags::EXT_MEMORY_BUDGET)
+    {
+        return;
+    }
+    let allocator = harness.create_allocator();
+    let memory_properties = allocator.get_memory_properties().unwrap();
+
+    unsafe {
+        let mut budget = ash::vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let properties = allocator.get_memory_properties2(&mut budget).unwrap();
+        assert_eq!(
+            properties.p_next,
+            &mut budget as *mut ash::vk::PhysicalDeviceMemoryBudgetPropertiesEXT as *mut _
+        );
+        let properties = properties.memory_properties;
+        assert_eq!(
+            properties.memory_type_count,
+            memory_properties.memory_type_count
+        );
+        assert_eq!(
+            properties.memory_heap_count,
+            memory_properties.memory_heap_count
+        );
+        for heap in 0..properties.memory_heap_count as usize {
+            assert_eq!(
+                properties.memory_heaps[heap].size,
+                memory_properties.memory_heaps[heap].size
+            );
+            assert!(budget.heap_budget[heap] > 0);
+        }
+
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn flush_range_overflow() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+    // The end of this range is 2^64, one past the largest `ash::vk::DeviceSize`.
+    let half = usize::MAX / 2 + 1;
+
+    unsafe {
+        let (allocation, _) = allocator
+            .allocate_memory(
+                &requirements,
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuToGpu),
+            )
+            .unwrap();
+        assert_eq!(
+            allocator.flush_allocation(allocation, half, half),
+            Err(vk_mem::Error::SizeOverflow)
+        );
+        assert_eq!(
+            allocator.invalidate_allocation(allocation, half, half),
+            Err(vk_mem::Error::SizeOverflow)
+        );
+        assert_eq!(
+            ash::vk::Result::from(vk_mem::Error::SizeOverflow),
+            ash::vk::Result::ERROR_OUT_OF_HOST_MEMORY
+        );
+        // `ash::vk::WHOLE_SIZE` is not an overflow, it covers the rest of the allocation.
+        allocator
+            .flush_allocation(allocation, 0, ash::vk::WHOLE_SIZE as usize)
+            .unwrap();
+
+        allocator.free_memory(allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn sub_buffer_arena() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(1024)
+        .usage(ash::vk::BufferUsageFlags::UNIFORM_BUFFER)
+        .build();
+    let allocation_info = vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuToGpu);
+
+    unsafe {
+        let mut arena =
+            vk_mem::SubBufferArena::new(&allocator, &buffer_info, &allocation_info).unwrap();
+        let base = allocator
+            .get_allocation_info(arena.allocation())
+            .unwrap()
+            .mapped_data();
+        assert!(!base.is_null());
+
+        let mut ranges = Vec::new();
+        for _ in 0..4 {
+            let (offset, data) = arena.suballoc(100, 64).unwrap();
+            assert_eq!(offset % 64, 0);
+            assert_eq!(data, base.add(offset as usize));
+            std::ptr::write_bytes(data, offset as u8, 100);
+            ranges.push(offset);
+        }
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            assert!(pair[0] + 100 <= pair[1]);
+        }
+        for &offset in &ranges {
+            assert_eq!(*base.add(offset as usize + 99), offset as u8);
+        }
+
+        // The remaining space is too small for the whole buffer size.
+        assert_eq!(
+            arena.suballoc(1024, 1).unwrap_err(),
+            ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY
+        );
+        for offset in ranges {
+            arena.free(offset).unwrap();
+        }
+        assert_eq!(arena.suballoc(1024, 1).unwrap().0, 0);
+
+        drop(arena);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn zero_initialize() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuOnly),
+        )
+        .unwrap();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        flags: vk_mem::AllocatorPoolCreateFlags::LINEAR_ALGORITHM,
+        block_size: 64 * 1024,
+        max_block_count: 1,
+        ..Default::default()
+    };
+    let requirements = ash::vk::MemoryRequirements {
+        size: 16 * 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            ..Default::default()
+        };
+
+        // Leave garbage behind in the range the next allocation of the linear pool reuses.
+        let (dirty, dirty_info) = allocator
+            .allocate_memory(&requirements, &allocation_info)
+            .unwrap();
+        allocator.map_memory_for_write(dirty).unwrap().fill(0xAB);
+        allocator.free_memory(dirty);
+
+        let (allocation, info) = allocator
+            .allocate_memory(
+                &requirements,
+                &vk_mem::AllocationCreateInfo {
+                    zero_initialize: true,
+                    ..allocation_info
+                },
+            )
+            .unwrap();
+        assert_eq!(info.device_memory(), dirty_info.device_memory());
+        assert_eq!(info.device_offset(), dirty_info.device_offset());
+        assert!(allocator
+            .map_memory_for_read(allocation)
+            .unwrap()
+            .iter()
+            .all(|&byte| byte == 0));
+
+        allocator.free_memory(allocation);
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn try_defragment_step() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuOnly),
+        )
+        .unwrap();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        block_size: 32 * 1024,
+        ..Default::default()
+    };
+    let requirements = ash::vk::MemoryRequirements {
+        size: 4 * 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            ..Default::default()
+        };
+        let mut allocations: Vec<_> = (0..12)
+            .map(|_| {
+                allocator
+                    .allocate_memory(&requirements, &allocation_info)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        assert_eq!(allocator.get_pool_stats(pool).unwrap().blockCount, 2);
+        // Free the first half, leaving both blocks partially used.
+        for allocation in allocations.drain(..6) {
+            allocator.free_memory(allocation);
+        }
+
+        let mut allocations_moved = 0;
+        for _ in 0..allocations.len() {
+            let stats = allocator.try_defragment_step(pool, 4 * 1024, 1).unwrap();
+            assert!(stats.allocations_moved <= 1);
+            assert!(stats.bytes_moved <= 4 * 1024);
+            if stats.allocations_moved == 0 {
+                break;
+            }
+            allocations_moved += stats.allocations_moved;
+        }
+        assert!(allocations_moved > 0);
+        assert_eq!(allocator.get_pool_stats(pool).unwrap().blockCount, 1);
+
+        for allocation in allocations {
+            allocator.free_memory(allocation);
+        }
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn create_image_dedicated() {
+    let image_info = ash::vk::ImageCreateInfo::builder()
+        .image_type(ash::vk::ImageType::TYPE_2D)
+        .format(ash::vk::Format::R8G8B8A8_UNORM)
+        .extent(ash::vk::Extent3D {
+            width: 64,
+            height: 64,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(ash::vk::SampleCountFlags::TYPE_1)
+        .tiling(ash::vk::ImageTiling::OPTIMAL)
+        .usage(ash::vk::ImageUsageFlags::SAMPLED)
+        .build();
+    let allocation_info = vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly);
+
+    // Vulkan 1.0 without `AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION` can't query the hints.
+    let harness = TestHarness::new();
+    let create_info = vk_mem::AllocatorCreateInfo::builder(
+        &harness.instance,
+        &harness.device,
+        harness.physical_device,
+    )
+    .build();
+    unsafe {
+        let allocator = vk_mem::Allocator::new(&create_info).unwrap();
+        assert_eq!(
+            allocator
+                .create_image_dedicated(&image_info, &allocation_info)
+                .unwrap_err(),
+            vk_mem::Error::Vulkan(ash::vk::Result::ERROR_EXTENSION_NOT_PRESENT)
+        );
+        allocator.destroy_allocator();
+    }
+    drop(harness);
+
+    let harness = TestHarness::with_api_version(ash::vk::API_VERSION_1_1);
+    if harness.api_version < ash::vk::API_VERSION_1_1 {
+        return;
+    }
+    let create_info = vk_mem::AllocatorCreateInfo::builder(
+        &harness.instance,
+        &harness.device,
+        harness.physical_device,
+    )
+    .vulkan_api_version(harness.api_version)
+    .build();
+    unsafe {
+        let allocator = vk_mem::Allocator::new(&create_info).unwrap();
+        let (image, allocation, info) = allocator
+            .create_image_dedicated(&image_info, &allocation_info)
+            .unwrap();
+        assert!(allocator.is_bound(allocation));
+        assert_ne!(info.device_memory(), ash::vk::DeviceMemory::null());
+
+        allocator.destroy_image(image, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn refresh_mapped_pointers() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuOnly),
+        )
+        .unwrap();
+    let pool_info = vk_mem::AllocatorPoolCreateInfo {
+        memory_type_index,
+        block_size: 32 * 1024,
+        ..Default::default()
+    };
+    let requirements = ash::vk::MemoryRequirements {
+        size: 4 * 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        let pool = allocator.create_pool(&pool_info).unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            flags: vk_mem::AllocationCreateFlags::MAPPED,
+            ..Default::default()
+        };
+        let mut allocations: Vec<_> = (0..12)
+            .map(|_| {
+                allocator
+                    .allocate_memory(&requirements, &allocation_info)
+                    .unwrap()
+            })
+            .collect();
+        for (allocation, _) in allocations.drain(..6) {
+            allocator.free_memory(allocation);
+        }
+        let (unmapped, _) = allocator
+            .allocate_memory(
+                &requirements,
+                &vk_mem::AllocationCreateInfo {
+                    pool: Some(pool),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        for (index, (_, info)) in allocations.iter().enumerate() {
+            *info.mapped_data() = index as u8;
+        }
+
+        let mut handles: Vec<_> = allocations
+            .iter()
+            .map(|&(allocation, _)| allocation)
+            .collect();
+        let info = vk_mem::DefragmentationInfo2 {
+            allocations: &handles,
+            pools: None,
+            max_cpu_bytes_to_move: ash::vk::WHOLE_SIZE,
+            max_cpu_allocations_to_move: u32::MAX,
+            max_gpu_bytes_to_move: 0,
+            max_gpu_allocations_to_move: 0,
+            command_buffer: None,
+        };
+        let mut context = allocator.defragmentation_begin(&info).unwrap();
+        let (stats, _) = allocator.defragmentation_end(&mut context).unwrap();
+        assert!(stats.allocations_moved > 0);
+
+        handles.push(unmapped);
+        let pointers = allocator.refresh_mapped_pointers(&handles).unwrap();
+        assert_eq!(pointers.len(), handles.len());
+        assert!(pointers[handles.len() - 1].is_null());
+        let mut moved = 0;
+        for (index, (&pointer, (allocation, old_info))) in
+            pointers.iter().zip(&allocations).enumerate()
+        {
+            assert_eq!(
+                pointer,
+                allocator
+                    .get_allocation_info(*allocation)
+                    .unwrap()
+                    .mapped_data()
+            );
+            assert_eq!(*pointer, index as u8);
+            if pointer != old_info.mapped_data() {
+                moved += 1;
+            }
+        }
+        assert_eq!(moved, stats.allocations_moved);
+
+        for allocation in handles {
+            allocator.free_memory(allocation);
+        }
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn allocation_strategy_flags() {
+    use vk_mem::AllocationCreateFlags as Flags;
+    use vk_mem::AllocationStrategy as Strategy;
+
+    let strategies = [
+        (Strategy::BestFit, Flags::STRATEGY_BEST_FIT),
+        (Strategy::WorstFit, Flags::STRATEGY_WORST_FIT),
+        (Strategy::FirstFit, Flags::STRATEGY_FIRST_FIT),
+        (Strategy::MinMemory, Flags::STRATEGY_MIN_MEMORY),
+        (Strategy::MinTime, Flags::STRATEGY_MIN_TIME),
+        (
+            Strategy::MinFragmentation,
+            Flags::STRATEGY_MIN_FRAGMENTATION,
+        ),
+    ];
+    for &(strategy, flags) in strategies.iter() {
+        assert_eq!(strategy.flags(), flags);
+        assert!(Flags::STRATEGY_MASK.contains(strategy.flags()));
+        assert_eq!(strategy.flags().bits().count_ones(), 1);
+    }
+    assert_eq!(Strategy::MinMemory.flags(), Strategy::BestFit.flags());
+    assert_eq!(Strategy::MinTime.flags(), Strategy::FirstFit.flags());
+    assert_eq!(
+        Strategy::MinFragmentation.flags(),
+        Strategy::WorstFit.flags()
+    );
+
+    assert_eq!(
+        vk_mem::AllocationCreateInfo::builder()
+            .flags(Flags::STRATEGY_BEST_FIT)
+            .strategy(Strategy::FirstFit)
+            .build()
+            .unwrap_err(),
+        vk_mem::Error::InvalidFlags
+    );
+}
+
+#[test]
+fn export_memory_info() {
+    let harness = TestHarness::with_api_version(ash::vk::API_VERSION_1_1);
+    if harness.api_version < ash::vk::API_VERSION_1_1 {
+        // `ash::vk::ExportMemoryAllocateInfo` is core in Vulkan 1.1.
+        return;
+    }
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+    let requirements = ash::vk::MemoryRequirements {
+        size: 64 * 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    let export_info = |handle_types| {
+        let mut export_info = ash::vk::ExportMemoryAllocateInfo::builder()
+            .handle_types(handle_types)
+            .build();
+        let pool_info = vk_mem::AllocatorPoolCreateInfo {
+            memory_type_index,
+            memory_allocate_next: Some(&mut export_info as *mut _ as *mut _),
+            ..Default::default()
+        };
+        unsafe {
+            let pool = allocator.create_pool(&pool_info).unwrap();
+            let allocation = allocator.allocate_memory(
+                &requirements,
+                &vk_mem::AllocationCreateInfo {
+                    pool: Some(pool),
+                    ..Default::default()
+                },
+            );
+            let info = allocation.map(|(allocation, info)| {
+                let export_info = allocator.export_memory_info(allocation).unwrap();
+                assert_eq!(export_info.device_memory, info.device_memory());
+                assert_eq!(export_info.offset, info.device_offset());
+                assert_eq!(export_info.size, info.device_size());
+                allocator.free_memory(allocation);
+                export_info
+            });
+            allocator.destroy_pool(pool);
+            info
+        }
+    };
+
+    unsafe {
+        let (allocation, _) = allocator
+            .allocate_memory(
+                &requirements,
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+            )
+            .unwrap();
+        assert!(allocator
+            .export_memory_info(allocation)
+            .unwrap()
+            .handle_type
+            .is_empty());
+        allocator.free_memory(allocation);
+    }
+
+    // Pools chaining no handle types are not exportable.
+    let empty = export_info(ash::vk::ExternalMemoryHandleTypeFlags::empty()).unwrap();
+    assert!(empty.handle_type.is_empty());
+
+    #[cfg(unix)]
+    {
+        let opaque_fd = ash::vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD;
+        // Skipped where the memory type cannot be exported as a file descriptor.
+        if let Ok(exportable) = export_info(opaque_fd) {
+            assert_eq!(exportable.handle_type, opaque_fd);
+        }
+    }
+
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn create_packed_buffers() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let buffer_infos: Vec<_> = [100, 256, 17, 4096, 1]
+        .iter()
+        .zip(
+            [
+                ash::vk::BufferUsageFlags::UNIFORM_BUFFER,
+                ash::vk::BufferUsageFlags::STORAGE_BUFFER,
+                ash::vk::BufferUsageFlags::VERTEX_BUFFER,
+                ash::vk::BufferUsageFlags::UNIFORM_BUFFER,
+                ash::vk::BufferUsageFlags::STORAGE_BUFFER,
+            ]
+            .iter(),
+        )
+        .map(|(&size, &usage)| {
+            ash::vk::BufferCreateInfo::builder()
+                .size(size)
+                .usage(usage)
+                .build()
+        })
+        .collect();
+
+    unsafe {
+        let (allocation, buffers) = allocator
+            .create_packed_buffers(
+                &buffer_infos,
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuToGpu),
+            )
+            .unwrap();
+        assert_eq!(buffers.len(), buffer_infos.len());
+        let allocation_size = allocator
+            .get_allocation_info(allocation)
+            .unwrap()
+            .device_size();
+
+        let mut ranges: Vec<_> = buffers
+            .iter()
+            .map(|&(buffer, offset)| {
+                let requirements = harness.device.get_buffer_memory_requirements(buffer);
+                assert_eq!(offset % requirements.alignment, 0);
+                (offset, offset + requirements.size)
+            })
+            .collect();
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            assert!(pair[0].1 <= pair[1].0, "{:?} overlap", pair);
+        }
+        assert!(ranges.last().unwrap().1 <= allocation_size);
+
+        for &(buffer, _) in &buffers {
+            harness.device.destroy_buffer(buffer, None);
+        }
+        allocator.free_memory(allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn is_host_visible() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_properties = allocator.get_memory_properties().unwrap();
+    let requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        for &(usage, host_visible) in [
+            (vk_mem::MemoryUsage::CpuOnly, Some(true)),
+            (vk_mem::MemoryUsage::GpuOnly, None),
+        ]
+        .iter()
+        {
+            let (allocation, allocation_info) = allocator
+                .allocate_memory(
+                    &requirements,
+                    &vk_mem::AllocationCreateInfo::for_usage(usage),
+                )
+                .unwrap();
+            let flags = memory_properties.memory_types[allocation_info.memory_type() as usize]
+                .property_flags;
+            assert_eq!(
+                allocation_info.is_host_visible(&allocator),
+                flags.contains(ash::vk::MemoryPropertyFlags::HOST_VISIBLE)
+            );
+            // GpuOnly memory is host visible on integrated GPUs.
+            if let Some(host_visible) = host_visible {
+                assert_eq!(allocation_info.is_host_visible(&allocator), host_visible);
+            }
+            allocator.free_memory(allocation);
+        }
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn create_image_zero_extent() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let image_info = ash::vk::ImageCreateInfo::builder()
+        .image_type(ash::vk::ImageType::TYPE_2D)
+        .format(ash::vk::Format::R8G8B8A8_UNORM)
+        .extent(ash::vk::Extent3D {
+            width: 0,
+            height: 16,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(ash::vk::SampleCountFlags::TYPE_1)
+        .usage(ash::vk::ImageUsageFlags::SAMPLED)
+        .build();
+    let allocation_info = vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly);
+
+    unsafe {
+        assert_eq!(
+            allocator
+                .create_image(&image_info, &allocation_info)
+                .unwrap_err(),
+            vk_mem::Error::InvalidImageExtent
+        );
+        let no_layers = ash::vk::ImageCreateInfo {
+            extent: ash::vk::Extent3D {
+                width: 16,
+                height: 16,
+                depth: 1,
+            },
+            array_layers: 0,
+            ..image_info
+        };
+        assert_eq!(
+            allocator
+                .create_image(&no_layers, &allocation_info)
+                .unwrap_err(),
+            vk_mem::Error::InvalidImageExtent
+        );
+        assert_eq!(
+            allocator.find_memory_type_index_for_image_info(image_info, &allocation_info),
+            Err(ash::vk::Result::ERROR_VALIDATION_FAILED_EXT)
+        );
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn is_mappable() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        for &usage in [vk_mem::MemoryUsage::CpuOnly, vk_mem::MemoryUsage::GpuOnly].iter() {
+            let (allocation, allocation_info) = allocator
+                .allocate_memory(
+                    &requirements,
+                    &vk_mem::AllocationCreateInfo::for_usage(usage),
+                )
+                .unwrap();
+            let mappable = allocator.is_mappable(allocation).unwrap();
+            assert_eq!(mappable, allocation_info.is_host_visible(&allocator));
+            // Mapping memory that isn't host visible is invalid usage, so only the mappable
+            // case can be checked.
+            if mappable {
+                assert!(!allocator.map_memory(allocation).unwrap().is_null());
+                allocator.unmap_memory(allocation);
+            }
+            allocator.free_memory(allocation);
+        }
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn allocate_sparse_pages() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let image_info = ash::vk::ImageCreateInfo::builder()
+        .image_type(ash::vk::ImageType::TYPE_2D)
+        .format(ash::vk::Format::R8G8B8A8_UNORM)
+        .extent(ash::vk::Extent3D {
+            width: 512,
+            height: 512,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(ash::vk::SampleCountFlags::TYPE_1)
+        .tiling(ash::vk::ImageTiling::OPTIMAL)
+        .usage(ash::vk::ImageUsageFlags::SAMPLED);
+    let allocation_info = vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly);
+
+    unsafe {
+        // Images without sparse residency have no sparse memory requirements.
+        let image = harness.device.create_image(&image_info, None).unwrap();
+        assert_eq!(
+            allocator
+                .allocate_sparse_pages(image, 4, &allocation_info)
+                .err(),
+            Some(vk_mem::Error::Vulkan(
+                ash::vk::Result::ERROR_FEATURE_NOT_PRESENT
+            ))
+        );
+        harness.device.destroy_image(image, None);
+
+        if !harness.sparse_image_2d {
+            // The device doesn't support sparse 2D images.
+            allocator.destroy_allocator();
+            return;
+        }
+        let sparse_info = image_info.flags(
+            ash::vk::ImageCreateFlags::SPARSE_BINDING | ash::vk::ImageCreateFlags::SPARSE_RESIDENCY,
+        );
+        let image = harness.device.create_image(&sparse_info, None).unwrap();
+        let requirements = harness.device.get_image_memory_requirements(image);
+        let pages = allocator
+            .allocate_sparse_pages(image, 4, &allocation_info)
+            .unwrap();
+        assert_eq!(pages.len(), 4);
+        for (_, info) in &pages {
+            assert_eq!(info.device_size(), requirements.alignment);
+            assert_eq!(info.device_offset() % requirements.alignment, 0);
+            assert_ne!(requirements.memory_type_bits & (1 << info.memory_type()), 0);
+        }
+
+        let allocations: Vec<_> = pages.iter().map(|&(allocation, _)| allocation).collect();
+        allocator.free_memory_pages(&allocations);
+        harness.device.destroy_image(image, None);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn memory_range() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let buffer_info = ash::vk::BufferCreateInfo::builder()
+        .size(16 * 1024)
+        .usage(ash::vk::BufferUsageFlags::UNIFORM_BUFFER);
+
+    unsafe {
+        let (buffer, allocation, allocation_info) = allocator
+            .create_buffer(
+                &buffer_info,
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+            )
+            .unwrap();
+        let (device_memory, offset, size) = allocation_info.memory_range();
+        assert_eq!(device_memory, allocation_info.device_memory());
+        assert_ne!(device_memory, ash::vk::DeviceMemory::null());
+        assert_eq!(offset, allocation_info.device_offset());
+        assert_eq!(size, allocation_info.device_size());
+        assert!(size >= 16 * 1024);
+        assert_eq!(
+            allocator
+                .get_allocation_info(allocation)
+                .unwrap()
+                .memory_range(),
+            (device_memory, offset, size)
+        );
+        allocator.destroy_buffer(buffer, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn route_khr_functions() {
+    let harness = TestHarness::new();
+    let create_info = |flags| {
+        vk_mem::AllocatorCreateInfo::builder(
+            &harness.instance,
+            &harness.device,
+            harness.physical_device,
+        )
+        .flags(flags)
+        .vulkan_api_version(ash::vk::API_VERSION_1_0)
+        .build()
+    };
+
+    unsafe {
+        // The harness never enables `VK_KHR_bind_memory2`, so its functions can't be routed.
+        assert_eq!(
+            vk_mem::Allocator::new(&create_info(vk_mem::AllocatorCreateFlags::KHR_BIND_MEMORY2))
+                .err(),
+            Some(vk_mem::Error::Vulkan(
+                ash::vk::Result::ERROR_EXTENSION_NOT_PRESENT
+            ))
+        );
+
+        if !harness
+            .allocator_flags
+            .contains(vk_mem::AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION)
+        {
+            // `VK_KHR_dedicated_allocation` is not available.
+            return;
+        }
+        // VMA queries every buffer and image through the routed
+        // `vkGet*MemoryRequirements2KHR`, which would panic if left unloaded on Vulkan 1.0.
+        let allocator = vk_mem::Allocator::new(&create_info(
+            vk_mem::AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION,
+        ))
+        .unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly);
+        let buffer_info = ash::vk::BufferCreateInfo::builder()
+            .size(64 * 1024)
+            .usage(ash::vk::BufferUsageFlags::STORAGE_BUFFER);
+        let (buffer, allocation, _) = allocator
+            .create_buffer(&buffer_info, &allocation_info)
+            .unwrap();
+        allocator.destroy_buffer(buffer, allocation);
+
+        let image_info = ash::vk::ImageCreateInfo::builder()
+            .image_type(ash::vk::ImageType::TYPE_2D)
+            .format(ash::vk::Format::R8G8B8A8_UNORM)
+            .extent(ash::vk::Extent3D {
+                width: 64,
+                height: 64,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(ash::vk::SampleCountFlags::TYPE_1)
+            .tiling(ash::vk::ImageTiling::OPTIMAL)
+            .usage(ash::vk::ImageUsageFlags::SAMPLED);
+        let (image, allocation, _) = allocator
+            .create_image(&image_info, &allocation_info)
+            .unwrap();
+        allocator.destroy_image(image, allocation);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn thread_local_arena_reclaim() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: u32::MAX,
+    };
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            memory_requirements.memory_type_bits,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+    let arena = vk_mem::ThreadLocalArena::new(
+        &allocator,
+        &vk_mem::AllocatorPoolCreateInfo {
+            memory_type_index,
+            ..Default::default()
+        },
+    );
+
+    unsafe {
+        let main_pool = arena.for_current_thread().unwrap();
+        // Joining waits for the thread's thread-locals to be destroyed, so it has exited.
+        let exited_pool = std::thread::scope(|scope| {
+            scope
+                .spawn(|| arena.for_current_thread().unwrap())
+                .join()
+                .unwrap()
+        });
+        assert_ne!(exited_pool, main_pool);
+        assert_eq!(arena.thread_count(), 2);
+
+        // A new thread takes over the pool of the exited one.
+        let taken_over = std::thread::scope(|scope| {
+            scope
+                .spawn(|| arena.for_current_thread().unwrap())
+                .join()
+                .unwrap()
+        });
+        assert_eq!(taken_over, exited_pool);
+        assert_eq!(arena.thread_count(), 2);
+
+        // Pools with allocations left are kept.
+        let (allocation, _) = allocator
+            .allocate_memory(
+                &memory_requirements,
+                &vk_mem::AllocationCreateInfo {
+                    pool: Some(exited_pool),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(arena.reclaim().unwrap(), 0);
+        let stats = arena.stats().unwrap();
+        assert_eq!(stats.allocation_count, 1);
+        assert_eq!(stats.block_count, 1);
+
+        allocator.free_memory(allocation);
+        assert_eq!(arena.reclaim().unwrap(), 1);
+        assert_eq!(arena.thread_count(), 1);
+        assert_eq!(arena.for_current_thread().unwrap(), main_pool);
+        assert_eq!(arena.stats().unwrap(), vk_mem::PoolStats::default());
+    }
+
+    drop(arena);
+    unsafe { allocator.destroy_allocator() };
+}
+
+#[test]
+fn get_allocation_info2() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let requirements = ash::vk::MemoryRequirements {
+        size: 4 * 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+    let block_size = 1024 * 1024;
+
+    unsafe {
+        let pool = allocator
+            .create_pool(&vk_mem::AllocatorPoolCreateInfo {
+                memory_type_index,
+                block_size,
+                ..Default::default()
+            })
+            .unwrap();
+        let (pooled, _) = allocator
+            .allocate_memory(
+                &requirements,
+                &vk_mem::AllocationCreateInfo {
+                    pool: Some(pool),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let info = allocator.get_allocation_info2(pooled).unwrap();
+        assert_eq!(info.block_size, block_size as ash::vk::DeviceSize);
+        assert!(!info.dedicated_memory);
+        assert_eq!(info.allocation_info.device_size(), 4 * 1024);
+
+        let (dedicated, _) = allocator
+            .allocate_memory(
+                &requirements,
+                &vk_mem::AllocationCreateInfo {
+                    usage: vk_mem::MemoryUsage::GpuOnly,
+                    flags: vk_mem::AllocationCreateFlags::DEDICATED_MEMORY,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let info = allocator.get_allocation_info2(dedicated).unwrap();
+        assert_eq!(info.block_size, info.allocation_info.device_size());
+        assert_eq!(info.allocation_info.device_offset(), 0);
+        assert!(info.dedicated_memory);
+
+        allocator.free_memory(dedicated);
+        allocator.free_memory(pooled);
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn defragment_images() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let image_info = ash::vk::ImageCreateInfo::builder()
+        .image_type(ash::vk::ImageType::TYPE_2D)
+        .format(ash::vk::Format::R8G8B8A8_UNORM)
+        .extent(ash::vk::Extent3D {
+            width: 64,
+            height: 64,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(ash::vk::SampleCountFlags::TYPE_1)
+        .tiling(ash::vk::ImageTiling::OPTIMAL)
+        .usage(ash::vk::ImageUsageFlags::TRANSFER_SRC | ash::vk::ImageUsageFlags::TRANSFER_DST)
+        .build();
+    let color_range = ash::vk::ImageSubresourceRange {
+        aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+    let barrier = |command_buffer, images: &[ash::vk::Image], old_layout, new_layout| {
+        let barriers: Vec<_> = images
+            .iter()
+            .map(|&image| {
+                ash::vk::ImageMemoryBarrier::builder()
+                    .src_access_mask(ash::vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(
+                        ash::vk::AccessFlags::TRANSFER_READ | ash::vk::AccessFlags::TRANSFER_WRITE,
+                    )
+                    .old_layout(old_layout)
+                    .new_layout(new_layout)
+                    .src_queue_family_index(ash::vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(ash::vk::QUEUE_FAMILY_IGNORED)
+                    .image(image)
+                    .subresource_range(color_range)
+                    .build()
+            })
+            .collect();
+        unsafe {
+            harness.device.cmd_pipeline_barrier(
+                command_buffer,
+                ash::vk::PipelineStageFlags::TRANSFER,
+                ash::vk::PipelineStageFlags::TRANSFER,
+                ash::vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            )
+        };
+    };
+
+    unsafe {
+        let pool = allocator
+            .create_image_pool(&image_info, vk_mem::MemoryUsage::GpuOnly)
+            .unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            ..Default::default()
+        };
+        let mut images: Vec<_> = (0..8)
+            .map(|_| {
+                let (image, allocation, _) = allocator
+                    .create_image(&image_info, &allocation_info)
+                    .unwrap();
+                (allocation, image, image_info)
+            })
+            .collect();
+        // Free every other image, leaving holes to compact.
+        let mut index = 0;
+        images.retain(|&(allocation, image, _)| {
+            index += 1;
+            if index % 2 == 1 {
+                allocator.destroy_image(image, allocation);
+            }
+            index % 2 == 0
+        });
+
+        // Fill each image with its own color and leave it ready to be copied.
+        let colors: Vec<[f32; 4]> = (0..images.len())
+            .map(|index| [(index * 40) as f32 / 255.0, 1.0, 0.0, 1.0])
+            .collect();
+        let old_images: Vec<_> = images.iter().map(|entry| entry.1).collect();
+        harness.submit_and_wait(|command_buffer| {
+            barrier(
+                command_buffer,
+                &old_images,
+                ash::vk::ImageLayout::UNDEFINED,
+                ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            );
+            for (&image, color) in old_images.iter().zip(&colors) {
+                harness.device.cmd_clear_color_image(
+                    command_buffer,
+                    image,
+                    ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &ash::vk::ClearColorValue { float32: *color },
+                    &[color_range],
+                );
+            }
+            barrier(
+                command_buffer,
+                &old_images,
+                ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            );
+        });
+
+        // Each call runs a single pass, so repeat it until nothing is moved.
+        let mut total_moved = 0;
+        for _ in 0..8 {
+            let mut result = None;
+            harness.submit_and_wait(|command_buffer| {
+                let (context, moved) = allocator
+                    .defragment_images(&images, command_buffer)
+                    .unwrap();
+                let new_images: Vec<_> = moved.iter().map(|&(_, image)| image).collect();
+                barrier(
+                    command_buffer,
+                    &new_images,
+                    ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                );
+                result = Some((context, moved));
+            });
+            let (mut context, moved) = result.unwrap();
+            let stats = allocator.end_image_defragmentation(&mut context).unwrap();
+            assert_eq!(stats.allocations_moved as usize, moved.len());
+            if moved.is_empty() {
+                break;
+            }
+            total_moved += moved.len();
+            for (index, image) in moved {
+                harness.device.destroy_image(images[index].1, None);
+                images[index].1 = image;
+            }
+        }
+        assert!(total_moved > 0);
+
+        // The contents followed the images to their new place.
+        let pixel_size = 4;
+        let (buffer, buffer_allocation, _) = allocator
+            .create_buffer(
+                &ash::vk::BufferCreateInfo::builder()
+                    .size((images.len() * pixel_size) as ash::vk::DeviceSize)
+                    .usage(ash::vk::BufferUsageFlags::TRANSFER_DST),
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuToCpu),
+            )
+            .unwrap();
+        harness.submit_and_wait(|command_buffer| {
+            for (index, &(_, image, _)) in images.iter().enumerate() {
+                harness.device.cmd_copy_image_to_buffer(
+                    command_buffer,
+                    image,
+                    ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    buffer,
+                    &[ash::vk::BufferImageCopy {
+                        buffer_offset: (index * pixel_size) as ash::vk::DeviceSize,
+                        buffer_row_length: 0,
+                        buffer_image_height: 0,
+                        image_subresource: ash::vk::ImageSubresourceLayers {
+                            aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+                            mip_level: 0,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        },
+                        image_offset: ash::vk::Offset3D::default(),
+                        image_extent: ash::vk::Extent3D {
+                            width: 1,
+                            height: 1,
+                            depth: 1,
+                        },
+                    }],
+                );
+            }
+        });
+        let mapped_data = allocator.map_memory(buffer_allocation).unwrap();
+        allocator
+            .invalidate_allocation(buffer_allocation, 0, images.len() * pixel_size)
+            .unwrap();
+        let pixels = std::slice::from_raw_parts(mapped_data, images.len() * pixel_size);
+        for (index, pixel) in pixels.chunks(pixel_size).enumerate() {
+            assert_eq!(pixel, &[(index * 40) as u8, 255, 0, 255]);
+        }
+        allocator.unmap_memory(buffer_allocation);
+
+        allocator.destroy_buffer(buffer, buffer_allocation);
+        for (allocation, image, _) in images {
+            allocator.destroy_image(image, allocation);
+        }
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[test]
+fn defragmentation_context_moved() {
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let memory_type_index = allocator
+        .find_memory_type_index(
+            !0,
+            &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::GpuOnly),
+        )
+        .unwrap();
+    let block_size = 16 * 1024;
+    let requirements = ash::vk::MemoryRequirements {
+        size: 4 * 1024,
+        alignment: 256,
+        memory_type_bits: !0,
+    };
+
+    unsafe {
+        let pool = allocator
+            .create_pool(&vk_mem::AllocatorPoolCreateInfo {
+                memory_type_index,
+                block_size,
+                ..Default::default()
+            })
+            .unwrap();
+        let allocation_info = vk_mem::AllocationCreateInfo {
+            pool: Some(pool),
+            ..Default::default()
+        };
+        // Three full blocks, of which only the first allocation of each is kept.
+        let mut allocations = Vec::new();
+        for index in 0..12 {
+            let (allocation, _) = allocator
+                .allocate_memory(&requirements, &allocation_info)
+                .unwrap();
+            if index % 4 == 0 {
+                allocations.push(allocation);
+            } else {
+                allocator.free_memory(allocation);
+            }
+        }
+        assert_eq!(allocator.get_pool_stats(pool).unwrap().blockCount, 3);
+
+        // A GPU defragmentation only ends after the copies executed, and VMA writes the
+        // statistics of the freed blocks through the pointer it got at the beginning.
+        let mut context = None;
+        harness.submit_and_wait(|command_buffer| {
+            let info = vk_mem::DefragmentationInfo2 {
+                allocations: &allocations,
+                pools: None,
+                max_cpu_bytes_to_move: 0,
+                max_cpu_allocations_to_move: 0,
+                max_gpu_bytes_to_move: ash::vk::WHOLE_SIZE,
+                max_gpu_allocations_to_move: u32::MAX,
+                command_buffer: Some(command_buffer),
+            };
+            context = Some(allocator.defragmentation_begin(&info).unwrap());
+        });
+        // Move the context to another place before ending the defragmentation.
+        let mut context = Box::new(context.unwrap());
+        let (stats, changed) = allocator.defragmentation_end(&mut context).unwrap();
+
+        assert_eq!(
+            changed.iter().filter(|&&changed| changed).count(),
+            stats.allocations_moved as usize
+        );
+        assert_eq!(
+            stats.bytes_moved,
+            stats.allocations_moved as usize * 4 * 1024
+        );
+        assert!(stats.device_memory_blocks_freed > 0);
+        assert_eq!(
+            stats.bytes_freed,
+            stats.device_memory_blocks_freed as usize * block_size
+        );
+        assert_eq!(
+            allocator.get_pool_stats(pool).unwrap().blockCount,
+            3 - stats.device_memory_blocks_freed as usize
+        );
+
+        for allocation in allocations {
+            allocator.free_memory(allocation);
+        }
+        allocator.destroy_pool(pool);
+        allocator.destroy_allocator();
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_events() {
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    // Records the message of every `vk_mem` event.
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    struct MessageVisitor<'a>(&'a mut String);
+
+    impl Visit for MessageVisitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                *self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "vk_mem"
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut message = String::new();
+            event.record(&mut MessageVisitor(&mut message));
+            self.0.lock().unwrap().push(message);
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let harness = TestHarness::new();
+    let allocator = harness.create_allocator();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    tracing::subscriber::with_default(Recorder(events.clone()), || unsafe {
+        let (buffer, allocation, _) = allocator
+            .create_buffer(
+                &ash::vk::BufferCreateInfo::builder()
+                    .size(16 * 1024)
+                    .usage(ash::vk::BufferUsageFlags::TRANSFER_SRC),
+                &vk_mem::AllocationCreateInfo::for_usage(vk_mem::MemoryUsage::CpuOnly),
+            )
+            .unwrap();
+        allocator.map_memory(allocation).unwrap();
+        allocator.unmap_memory(allocation);
+        allocator.destroy_buffer(buffer, allocation);
+    });
+    assert_eq!(
+        *events.lock().unwrap(),
+        ["allocated", "mapped", "unmapped", "freed"]
+    );
+}
+
+#[test]
+#[should_panic(expected = "from inside a callback")]
+fn allocate_from_allocation_failure_callback() {
+    let harness = TestHarness::new();
+    let mut allocator = harness.create_allocator();
+    allocator.set_allocation_failure_callback(Some(Box::new(
+        |allocator, allocation_info, memory_requirements, _| {
+            // Retrying here would fail and invoke the callback again, endlessly.
+            let _ = unsafe { allocator.allocate_memory(memory_requirements, allocation_info) };
+        },
+    )));
+    let memory_requirements = ash::vk::MemoryRequirements {
+        size: 1024,
+        alignment: 256,
+        memory_type_bits: 0,
+    };
+    let _ = unsafe {
+        allocator.allocate_memory(
+            &memory_requirements,
+            &vk_mem::AllocationCreateInfo::default(),
+        )
+    };
+}
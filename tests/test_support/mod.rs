@@ -0,0 +1,346 @@
+//! Headless Vulkan setup shared by the integration tests.
+//!
+//! Creates an instance and a device without any surface, so the tests can run on machines
+//! without a display, e.g. in CI with a software implementation such as lavapipe.
+//!
+//! Set `VK_MEM_TEST_DEVICE` to the index of a physical device (as returned by
+//! `vkEnumeratePhysicalDevices`) to override the automatic selection.
+
+use ash::extensions::ext::DebugReport;
+use ash::vk;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+
+const VALIDATION_LAYER: &[u8] = b"VK_LAYER_KHRONOS_validation\0";
+
+unsafe extern "system" fn vulkan_debug_callback(
+    _: vk::DebugReportFlagsEXT,
+    _: vk::DebugReportObjectTypeEXT,
+    _: u64,
+    _: usize,
+    _: i32,
+    _: *const c_char,
+    p_message: *const c_char,
+    _: *mut c_void,
+) -> u32 {
+    println!("{:?}", CStr::from_ptr(p_message));
+    vk::FALSE
+}
+
+fn has_extension(properties: &[vk::ExtensionProperties], name: &CStr) -> bool {
+    properties
+        .iter()
+        .any(|p| unsafe { CStr::from_ptr(p.extension_name.as_ptr()) } == name)
+}
+
+/// Ranks device types so that real GPUs are preferred over software implementations.
+fn device_type_rank(device_type: vk::PhysicalDeviceType) -> u32 {
+    match device_type {
+        vk::PhysicalDeviceType::DISCRETE_GPU => 0,
+        vk::PhysicalDeviceType::INTEGRATED_GPU => 1,
+        vk::PhysicalDeviceType::VIRTUAL_GPU => 2,
+        vk::PhysicalDeviceType::CPU => 3,
+        _ => 4,
+    }
+}
+
+/// Picks a physical device and the index of its first queue family supporting transfer operations.
+fn select_physical_device(instance: &ash::Instance) -> (vk::PhysicalDevice, u32) {
+    let physical_devices = unsafe {
+        instance
+            .enumerate_physical_devices()
+            .expect("Physical device error")
+    };
+    let with_queue = |physical_device: vk::PhysicalDevice| {
+        unsafe { instance.get_physical_device_queue_family_properties(physical_device) }
+            .iter()
+            .position(|family| {
+                family.queue_count > 0
+                    && family.queue_flags.intersects(
+                        vk::QueueFlags::GRAPHICS
+                            | vk::QueueFlags::COMPUTE
+                            | vk::QueueFlags::TRANSFER,
+                    )
+            })
+            .map(|index| (physical_device, index as u32))
+    };
+
+    if let Ok(index) = std::env::var("VK_MEM_TEST_DEVICE") {
+        let index: usize = index.parse().expect("VK_MEM_TEST_DEVICE must be an index");
+        let physical_device = *physical_devices
+            .get(index)
+            .expect("VK_MEM_TEST_DEVICE is out of range");
+        return with_queue(physical_device).expect("Selected device has no transfer queue.");
+    }
+
+    physical_devices
+        .iter()
+        .filter_map(|&physical_device| with_queue(physical_device))
+        .min_by_key(|&(physical_device, _)| {
+            let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+            device_type_rank(properties.device_type)
+        })
+        .expect("Couldn't find suitable device.")
+}
+
+pub struct TestHarness {
+    // Keeps the Vulkan loader loaded for as long as `instance` and `device` are used.
+    _entry: ash::Entry,
+    pub instance: ash::Instance,
+    pub device: ash::Device,
+    pub physical_device: vk::PhysicalDevice,
+    /// Vulkan version the instance and device may use, without the patch version.
+    pub api_version: u32,
+    /// Queue supporting transfer operations.
+    pub queue: vk::Queue,
+    /// Command pool for `queue`, with resettable command buffers.
+    pub command_pool: vk::CommandPool,
+    /// Allocator flags matching the extensions enabled on `device`.
+    pub allocator_flags: vk_mem::AllocatorCreateFlags,
+    /// Whether `VK_KHR_device_group` is enabled on `device`, allowing
+    /// `ash::vk::MemoryAllocateFlagsInfo` in memory allocations.
+    pub device_group: bool,
+    /// Whether the `sparse_binding` and `sparse_residency_image2_d` features are enabled on
+    /// `device`, allowing sparse 2D images.
+    pub sparse_image_2d: bool,
+    pub debug_report: Option<(DebugReport, vk::DebugReportCallbackEXT)>,
+}
+
+impl Drop for TestHarness {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.device_wait_idle().unwrap();
+            self.device.destroy_command_pool(self.command_pool, None);
+            self.device.destroy_device(None);
+            if let Some((loader, callback)) = self.debug_report.take() {
+                loader.destroy_debug_report_callback(callback, None);
+            }
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+impl TestHarness {
+    pub fn new() -> Self {
+        Self::with_api_version(vk::API_VERSION_1_0)
+    }
+
+    /// Same as `TestHarness::new`, but requests `api_version`, capped at the versions supported
+    /// by the instance and the selected physical device. The resulting version is stored in
+    /// `TestHarness::api_version` and passed to the allocators made by `create_allocator`.
+    pub fn with_api_version(api_version: u32) -> Self {
+        let entry = unsafe { ash::Entry::new().unwrap() };
+        let instance_version = entry
+            .try_enumerate_instance_version()
+            .ok()
+            .flatten()
+            .unwrap_or(vk::API_VERSION_1_0);
+        let api_version = api_version.min(instance_version);
+
+        let app_name = ::std::ffi::CString::new("vk-mem testing").unwrap();
+        let app_info = vk::ApplicationInfo::builder()
+            .application_name(&app_name)
+            .application_version(0)
+            .engine_name(&app_name)
+            .engine_version(0)
+            .api_version(api_version);
+
+        // The validation layer and debug report extension are optional, so that the tests
+        // still run where the SDK is not installed.
+        let validation_layer = CStr::from_bytes_with_nul(VALIDATION_LAYER).unwrap();
+        let layers_names_raw: Vec<*const c_char> = entry
+            .enumerate_instance_layer_properties()
+            .unwrap_or_default()
+            .iter()
+            .filter(|p| unsafe { CStr::from_ptr(p.layer_name.as_ptr()) } == validation_layer)
+            .map(|_| validation_layer.as_ptr())
+            .take(1)
+            .collect();
+
+        let instance_extensions = entry
+            .enumerate_instance_extension_properties()
+            .unwrap_or_default();
+        let has_debug_report = has_extension(&instance_extensions, DebugReport::name());
+        let has_properties2 = has_extension(
+            &instance_extensions,
+            vk::KhrGetPhysicalDeviceProperties2Fn::name(),
+        );
+        let has_device_group_creation =
+            has_extension(&instance_extensions, vk::KhrDeviceGroupCreationFn::name());
+        let mut extension_names_raw = Vec::new();
+        if has_debug_report {
+            extension_names_raw.push(DebugReport::name().as_ptr());
+        }
+        if has_properties2 {
+            extension_names_raw.push(vk::KhrGetPhysicalDeviceProperties2Fn::name().as_ptr());
+        }
+        if has_device_group_creation {
+            extension_names_raw.push(vk::KhrDeviceGroupCreationFn::name().as_ptr());
+        }
+
+        let create_info = vk::InstanceCreateInfo::builder()
+            .application_info(&app_info)
+            .enabled_layer_names(&layers_names_raw)
+            .enabled_extension_names(&extension_names_raw);
+
+        let instance: ash::Instance = unsafe {
+            entry
+                .create_instance(&create_info, None)
+                .expect("Instance creation error")
+        };
+
+        let debug_report = if has_debug_report {
+            let debug_info = vk::DebugReportCallbackCreateInfoEXT::builder()
+                .flags(
+                    vk::DebugReportFlagsEXT::ERROR
+                        | vk::DebugReportFlagsEXT::WARNING
+                        | vk::DebugReportFlagsEXT::PERFORMANCE_WARNING,
+                )
+                .pfn_callback(Some(vulkan_debug_callback));
+            let loader = DebugReport::new(&entry, &instance);
+            let callback = unsafe {
+                loader
+                    .create_debug_report_callback(&debug_info, None)
+                    .unwrap()
+            };
+            Some((loader, callback))
+        } else {
+            None
+        };
+
+        let (physical_device, queue_family_index) = select_physical_device(&instance);
+        let device_version =
+            unsafe { instance.get_physical_device_properties(physical_device) }.api_version;
+        let api_version = vk::make_api_version(
+            0,
+            vk::api_version_major(api_version.min(device_version)),
+            vk::api_version_minor(api_version.min(device_version)),
+            0,
+        );
+
+        // Enable the optional extensions VMA can make use of, and the matching allocator flags.
+        let device_extensions = unsafe {
+            instance
+                .enumerate_device_extension_properties(physical_device)
+                .unwrap_or_default()
+        };
+        let mut device_extension_names_raw = Vec::new();
+        let mut allocator_flags = vk_mem::AllocatorCreateFlags::NONE;
+        if has_extension(&device_extensions, vk::KhrGetMemoryRequirements2Fn::name())
+            && has_extension(&device_extensions, vk::KhrDedicatedAllocationFn::name())
+        {
+            device_extension_names_raw.push(vk::KhrGetMemoryRequirements2Fn::name().as_ptr());
+            device_extension_names_raw.push(vk::KhrDedicatedAllocationFn::name().as_ptr());
+            allocator_flags |= vk_mem::AllocatorCreateFlags::KHR_DEDICATED_ALLOCATION;
+        }
+        if has_properties2 && has_extension(&device_extensions, vk::ExtMemoryBudgetFn::name()) {
+            device_extension_names_raw.push(vk::ExtMemoryBudgetFn::name().as_ptr());
+            allocator_flags |= vk_mem::AllocatorCreateFlags::EXT_MEMORY_BUDGET;
+        }
+
+        let device_group = has_device_group_creation
+            && has_extension(&device_extensions, vk::KhrDeviceGroupFn::name());
+        if device_group {
+            device_extension_names_raw.push(vk::KhrDeviceGroupFn::name().as_ptr());
+        }
+
+        let supported_features = unsafe { instance.get_physical_device_features(physical_device) };
+        let sparse_image_2d = supported_features.sparse_binding == vk::TRUE
+            && supported_features.sparse_residency_image2_d == vk::TRUE;
+        let features = vk::PhysicalDeviceFeatures {
+            sparse_binding: supported_features.sparse_binding,
+            sparse_residency_image2_d: supported_features.sparse_residency_image2_d,
+            ..Default::default()
+        };
+
+        let priorities = [1.0];
+
+        let queue_info = [vk::DeviceQueueCreateInfo::builder()
+            .queue_family_index(queue_family_index)
+            .queue_priorities(&priorities)
+            .build()];
+
+        let device_create_info = vk::DeviceCreateInfo::builder()
+            .queue_create_infos(&queue_info)
+            .enabled_extension_names(&device_extension_names_raw)
+            .enabled_features(&features);
+
+        let device: ash::Device = unsafe {
+            instance
+                .create_device(physical_device, &device_create_info, None)
+                .unwrap()
+        };
+
+        let queue = unsafe { device.get_device_queue(queue_family_index, 0) };
+        let command_pool_info = vk::CommandPoolCreateInfo::builder()
+            .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+            .queue_family_index(queue_family_index);
+        let command_pool = unsafe {
+            device
+                .create_command_pool(&command_pool_info, None)
+                .unwrap()
+        };
+
+        TestHarness {
+            _entry: entry,
+            instance,
+            device,
+            physical_device,
+            api_version,
+            queue,
+            command_pool,
+            allocator_flags,
+            device_group,
+            sparse_image_2d,
+            debug_report,
+        }
+    }
+
+    pub fn create_allocator(&self) -> vk_mem::Allocator {
+        let create_info = vk_mem::AllocatorCreateInfo::builder(
+            &self.instance,
+            &self.device,
+            self.physical_device,
+        )
+        .flags(self.allocator_flags)
+        .vulkan_api_version(self.api_version)
+        .build();
+        unsafe { vk_mem::Allocator::new(&create_info).unwrap() }
+    }
+
+    pub fn allocate_command_buffer(&self) -> vk::CommandBuffer {
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(self.command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        unsafe {
+            self.device
+                .allocate_command_buffers(&allocate_info)
+                .unwrap()[0]
+        }
+    }
+
+    /// Records commands with `record` into a new command buffer, submits it to `queue` and
+    /// waits for it to finish executing.
+    pub fn submit_and_wait(&self, record: impl FnOnce(vk::CommandBuffer)) {
+        let command_buffer = self.allocate_command_buffer();
+        let command_buffers = [command_buffer];
+        unsafe {
+            let begin_info = vk::CommandBufferBeginInfo::builder()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            self.device
+                .begin_command_buffer(command_buffer, &begin_info)
+                .unwrap();
+            record(command_buffer);
+            self.device.end_command_buffer(command_buffer).unwrap();
+
+            let submit_info = vk::SubmitInfo::builder().command_buffers(&command_buffers);
+            self.device
+                .queue_submit(self.queue, &[submit_info.build()], vk::Fence::null())
+                .unwrap();
+            self.device.queue_wait_idle(self.queue).unwrap();
+            self.device
+                .free_command_buffers(self.command_pool, &command_buffers);
+        }
+    }
+}